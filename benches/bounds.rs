@@ -0,0 +1,150 @@
+//! Benchmarks of the bounding box tests that let rays skip objects.
+//!
+//! Every measurement is compared with the plain loop it replaces: `Boxes4::intersect` with
+//! `Aabb::intersect` on every box, and `World::intersect` with intersecting every object.
+
+#![allow(clippy::needless_return)]
+
+use sorb_rust::geometry::{Aabb, Boxes4};
+use sorb_rust::prelude::*;
+use sorb_rust::utils::{Float, Rng};
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Boxes tested by every ray, a multiple of four.
+const BOX_COUNT: usize = 1024;
+/// Spheres in the world of the intersection benchmark.
+const SPHERE_COUNT: usize = 200;
+/// Rays per measurement.
+const RAY_COUNT: usize = 2_000;
+/// Measurements of every benchmark.
+const ITERATIONS: u32 = 5;
+
+/// Returns the time of the fastest of `iterations` calls of `f` in nanoseconds, which is the
+/// least disturbed by other work on the machine.
+fn time<R>(iterations: u32, mut f: impl FnMut() -> R) -> f64 {
+  return (0..iterations)
+    .map(|_| {
+      let start = Instant::now();
+      black_box(f());
+      return start.elapsed().as_nanos() as f64;
+    })
+    .fold(f64::INFINITY, f64::min);
+}
+
+fn compare(name: &str, count: usize, baseline: f64, measured: f64) {
+  println!(
+    "{:<18} baseline {:>8.2} ns   measured {:>8.2} ns   speedup {:.2}x",
+    name,
+    baseline / count as f64,
+    measured / count as f64,
+    baseline / measured
+  );
+}
+
+fn random_point(rng: &mut Rng, size: Float) -> Point {
+  return Point::new(
+    (rng.next_float() - 0.5) * size,
+    (rng.next_float() - 0.5) * size,
+    (rng.next_float() - 0.5) * size,
+  );
+}
+
+fn random_rays(rng: &mut Rng) -> Vec<Ray> {
+  return (0..RAY_COUNT)
+    .map(|_| {
+      let direction = random_point(rng, 2.) - Point::origin();
+      return Ray::new(random_point(rng, 20.), direction.normalize());
+    })
+    .collect();
+}
+
+fn bench_boxes() {
+  let mut rng = Rng::new(1);
+  let boxes: Vec<Aabb> = (0..BOX_COUNT)
+    .map(|_| {
+      let corner = random_point(&mut rng, 20.);
+      return Aabb::new(
+        corner,
+        corner + (random_point(&mut rng, 2.) - Point::origin()),
+      );
+    })
+    .collect();
+  let groups: Vec<Boxes4> = boxes
+    .chunks(4)
+    .map(|chunk| {
+      let mut group = Boxes4::EMPTY;
+      for (index, bounds) in chunk.iter().enumerate() {
+        group.set(index, bounds);
+      }
+      return group;
+    })
+    .collect();
+  let rays = random_rays(&mut rng);
+
+  let baseline = time(ITERATIONS, || {
+    let mut hits = 0;
+    for ray in &rays {
+      for bounds in &boxes {
+        if let Some((near, far)) = bounds.intersect(ray) {
+          hits += (near.max(0.) <= far) as usize;
+        }
+      }
+    }
+    return hits;
+  });
+  let measured = time(ITERATIONS, || {
+    let mut hits = 0;
+    for ray in &rays {
+      let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+      let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+      let inverse_direction = direction.map(|component| 1. / component);
+      for group in &groups {
+        let entries = group.intersect(&origin, &inverse_direction, 0., Float::INFINITY);
+        hits += entries.iter().filter(|entry| entry.is_finite()).count();
+      }
+    }
+    return hits;
+  });
+  compare("box test", BOX_COUNT * RAY_COUNT, baseline, measured);
+}
+
+fn bench_world() {
+  let mut rng = Rng::new(2);
+  let mut world = World::default();
+  for _ in 0..SPHERE_COUNT {
+    let center = random_point(&mut rng, 20.);
+    world.add_object(Box::new(Sphere::new(
+      Material::default(),
+      Matrix::identity()
+        .scale(0.5, 0.5, 0.5)
+        .translate(center.x, center.y, center.z),
+    )));
+  }
+  let rays = random_rays(&mut rng);
+
+  let baseline = time(ITERATIONS, || {
+    return rays
+      .iter()
+      .map(|ray| {
+        return world
+          .objects()
+          .iter()
+          .map(|object| object.intersect(ray).len())
+          .sum::<usize>();
+      })
+      .sum::<usize>();
+  });
+  let measured = time(ITERATIONS, || {
+    return rays
+      .iter()
+      .map(|ray| world.intersect(ray).len())
+      .sum::<usize>();
+  });
+  compare("world intersect", RAY_COUNT, baseline, measured);
+}
+
+fn main() {
+  bench_boxes();
+  bench_world();
+}
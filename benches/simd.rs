@@ -0,0 +1,191 @@
+//! Benchmarks of the kernels in `sorb_rust::simd`, and of renders of the sphere and plane scenes
+//! that use them.
+//!
+//! Every kernel is timed in its portable version and in the version the crate uses, which is
+//! vectorized where the target supports it.
+//!
+//! Renders always use the version the crate uses, so their baseline comes from a separate run:
+//! `cargo bench --bench simd --features no-simd` saves the render times of the portable kernels,
+//! and a later `cargo bench --bench simd` prints the speedup of every scene over them. Add
+//! `--features f32` to both to compare single precision.
+
+#![allow(clippy::needless_return)]
+
+use sorb_rust::geometry::{Aabb, Boxes4};
+use sorb_rust::prelude::*;
+use sorb_rust::simd::{self, scalar};
+use sorb_rust::utils::Float;
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Calls of each kernel per measurement.
+const KERNEL_ITERATIONS: u32 = 2_000_000;
+/// Measurements of each kernel and scene.
+const MEASUREMENTS: u32 = 5;
+
+/// Returns the time of the fastest of several measurements of `iterations` calls of `f`, in
+/// nanoseconds per call. The fastest is the least disturbed by other work on the machine.
+fn time<R>(iterations: u32, mut f: impl FnMut() -> R) -> f64 {
+  black_box(f());
+  return (0..MEASUREMENTS)
+    .map(|_| {
+      let start = Instant::now();
+      for _ in 0..iterations {
+        black_box(f());
+      }
+      return start.elapsed().as_nanos() as f64 / iterations as f64;
+    })
+    .fold(f64::INFINITY, f64::min);
+}
+
+fn compare<R>(name: &str, portable: impl FnMut() -> R, vectorized: impl FnMut() -> R) {
+  let portable = time(KERNEL_ITERATIONS, portable);
+  let vectorized = time(KERNEL_ITERATIONS, vectorized);
+  println!(
+    "{:<18} portable {:>8.2} ns   in use {:>8.2} ns   speedup {:.2}x",
+    name,
+    portable,
+    vectorized,
+    portable / vectorized
+  );
+}
+
+fn bench_kernels() {
+  let a: [Float; 3] = [1.5, -2.25, 3.];
+  let b: [Float; 3] = [-0.1, 7., 0.3];
+  compare(
+    "dot3",
+    || scalar::dot3(black_box(a), black_box(b)),
+    || simd::dot3(black_box(a), black_box(b)),
+  );
+  compare(
+    "cross3",
+    || scalar::cross3(black_box(a), black_box(b)),
+    || simd::cross3(black_box(a), black_box(b)),
+  );
+
+  let matrix = *Matrix::identity()
+    .rotate_x(0.5)
+    .scale(2., 3., 4.)
+    .translate(1., -2., 3.)
+    .elements();
+  compare(
+    "mat4_mul",
+    || scalar::mat4_mul(black_box(&matrix), black_box(&matrix)),
+    || simd::mat4_mul(black_box(&matrix), black_box(&matrix)),
+  );
+  compare(
+    "mat4_inverse",
+    || scalar::mat4_inverse(black_box(&matrix)),
+    || simd::mat4_inverse(black_box(&matrix)),
+  );
+
+  let mut boxes = Boxes4::EMPTY;
+  let corners = [
+    ([-1., -1., 4.], [1., 1., 6.]),
+    ([2., 2., 4.], [3., 3., 6.]),
+    ([-1., -1., -1.], [1., 1., 1.]),
+    ([-5., 0., 2.], [0., 5., 3.]),
+  ];
+  for (index, (min, max)) in corners.iter().enumerate() {
+    let bounds = Aabb::new(
+      Point::new(min[0], min[1], min[2]),
+      Point::new(max[0], max[1], max[2]),
+    );
+    boxes.set(index, &bounds);
+  }
+  let origin = [0.1, 0.2, -3.];
+  let inverse_direction = [10., 5., 1.];
+  compare(
+    "intersect_boxes4",
+    || {
+      scalar::intersect_boxes4(
+        black_box(&origin),
+        &inverse_direction,
+        black_box(&boxes),
+        0.,
+        Float::INFINITY,
+      )
+    },
+    || {
+      simd::intersect_boxes4(
+        black_box(&origin),
+        &inverse_direction,
+        black_box(&boxes),
+        0.,
+        Float::INFINITY,
+      )
+    },
+  );
+}
+
+/// Returns the file holding the render times of the portable kernels, for the precision of the
+/// build.
+fn baseline_path() -> PathBuf {
+  let precision = if cfg!(feature = "f32") { "f32" } else { "f64" };
+  return Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("simd-baseline-{}.txt", precision));
+}
+
+/// Reads the render times saved by a run with the `no-simd` feature, as lines of a scene name and
+/// a time in milliseconds.
+fn read_baseline() -> Vec<(String, f64)> {
+  let text = fs::read_to_string(baseline_path()).unwrap_or_default();
+  return text
+    .lines()
+    .filter_map(|line| {
+      let (name, time) = line.split_once(' ')?;
+      return Some((name.to_string(), time.parse().ok()?));
+    })
+    .collect();
+}
+
+fn bench_scenes() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let scenes = [
+    ("spheres", "tests/golden/shading.scene"),
+    ("patterns", "tests/golden/patterns.scene"),
+    ("corner", "scenes/corner.scene"),
+  ];
+  let baseline = read_baseline();
+  let mut times = Vec::new();
+  for (name, path) in scenes.iter() {
+    let (camera, world) = Scene::open(root.join(path)).unwrap().into_parts();
+    let camera = Camera::new(320, 240, camera.fov(), *camera.camera_to_world());
+    let render = time(1, || camera.render(&world)) / 1e6;
+    times.push(format!("{} {}\n", name, render));
+
+    let portable = baseline.iter().find(|(scene, _)| scene == name);
+    match portable {
+      Some((_, portable)) if simd::VECTORIZED => println!(
+        "render {:<11} portable {:>8.2} ms   in use {:>8.2} ms   speedup {:.2}x",
+        name,
+        portable,
+        render,
+        portable / render
+      ),
+      _ => println!("render {:<11} {:>8.2} ms", name, render),
+    }
+  }
+
+  if !simd::VECTORIZED {
+    fs::write(baseline_path(), times.concat()).unwrap();
+    println!("Saved the portable render times as the baseline.");
+  } else if baseline.is_empty() {
+    println!("Run with `--features no-simd` first to compare renders with the portable kernels.");
+  }
+}
+
+fn main() {
+  println!(
+    "kernels in use: {}",
+    if simd::VECTORIZED {
+      "vectorized"
+    } else {
+      "portable"
+    }
+  );
+  bench_kernels();
+  bench_scenes();
+}
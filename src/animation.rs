@@ -0,0 +1,548 @@
+//! Keyframe animation of cameras, objects and lights, and rendering of image sequences.
+//!
+//! Every animated property is a [`Track`] of keyframes. An [`Animation`] collects the tracks of a
+//! camera and of objects and lights of a [`World`], and [`Animation::write_frames`] renders a
+//! range of frames to numbered files. [`Animation::render_frames`] passes each frame to a callback
+//! instead, to handle it some other way.
+//!
+//! ```
+//! use sorb_rust::animation::{frame_path, Animation, CameraAnimation, Interpolation, Track};
+//! use sorb_rust::formats::OutputFormat;
+//! use sorb_rust::prelude::*;
+//!
+//! let mut world = World::default();
+//! world.add_light(PointLight::new(Point::new(-5., 5., 5.), Color::white()));
+//! world.add_object(Box::new(Sphere::default()));
+//! let mut camera = Camera::new(16, 12, 60., Matrix::identity());
+//!
+//! let from = Track::new()
+//!   .with_key(0., Point::new(0., 0., 5.), Interpolation::Cubic)
+//!   .with_key(1., Point::new(5., 0., 0.), Interpolation::Cubic);
+//! let animation = Animation::new().with_camera(CameraAnimation::look_at(
+//!   from,
+//!   Track::constant(Point::origin()),
+//!   Track::constant(Vector::new(0., 1., 0.)),
+//! ));
+//!
+//! let pattern = std::env::temp_dir().join("sorb-orbit-###.png");
+//! animation
+//!   .write_frames(
+//!     &mut camera,
+//!     &mut world,
+//!     0..=2,
+//!     1,
+//!     &pattern,
+//!     OutputFormat::Png,
+//!     &DisplayTransform::default(),
+//!   )
+//!   .unwrap();
+//! assert!(frame_path(&pattern, 2).ends_with("sorb-orbit-002.png"));
+//! assert!(frame_path(&pattern, 2).exists());
+//! ```
+
+mod track;
+pub use track::*;
+
+use crate::formats::{FormatError, OutputFormat};
+use crate::geometry::{Matrix, Point, Vector};
+use crate::render::{
+  Camera, Canvas, DisplayTransform, LightHandle, ObjectHandle, PointLight, World,
+};
+use crate::shapes::Shape;
+use crate::utils::Float;
+use crate::Color;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Frames per second of an [`Animation`] unless set otherwise.
+pub const DEFAULT_FRAME_RATE: Float = 24.;
+
+/// Moves a camera, either to look from one point at another, both following tracks, or along a
+/// track of its transformation.
+#[derive(Clone, Debug)]
+pub struct CameraAnimation {
+  motion: CameraMotion,
+}
+#[derive(Clone, Debug)]
+enum CameraMotion {
+  LookAt {
+    from: Track<Point>,
+    to: Track<Point>,
+    up: Track<Vector>,
+  },
+  Transform(Track<Matrix>),
+}
+impl CameraAnimation {
+  /// See [`Matrix::look_at`].
+  pub fn look_at(from: Track<Point>, to: Track<Point>, up: Track<Vector>) -> Self {
+    return Self {
+      motion: CameraMotion::LookAt { from, to, up },
+    };
+  }
+
+  /// Animates the camera's camera-to-world transformation.
+  pub fn transform(camera_to_world: Track<Matrix>) -> Self {
+    return Self {
+      motion: CameraMotion::Transform(camera_to_world),
+    };
+  }
+
+  /// Returns the camera's transformation at `time`, or `None` if a track has no keyframes.
+  pub fn camera_to_world(&self, time: Float) -> Option<Matrix> {
+    return match &self.motion {
+      CameraMotion::LookAt { from, to, up } => Some(Matrix::look_at(
+        &from.sample(time)?,
+        &to.sample(time)?,
+        &up.sample(time)?,
+      )),
+      CameraMotion::Transform(camera_to_world) => camera_to_world.sample(time),
+    };
+  }
+
+  pub fn apply(&self, time: Float, camera: &mut Camera) {
+    if let Some(camera_to_world) = self.camera_to_world(time) {
+      camera.set_camera_to_world(camera_to_world);
+    }
+  }
+}
+
+/// Tracks of the transformation and material parameters of an object. Properties without a track
+/// are left alone.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectAnimation {
+  transform: Option<Track<Matrix>>,
+  ambience: Option<Track<Float>>,
+  diffuse: Option<Track<Float>>,
+  specular: Option<Track<Float>>,
+  shininess: Option<Track<Float>>,
+}
+impl ObjectAnimation {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  /// Animates the object's object-to-world transformation.
+  pub fn with_transform(mut self, transform: Track<Matrix>) -> Self {
+    self.transform = Some(transform);
+    return self;
+  }
+  pub fn with_ambience(mut self, ambience: Track<Float>) -> Self {
+    self.ambience = Some(ambience);
+    return self;
+  }
+  pub fn with_diffuse(mut self, diffuse: Track<Float>) -> Self {
+    self.diffuse = Some(diffuse);
+    return self;
+  }
+  pub fn with_specular(mut self, specular: Track<Float>) -> Self {
+    self.specular = Some(specular);
+    return self;
+  }
+  pub fn with_shininess(mut self, shininess: Track<Float>) -> Self {
+    self.shininess = Some(shininess);
+    return self;
+  }
+
+  pub fn apply(&self, time: Float, object: &mut dyn Shape) {
+    let sample = |track: &Option<Track<Float>>| {
+      return track.as_ref().and_then(|track| track.sample(time));
+    };
+    if let Some(transform) = self.transform.as_ref().and_then(|track| track.sample(time)) {
+      object.set_object_to_world(transform);
+    }
+    let material = object.material_mut();
+    if let Some(ambience) = sample(&self.ambience) {
+      material.set_ambience(ambience);
+    }
+    if let Some(diffuse) = sample(&self.diffuse) {
+      material.set_diffuse(diffuse);
+    }
+    if let Some(specular) = sample(&self.specular) {
+      material.set_specular(specular);
+    }
+    if let Some(shininess) = sample(&self.shininess) {
+      material.set_shininess(shininess);
+    }
+  }
+}
+
+/// Tracks of the position and color of a light. Properties without a track are left alone.
+#[derive(Clone, Debug, Default)]
+pub struct LightAnimation {
+  position: Option<Track<Point>>,
+  color: Option<Track<Color>>,
+}
+impl LightAnimation {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  pub fn with_position(mut self, position: Track<Point>) -> Self {
+    self.position = Some(position);
+    return self;
+  }
+  pub fn with_color(mut self, color: Track<Color>) -> Self {
+    self.color = Some(color);
+    return self;
+  }
+
+  pub fn apply(&self, time: Float, light: &mut PointLight) {
+    if let Some(position) = self.position.as_ref().and_then(|track| track.sample(time)) {
+      light.set_position(position);
+    }
+    if let Some(color) = self.color.as_ref().and_then(|track| track.sample(time)) {
+      light.set_color(color);
+    }
+  }
+}
+
+/// Animation of a camera and of objects and lights of a [`World`], referred to by their handles.
+#[derive(Clone, Debug)]
+pub struct Animation {
+  frame_rate: Float,
+  camera: Option<CameraAnimation>,
+  objects: Vec<(ObjectHandle, ObjectAnimation)>,
+  lights: Vec<(LightHandle, LightAnimation)>,
+}
+impl Animation {
+  pub fn new() -> Self {
+    return Self {
+      frame_rate: DEFAULT_FRAME_RATE,
+      camera: None,
+      objects: Vec::new(),
+      lights: Vec::new(),
+    };
+  }
+
+  /// Sets the number of frames per second. Keyframe times are in seconds.
+  pub fn with_frame_rate(mut self, frame_rate: Float) -> Self {
+    self.frame_rate = frame_rate;
+    return self;
+  }
+  pub fn with_camera(mut self, camera: CameraAnimation) -> Self {
+    self.camera = Some(camera);
+    return self;
+  }
+  pub fn with_object(mut self, handle: ObjectHandle, animation: ObjectAnimation) -> Self {
+    self.objects.push((handle, animation));
+    return self;
+  }
+  pub fn with_light(mut self, handle: LightHandle, animation: LightAnimation) -> Self {
+    self.lights.push((handle, animation));
+    return self;
+  }
+
+  pub fn frame_rate(&self) -> Float {
+    return self.frame_rate;
+  }
+  /// Returns the time in seconds at which a frame is shown.
+  pub fn frame_time(&self, frame: usize) -> Float {
+    return frame as Float / self.frame_rate;
+  }
+
+  /// Sets every animated property to its value at `time`. Objects and lights no longer in the
+  /// world are skipped.
+  pub fn apply(&self, time: Float, camera: &mut Camera, world: &mut World) {
+    if let Some(animation) = &self.camera {
+      animation.apply(time, camera);
+    }
+    for (handle, animation) in &self.objects {
+      if let Some(object) = world.object_mut(*handle) {
+        animation.apply(time, object);
+      }
+    }
+    for (handle, animation) in &self.lights {
+      if let Some(light) = world.light_mut(*handle) {
+        animation.apply(time, light);
+      }
+    }
+  }
+
+  /// Renders the frames in `frames` with `threads` threads, in order, and passes each to
+  /// `on_frame` with its number. Stops at the first error returned by `on_frame`.
+  ///
+  /// The camera and world are left as they are in the last rendered frame.
+  pub fn render_frames<F, E>(
+    &self,
+    camera: &mut Camera,
+    world: &mut World,
+    frames: RangeInclusive<usize>,
+    threads: usize,
+    mut on_frame: F,
+  ) -> Result<(), E>
+  where
+    F: FnMut(usize, &Canvas) -> Result<(), E>,
+  {
+    for frame in frames {
+      self.apply(self.frame_time(frame), camera, world);
+      let canvas = camera.render_observed(world, threads, &()).into_canvas();
+      on_frame(frame, &canvas)?;
+    }
+    return Ok(());
+  }
+
+  /// Renders the frames in `frames` like [`Animation::render_frames`] and writes each in `format`
+  /// to the path [`frame_path`] makes from `pattern`, converting colors with `display` for formats
+  /// limited to `0.0..=1.0`. Stops at the first frame that cannot be written.
+  #[allow(clippy::too_many_arguments)]
+  pub fn write_frames(
+    &self,
+    camera: &mut Camera,
+    world: &mut World,
+    frames: RangeInclusive<usize>,
+    threads: usize,
+    pattern: &Path,
+    format: OutputFormat,
+    display: &DisplayTransform,
+  ) -> Result<(), FormatError> {
+    return self.render_frames(camera, world, frames, threads, |frame, canvas| {
+      return format.write(canvas, &frame_path(pattern, frame), display);
+    });
+  }
+}
+impl Default for Animation {
+  fn default() -> Self {
+    return Self::new();
+  }
+}
+
+/// Returns the path of a frame of an image sequence.
+///
+/// The last run of `#` in the file name of `pattern` is replaced by the frame number, padded with
+/// zeros to the length of the run. Without a `#`, the number is padded to four digits and added
+/// before the extension, after an underscore.
+pub fn frame_path(pattern: &Path, frame: usize) -> PathBuf {
+  let file_name = match pattern.file_name() {
+    Some(file_name) => file_name.to_string_lossy().into_owned(),
+    None => return pattern.join(format!("{:04}", frame)),
+  };
+  let file_name = match file_name.rfind('#') {
+    Some(end) => {
+      let start = file_name[..end].trim_end_matches('#').len();
+      let width = end + 1 - start;
+      format!(
+        "{}{:0width$}{}",
+        &file_name[..start],
+        frame,
+        &file_name[end + 1..],
+        width = width
+      )
+    }
+    None => match (pattern.file_stem(), pattern.extension()) {
+      (Some(stem), Some(extension)) => format!(
+        "{}_{:04}.{}",
+        stem.to_string_lossy(),
+        frame,
+        extension.to_string_lossy()
+      ),
+      _ => format!("{}_{:04}", file_name, frame),
+    },
+  };
+  return pattern.with_file_name(file_name);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::formats::read_image;
+  use crate::geometry::Material;
+  use crate::shapes::Sphere;
+  use crate::utils::consts::PI;
+  use std::fs;
+
+  fn scene() -> (Camera, World, ObjectHandle, LightHandle) {
+    let mut world = World::default();
+    let light = world.add_light(PointLight::default());
+    let object = world.add_object(Box::new(Sphere::default()));
+    return (
+      Camera::new(4, 3, 60., Matrix::identity()),
+      world,
+      object,
+      light,
+    );
+  }
+
+  #[test]
+  fn camera_animation_looks_at_target() {
+    let animation = CameraAnimation::look_at(
+      Track::new()
+        .with_key(0., Point::new(0., 0., 5.), Interpolation::Linear)
+        .with_key(2., Point::new(0., 0., 1.), Interpolation::Linear),
+      Track::constant(Point::origin()),
+      Track::constant(Vector::new(0., 1., 0.)),
+    );
+    assert_eq!(
+      animation.camera_to_world(1.),
+      Some(Matrix::look_at(
+        &Point::new(0., 0., 3.),
+        &Point::origin(),
+        &Vector::new(0., 1., 0.)
+      ))
+    );
+    let missing = CameraAnimation::look_at(
+      Track::new(),
+      Track::constant(Point::origin()),
+      Track::constant(Vector::new(0., 1., 0.)),
+    );
+    assert_eq!(missing.camera_to_world(1.), None);
+  }
+
+  #[test]
+  fn camera_animation_follows_transform() {
+    let animation = CameraAnimation::transform(
+      Track::new()
+        .with_key(0., Matrix::identity(), Interpolation::Linear)
+        .with_key(
+          1.,
+          Matrix::identity().translate(0., 0., 4.),
+          Interpolation::Linear,
+        ),
+    );
+    assert_eq!(
+      animation.camera_to_world(0.5),
+      Some(Matrix::identity().translate(0., 0., 2.))
+    );
+    assert_eq!(
+      CameraAnimation::transform(Track::new()).camera_to_world(0.),
+      None
+    );
+  }
+
+  #[test]
+  fn apply_sets_object_and_light_properties() {
+    let (mut camera, mut world, object, light) = scene();
+    let animation = Animation::new()
+      .with_object(
+        object,
+        ObjectAnimation::new()
+          .with_transform(
+            Track::new()
+              .with_key(0., Matrix::identity(), Interpolation::Linear)
+              .with_key(
+                1.,
+                Matrix::identity().rotate_y(PI / 2.),
+                Interpolation::Linear,
+              ),
+          )
+          .with_diffuse(Track::constant(0.25)),
+      )
+      .with_light(
+        light,
+        LightAnimation::new()
+          .with_position(Track::constant(Point::new(1., 2., 3.)))
+          .with_color(
+            Track::new()
+              .with_key(0., Color::black(), Interpolation::Step)
+              .with_key(1., Color::white(), Interpolation::Step),
+          ),
+      );
+    animation.apply(0.5, &mut camera, &mut world);
+
+    let object = world.object(object).unwrap();
+    assert_eq!(
+      object.object_to_world(),
+      &Matrix::identity().rotate_y(PI / 4.)
+    );
+    assert_eq!(object.material().diffuse(), 0.25);
+    assert_eq!(object.material().ambience(), Material::default().ambience());
+    let light = world.light(light).unwrap();
+    assert_eq!(light.position(), &Point::new(1., 2., 3.));
+    assert_eq!(light.color(), &Color::black());
+    assert_eq!(camera.camera_to_world(), &Matrix::identity());
+  }
+
+  #[test]
+  fn apply_skips_removed_objects() {
+    let (mut camera, mut world, object, _) = scene();
+    world.remove_object(object);
+    let animation = Animation::new().with_object(
+      object,
+      ObjectAnimation::new().with_ambience(Track::constant(1.)),
+    );
+    animation.apply(0., &mut camera, &mut world);
+    assert!(world.objects().is_empty());
+  }
+
+  #[test]
+  fn render_frames_in_order() {
+    let (mut camera, mut world, _, light) = scene();
+    let animation = Animation::new().with_frame_rate(2.).with_light(
+      light,
+      LightAnimation::new().with_color(
+        Track::new()
+          .with_key(0., Color::black(), Interpolation::Linear)
+          .with_key(1., Color::white(), Interpolation::Linear),
+      ),
+    );
+    let mut frames = Vec::new();
+    animation
+      .render_frames(&mut camera, &mut world, 1..=3, 2, |frame, canvas| {
+        frames.push((frame, canvas.width()));
+        return Ok::<(), ()>(());
+      })
+      .unwrap();
+    assert_eq!(frames, vec![(1, 4), (2, 4), (3, 4)]);
+    assert_eq!(world.light(light).unwrap().color(), &Color::white());
+  }
+
+  #[test]
+  fn render_frames_stops_at_first_error() {
+    let (mut camera, mut world, _, _) = scene();
+    let mut rendered = 0;
+    let result = Animation::new().render_frames(&mut camera, &mut world, 0..=9, 1, |frame, _| {
+      rendered += 1;
+      return if frame == 1 { Err(frame) } else { Ok(()) };
+    });
+    assert_eq!(result, Err(1));
+    assert_eq!(rendered, 2);
+  }
+
+  #[test]
+  fn write_frames_to_numbered_files() {
+    let (mut camera, mut world, _, _) = scene();
+    let directory = std::env::temp_dir().join(format!("sorb-frames-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let pattern = directory.join("frame-#.pfm");
+    Animation::new()
+      .write_frames(
+        &mut camera,
+        &mut world,
+        1..=2,
+        1,
+        &pattern,
+        OutputFormat::Pfm,
+        &DisplayTransform::default(),
+      )
+      .unwrap();
+    assert!(!directory.join("frame-0.pfm").exists());
+    for frame in 1..=2 {
+      let data = fs::read(directory.join(format!("frame-{}.pfm", frame))).unwrap();
+      assert_eq!(read_image(&data).unwrap().width(), 4);
+    }
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn frame_path_replaces_hashes() {
+    assert_eq!(
+      frame_path(Path::new("out/frame-###.png"), 7),
+      Path::new("out/frame-007.png")
+    );
+    assert_eq!(frame_path(Path::new("#-#.ppm"), 12), Path::new("#-12.ppm"));
+    assert_eq!(
+      frame_path(Path::new("out/f##.exr"), 1234),
+      Path::new("out/f1234.exr")
+    );
+    assert_eq!(frame_path(Path::new("é##.pfm"), 3), Path::new("é03.pfm"));
+    assert_eq!(frame_path(Path::new("プ#.png"), 5), Path::new("プ5.png"));
+  }
+
+  #[test]
+  fn frame_path_without_hashes() {
+    assert_eq!(
+      frame_path(Path::new("out/frame.png"), 7),
+      Path::new("out/frame_0007.png")
+    );
+    assert_eq!(frame_path(Path::new("frame"), 7), Path::new("frame_0007"));
+  }
+}
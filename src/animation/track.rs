@@ -0,0 +1,232 @@
+use crate::utils::{Float, Lerp};
+
+/// How a [`Track`] changes from a keyframe to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+  /// Holds the value of the keyframe until the next one.
+  Step,
+  /// Changes at a constant rate.
+  #[default]
+  Linear,
+  /// Follows a Catmull-Rom spline through the neighbouring keyframes, so the rate changes
+  /// smoothly across keyframes.
+  Cubic,
+}
+
+/// Value of a [`Track`] at a point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe<T> {
+  pub time: Float,
+  pub value: T,
+  /// Interpolation from this keyframe to the next.
+  pub interpolation: Interpolation,
+}
+
+/// Value that changes over time, given by keyframes.
+///
+/// Before the first keyframe and after the last, the track holds their values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+  keys: Vec<Keyframe<T>>,
+}
+impl<T> Track<T>
+where
+  T: Lerp<Scalar = Float> + Clone,
+{
+  pub fn new() -> Self {
+    return Self { keys: Vec::new() };
+  }
+
+  /// Creates a track holding a single value.
+  pub fn constant(value: T) -> Self {
+    return Self::new().with_key(0., value, Interpolation::Step);
+  }
+
+  pub fn with_key(mut self, time: Float, value: T, interpolation: Interpolation) -> Self {
+    self.add_key(time, value, interpolation);
+    return self;
+  }
+
+  /// Adds a keyframe, replacing any keyframe at the same time.
+  pub fn add_key(&mut self, time: Float, value: T, interpolation: Interpolation) {
+    let key = Keyframe {
+      time,
+      value,
+      interpolation,
+    };
+    let index = self.keys.partition_point(|key| key.time < time);
+    match self.keys.get(index) {
+      Some(existing) if existing.time == time => self.keys[index] = key,
+      _ => self.keys.insert(index, key),
+    }
+  }
+
+  /// Returns the keyframes, sorted by time.
+  pub fn keys(&self) -> &[Keyframe<T>] {
+    return &self.keys;
+  }
+
+  /// Returns the value at `time`, or `None` if the track has no keyframes.
+  pub fn sample(&self, time: Float) -> Option<T> {
+    let keys = &self.keys;
+    let first = keys.first()?;
+    if time <= first.time {
+      return Some(first.value.clone());
+    }
+    let next = keys.partition_point(|key| key.time <= time);
+    if next == keys.len() {
+      return Some(keys[next - 1].value.clone());
+    }
+
+    let (start, end) = (&keys[next - 1], &keys[next]);
+    let t = (time - start.time) / (end.time - start.time);
+    return Some(match start.interpolation {
+      Interpolation::Step => start.value.clone(),
+      Interpolation::Linear => start.value.lerp(&end.value, &t),
+      Interpolation::Cubic => {
+        // The ends of the track are extended with keyframes holding their values.
+        let duration = end.time - start.time;
+        let before = match next.checked_sub(2) {
+          Some(index) => (keys[index].time, &keys[index].value),
+          None => (start.time - duration, &start.value),
+        };
+        let after = match keys.get(next + 1) {
+          Some(key) => (key.time, &key.value),
+          None => (end.time + duration, &end.value),
+        };
+        catmull_rom(
+          [
+            before,
+            (start.time, &start.value),
+            (end.time, &end.value),
+            after,
+          ],
+          time,
+        )
+      }
+    });
+  }
+}
+impl<T> Default for Track<T>
+where
+  T: Lerp<Scalar = Float> + Clone,
+{
+  fn default() -> Self {
+    return Self::new();
+  }
+}
+
+/// Evaluates the Catmull-Rom spline through four keyframes between the middle two, with the
+/// Barry-Goldman pyramid. Only linear interpolation is needed, so any [`Lerp`] type can be
+/// interpolated.
+fn catmull_rom<T>(keys: [(Float, &T); 4], time: Float) -> T
+where
+  T: Lerp<Scalar = Float> + Clone,
+{
+  let [(t0, p0), (t1, p1), (t2, p2), (t3, p3)] = keys;
+  let at = |start: Float, end: Float| {
+    return (time - start) / (end - start);
+  };
+  let a1 = p0.lerp(p1, &at(t0, t1));
+  let a2 = p1.lerp(p2, &at(t1, t2));
+  let a3 = p2.lerp(p3, &at(t2, t3));
+  let b1 = a1.lerp(&a2, &at(t0, t2));
+  let b2 = a2.lerp(&a3, &at(t1, t3));
+  return b1.lerp(&b2, &at(t1, t2));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::assert_ae;
+  use crate::geometry::Point;
+
+  #[test]
+  fn empty_track_has_no_value() {
+    assert_eq!(Track::<Float>::new().sample(0.), None);
+  }
+
+  #[test]
+  fn holds_values_outside_keyframes() {
+    let track = Track::new()
+      .with_key(1., 2., Interpolation::Linear)
+      .with_key(3., 6., Interpolation::Linear);
+    assert_eq!(track.sample(-5.), Some(2.));
+    assert_eq!(track.sample(10.), Some(6.));
+    assert_eq!(Track::constant(4.).sample(100.), Some(4.));
+  }
+
+  #[test]
+  fn keys_are_sorted_and_replaced() {
+    let track = Track::new()
+      .with_key(2., 1., Interpolation::Linear)
+      .with_key(0., 0., Interpolation::Linear)
+      .with_key(2., 5., Interpolation::Step);
+    let times: Vec<Float> = track.keys().iter().map(|key| key.time).collect();
+    assert_eq!(times, vec![0., 2.]);
+    assert_eq!(track.keys()[1].value, 5.);
+  }
+
+  #[test]
+  fn step_interpolation() {
+    let track =
+      Track::new()
+        .with_key(0., 1., Interpolation::Step)
+        .with_key(1., 3., Interpolation::Step);
+    assert_eq!(track.sample(0.99), Some(1.));
+    assert_eq!(track.sample(1.), Some(3.));
+  }
+
+  #[test]
+  fn linear_interpolation() {
+    let track = Track::new()
+      .with_key(0., Point::origin(), Interpolation::Linear)
+      .with_key(2., Point::new(4., 0., -2.), Interpolation::Linear);
+    assert_eq!(track.sample(0.5), Some(Point::new(1., 0., -0.5)));
+  }
+
+  #[test]
+  fn interpolation_is_chosen_by_earlier_key() {
+    let track = Track::new()
+      .with_key(0., 0., Interpolation::Step)
+      .with_key(1., 1., Interpolation::Linear)
+      .with_key(2., 3., Interpolation::Linear);
+    assert_eq!(track.sample(0.5), Some(0.));
+    assert_eq!(track.sample(1.5), Some(2.));
+  }
+
+  #[test]
+  fn cubic_interpolation_passes_through_keys() {
+    let track = Track::new()
+      .with_key(0., 0., Interpolation::Cubic)
+      .with_key(1., 2., Interpolation::Cubic)
+      .with_key(3., -1., Interpolation::Cubic)
+      .with_key(4., 5., Interpolation::Cubic);
+    for key in track.keys() {
+      assert_ae!(track.sample(key.time).unwrap(), key.value);
+    }
+  }
+
+  #[test]
+  fn cubic_interpolation_of_a_line_is_linear() {
+    let track = Track::new()
+      .with_key(0., 0., Interpolation::Cubic)
+      .with_key(1., 1., Interpolation::Cubic)
+      .with_key(3., 3., Interpolation::Cubic)
+      .with_key(4., 4., Interpolation::Cubic);
+    assert_ae!(track.sample(1.5).unwrap(), 1.5);
+    assert_ae!(track.sample(2.2).unwrap(), 2.2);
+  }
+
+  #[test]
+  fn cubic_interpolation_is_smooth_at_keys() {
+    let track = Track::new()
+      .with_key(0., 0., Interpolation::Cubic)
+      .with_key(1., 1., Interpolation::Cubic)
+      .with_key(2., 0., Interpolation::Cubic);
+    let h = 1e-4;
+    let slope_before = (track.sample(1.).unwrap() - track.sample(1. - h).unwrap()) / h;
+    let slope_after = (track.sample(1. + h).unwrap() - track.sample(1.).unwrap()) / h;
+    assert!((slope_before - slope_after).abs() < 1e-2);
+  }
+}
@@ -0,0 +1,898 @@
+//! Command-line interface of the renderer: renders a scene file to an image file.
+
+use sorb_rust::animation::{Animation, CameraAnimation, Interpolation, Track, DEFAULT_FRAME_RATE};
+use sorb_rust::formats::{FormatError, OutputFormat};
+use sorb_rust::render::{
+  Accumulator, Camera, Canvas, DisplayTransform, RenderObserver, RenderPass, ToneCurve, World,
+};
+use sorb_rust::scene::{Scene, SceneError};
+use sorb_rust::utils::consts::PI;
+use sorb_rust::utils::Float;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+mod progress;
+use progress::ProgressBar;
+
+pub const USAGE: &str = "Usage: sorb-rust [OPTIONS] SCENE OUTPUT";
+
+const HELP: &str = "Renders a scene file to an image.
+
+Usage: sorb-rust [OPTIONS] SCENE OUTPUT
+
+Options:
+  --width PIXELS       Width of the image. Keeps the aspect ratio of the scene when
+                       --height is not given
+  --height PIXELS      Height of the image. Keeps the aspect ratio of the scene when
+                       --width is not given
+  -s, --samples N      Rays traced per pixel, to smooth jagged edges [default: 1]
+  -p, --passes N       Splits the samples into N passes, each refining the whole
+                       image and rewriting OUTPUT [default: 1]
+      --checkpoint PATH
+                       Saves the samples of every pass to PATH, and continues from
+                       PATH when it exists, so a stopped render can be resumed
+      --aovs NAMES     Also writes the comma-separated render passes depth, normal,
+                       albedo, object_id, direct and indirect, next to OUTPUT as
+                       in render.depth.exr [default: the aovs of the scene camera]
+      --turntable SECONDS
+                       Renders an image sequence of the camera circling the vertical
+                       axis through the origin once in SECONDS, at 24 frames per
+                       second. The last run of # in OUTPUT is replaced by the frame
+                       number, as in frame-###.png
+  -t, --threads N      Number of render threads [default: number of CPUs]
+  -f, --format FORMAT  ppm, ppm-ascii, png, png16, pfm or exr [default: from the
+                       extension of OUTPUT]
+  -e, --exposure STOPS Brightens or darkens the image for ppm and png [default: 0]
+      --tone CURVE     clamp, reinhard, aces or hable, compressing bright colors for
+                       ppm and png [default: clamp]
+  -q, --quiet          Do not show progress
+  -h, --help           Print this help
+
+pfm and exr keep the linear colors of the render, so exposure and tone are left to
+the program opening them.";
+
+/// Error returned when the command line is invalid or the render cannot be completed.
+#[derive(Debug)]
+pub enum CliError {
+  /// The arguments are missing, unknown or invalid.
+  Usage(String),
+  Scene {
+    path: PathBuf,
+    error: SceneError,
+  },
+  Output {
+    path: PathBuf,
+    error: FormatError,
+  },
+  /// The checkpoint cannot be read or written, or was saved for a different image size.
+  Checkpoint {
+    path: PathBuf,
+    error: FormatError,
+  },
+}
+impl CliError {
+  /// Returns the exit status of the process for the error: 2 for usage errors, 1 otherwise.
+  pub fn exit_code(&self) -> i32 {
+    return match self {
+      CliError::Usage(_) => 2,
+      _ => 1,
+    };
+  }
+}
+impl Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self {
+      CliError::Usage(message) => write!(f, "{}", message),
+      CliError::Scene { path, error } => write!(f, "{}: {}", path.display(), error),
+      CliError::Output { path, error } => {
+        write!(f, "cannot write {}: {}", path.display(), error)
+      }
+      CliError::Checkpoint { path, error } => {
+        write!(f, "checkpoint {}: {}", path.display(), error)
+      }
+    };
+  }
+}
+impl Error for CliError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    return match self {
+      CliError::Usage(_) => None,
+      CliError::Scene { error, .. } => Some(error),
+      CliError::Output { error, .. } => Some(error),
+      CliError::Checkpoint { error, .. } => Some(error),
+    };
+  }
+}
+
+/// Settings of a render, parsed from the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+  pub scene: PathBuf,
+  pub output: PathBuf,
+  pub width: Option<usize>,
+  pub height: Option<usize>,
+  pub samples: usize,
+  pub passes: usize,
+  pub checkpoint: Option<PathBuf>,
+  /// Passes written besides the image, replacing those of the scene.
+  pub aovs: Option<Vec<RenderPass>>,
+  /// Length in seconds of a turntable sequence, rendered instead of a single image.
+  pub turntable: Option<Float>,
+  pub threads: usize,
+  pub format: OutputFormat,
+  pub exposure: Float,
+  pub curve: ToneCurve,
+  pub quiet: bool,
+}
+
+/// What the command line asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+  Help,
+  Render(Options),
+}
+
+/// Parses the command-line arguments, without the name of the program.
+///
+/// Options take their value either as the next argument or after `=`, as in `--samples=16`.
+pub fn parse_args<I>(args: I) -> Result<Command, CliError>
+where
+  I: IntoIterator<Item = String>,
+{
+  let mut args = args.into_iter();
+  let mut paths = Vec::new();
+  let mut width = None;
+  let mut height = None;
+  let mut samples = 1;
+  let mut passes = 1;
+  let mut checkpoint = None;
+  let mut aovs = None;
+  let mut turntable = None;
+  let mut threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+  let mut format = None;
+  let mut exposure = 0.;
+  let mut curve = ToneCurve::default();
+  let mut quiet = false;
+
+  while let Some(arg) = args.next() {
+    if !arg.starts_with('-') || arg == "-" {
+      paths.push(PathBuf::from(arg));
+      continue;
+    }
+    let (name, inline_value) = match arg.split_once('=') {
+      Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+      _ => (arg.as_str(), None),
+    };
+    let mut value = || {
+      return inline_value
+        .clone()
+        .or_else(|| args.next())
+        .ok_or_else(|| CliError::Usage(format!("missing value for `{}`", name)));
+    };
+    match name {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-q" | "--quiet" => quiet = true,
+      "--width" => width = Some(parse_count(name, &value()?)?),
+      "--height" => height = Some(parse_count(name, &value()?)?),
+      "-s" | "--samples" => samples = parse_count(name, &value()?)?,
+      "-p" | "--passes" => passes = parse_count(name, &value()?)?,
+      "--checkpoint" => checkpoint = Some(PathBuf::from(value()?)),
+      "--aovs" => {
+        let value = value()?;
+        aovs = Some(
+          value
+            .split(',')
+            .map(|name| {
+              return RenderPass::from_name(name)
+                .ok_or_else(|| CliError::Usage(format!("unknown render pass `{}`", name)));
+            })
+            .collect::<Result<_, _>>()?,
+        );
+      }
+      "--turntable" => {
+        let value = value()?;
+        turntable = Some(
+          value
+            .parse()
+            .ok()
+            .filter(|seconds: &Float| seconds.is_finite() && *seconds > 0.)
+            .ok_or_else(|| CliError::Usage(format!("invalid turntable length `{}`", value)))?,
+        );
+      }
+      "-t" | "--threads" => threads = parse_count(name, &value()?)?,
+      "-f" | "--format" => {
+        let value = value()?;
+        format = Some(
+          OutputFormat::from_name(&value)
+            .ok_or_else(|| CliError::Usage(format!("unknown format `{}`", value)))?,
+        );
+      }
+      "-e" | "--exposure" => {
+        let value = value()?;
+        exposure = value
+          .parse()
+          .ok()
+          .filter(|exposure: &Float| exposure.is_finite())
+          .ok_or_else(|| CliError::Usage(format!("invalid exposure `{}`", value)))?;
+      }
+      "--tone" => {
+        let value = value()?;
+        curve = match value.as_str() {
+          "clamp" => ToneCurve::Clamp,
+          "reinhard" => ToneCurve::Reinhard,
+          "aces" => ToneCurve::Aces,
+          "hable" => ToneCurve::Hable,
+          _ => return Err(CliError::Usage(format!("unknown tone curve `{}`", value))),
+        };
+      }
+      _ => return Err(CliError::Usage(format!("unknown option `{}`", name))),
+    }
+  }
+
+  let (scene, output) = match <[PathBuf; 2]>::try_from(paths) {
+    Ok([scene, output]) => (scene, output),
+    Err(paths) if paths.len() < 2 => {
+      return Err(CliError::Usage(
+        "expected a scene file and an output file".to_string(),
+      ))
+    }
+    Err(paths) => {
+      return Err(CliError::Usage(format!(
+        "unexpected argument `{}`",
+        paths[2].display()
+      )))
+    }
+  };
+  if turntable.is_some() && (passes > 1 || checkpoint.is_some()) {
+    return Err(CliError::Usage(
+      "--turntable cannot be combined with --passes or --checkpoint".to_string(),
+    ));
+  }
+  let format = match format.or_else(|| OutputFormat::from_path(&output)) {
+    Some(format) => format,
+    None => {
+      return Err(CliError::Usage(format!(
+        "cannot tell the format of `{}` from its extension, use --format",
+        output.display()
+      )))
+    }
+  };
+
+  return Ok(Command::Render(Options {
+    scene,
+    output,
+    width,
+    height,
+    samples,
+    passes,
+    checkpoint,
+    aovs,
+    turntable,
+    threads,
+    format,
+    exposure,
+    curve,
+    quiet,
+  }));
+}
+
+/// Parses a positive whole number given to option `name`.
+fn parse_count(name: &str, value: &str) -> Result<usize, CliError> {
+  return match value.parse() {
+    Ok(count) if count > 0 => Ok(count),
+    _ => Err(CliError::Usage(format!(
+      "`{}` expects a positive whole number, got `{}`",
+      name, value
+    ))),
+  };
+}
+
+/// Returns the size of the image, filling in a missing dimension from the aspect ratio of the
+/// scene camera.
+fn image_size(options: &Options, camera: &Camera) -> (usize, usize) {
+  let aspect_ratio = camera.aspect_ratio();
+  let scaled = |size: Float| (size.round() as usize).max(1);
+  return match (options.width, options.height) {
+    (Some(width), Some(height)) => (width, height),
+    (Some(width), None) => (width, scaled(width as Float / aspect_ratio)),
+    (None, Some(height)) => (scaled(height as Float * aspect_ratio), height),
+    (None, None) => (camera.canvas_width(), camera.canvas_height()),
+  };
+}
+
+/// Runs `render` with a progress bar over `rows` rows, unless `quiet` is set.
+fn with_progress<T, F>(quiet: bool, rows: usize, render: F) -> T
+where
+  F: FnOnce(&dyn RenderObserver) -> T,
+{
+  if quiet {
+    return render(&());
+  }
+  let progress = ProgressBar::new(rows);
+  let result = render(&progress);
+  progress.finish();
+  return result;
+}
+
+/// Returns the path a render pass is written to: `OUTPUT` with the name of the pass before its
+/// extension.
+fn pass_path(output: &Path, pass: RenderPass) -> PathBuf {
+  let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+  let file_name = match output.extension() {
+    Some(extension) => format!("{}.{}.{}", stem, pass.name(), extension.to_string_lossy()),
+    None => format!("{}.{}", stem, pass.name()),
+  };
+  return output.with_file_name(file_name);
+}
+
+/// Returns a hash of the scene source and the camera, which identifies the render a checkpoint
+/// belongs to. FNV-1a is used because, unlike the hasher of the standard library, it gives the
+/// same value in every build.
+// Camera values are already `f64` in double precision.
+#[cfg_attr(not(feature = "f32"), allow(clippy::unnecessary_cast))]
+fn fingerprint(source: &[u8], camera: &Camera) -> u64 {
+  let transform = camera.camera_to_world();
+  let camera_values = (0..16)
+    .map(|index| transform[(index / 4, index % 4)])
+    .chain(std::iter::once(camera.fov()))
+    .flat_map(|value| (value as f64).to_bits().to_le_bytes());
+  return source.iter().copied().chain(camera_values).fold(
+    0xcbf2_9ce4_8422_2325,
+    |hash: u64, byte| {
+      return (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    },
+  );
+}
+
+/// Loads the checkpoint at `path`, checking that it was saved for an image of the given size and
+/// the same scene.
+fn load_checkpoint(
+  path: &Path,
+  width: usize,
+  height: usize,
+  fingerprint: u64,
+) -> Result<Accumulator, CliError> {
+  let error = |error| CliError::Checkpoint {
+    path: path.to_path_buf(),
+    error,
+  };
+  let accumulator = Accumulator::open(path).map_err(error)?;
+  if (accumulator.width(), accumulator.height()) != (width, height) {
+    return Err(error(FormatError::InvalidData(format!(
+      "saved for a {}x{} image, not {}x{}",
+      accumulator.width(),
+      accumulator.height(),
+      width,
+      height
+    ))));
+  }
+  if accumulator.fingerprint() != fingerprint {
+    return Err(error(FormatError::InvalidData(
+      "saved for a different scene or camera".to_string(),
+    )));
+  }
+  return Ok(accumulator);
+}
+
+/// Returns the animation of a camera circling the vertical axis through the origin once in
+/// `frame_count` frames, so the sequence loops. Every frame has a key, which keeps the camera on the
+/// circle.
+fn turntable(camera: &Camera, frame_count: usize) -> Animation {
+  let mut camera_to_world = Track::new();
+  for frame in 0..frame_count {
+    let angle = 2. * PI * frame as Float / frame_count as Float;
+    camera_to_world.add_key(
+      frame as Float / DEFAULT_FRAME_RATE,
+      camera.camera_to_world().rotate_y(angle),
+      Interpolation::Step,
+    );
+  }
+  return Animation::new().with_camera(CameraAnimation::transform(camera_to_world));
+}
+
+/// Renders the turntable sequence of `options`, `seconds` long, to numbered files.
+fn render_turntable(
+  options: &Options,
+  mut camera: Camera,
+  mut world: World,
+  seconds: Float,
+  display: &DisplayTransform,
+) -> Result<(), CliError> {
+  let frame_count = ((seconds * DEFAULT_FRAME_RATE).round() as usize).max(1);
+  if !options.quiet {
+    eprintln!("Rendering {} frames", frame_count);
+  }
+  return turntable(&camera, frame_count)
+    .write_frames(
+      &mut camera,
+      &mut world,
+      0..=frame_count - 1,
+      options.threads,
+      &options.output,
+      options.format,
+      display,
+    )
+    .map_err(|error| CliError::Output {
+      path: options.output.clone(),
+      error,
+    });
+}
+
+/// Renders the scene described by `options` and writes it to the output file, or to numbered files
+/// for a turntable sequence.
+///
+/// With several passes or a checkpoint, the output file is rewritten after every pass. Render
+/// passes are written next to the output file.
+pub fn render(options: &Options) -> Result<(), CliError> {
+  let scene = Scene::open(&options.scene).map_err(|error| CliError::Scene {
+    path: options.scene.clone(),
+    error,
+  })?;
+  let aovs = match &options.aovs {
+    Some(aovs) => aovs.clone(),
+    None => scene.passes().to_vec(),
+  };
+  if !aovs.is_empty()
+    && (options.turntable.is_some() || options.passes > 1 || options.checkpoint.is_some())
+  {
+    return Err(CliError::Usage(
+      "render passes cannot be combined with --passes, --checkpoint or --turntable".to_string(),
+    ));
+  }
+  let (camera, world) = scene.into_parts();
+  let (width, height) = image_size(options, &camera);
+  let camera = Camera::new(width, height, camera.fov(), *camera.camera_to_world())
+    .with_samples(options.samples);
+
+  let display = DisplayTransform::default()
+    .with_exposure(options.exposure)
+    .with_curve(options.curve);
+  let write_to = |canvas: &Canvas, path: &Path| {
+    return options
+      .format
+      .write(canvas, path, &display)
+      .map_err(|error| CliError::Output {
+        path: path.to_path_buf(),
+        error,
+      });
+  };
+  let write_output = |canvas: &Canvas| write_to(canvas, &options.output);
+
+  if let Some(seconds) = options.turntable {
+    return render_turntable(options, camera, world, seconds, &display);
+  }
+  if !aovs.is_empty() {
+    let layers = with_progress(options.quiet, height, |observer| {
+      return camera.render_passes_observed(&world, options.threads, &aovs, observer);
+    });
+    write_output(layers.beauty())?;
+    for (pass, canvas) in layers.passes() {
+      write_to(canvas, &pass_path(&options.output, *pass))?;
+    }
+    return Ok(());
+  }
+  if options.passes == 1 && options.checkpoint.is_none() {
+    let render = with_progress(options.quiet, height, |observer| {
+      return camera.render_observed(&world, options.threads, observer);
+    });
+    return write_output(render.canvas());
+  }
+
+  let source = fs::read(&options.scene).map_err(|error| CliError::Scene {
+    path: options.scene.clone(),
+    error: error.into(),
+  })?;
+  let fingerprint = fingerprint(&source, &camera);
+  let mut accumulator = match &options.checkpoint {
+    Some(path) if path.exists() => load_checkpoint(path, width, height, fingerprint)?,
+    _ => Accumulator::new(width, height).with_fingerprint(fingerprint),
+  };
+  let passes = options.passes.min(options.samples);
+  let mut rendered = false;
+  for pass in 1..=passes {
+    let samples = (options.samples * pass).div_ceil(passes) as u64;
+    if accumulator.min_sample_count() >= samples {
+      continue;
+    }
+    if !options.quiet {
+      eprintln!("Pass {}/{}, {} samples per pixel", pass, passes, samples);
+    }
+    with_progress(options.quiet, height, |observer| {
+      return camera.accumulate(&world, options.threads, &mut accumulator, samples, observer);
+    });
+    write_output(&accumulator.to_canvas())?;
+    rendered = true;
+    if let Some(path) = &options.checkpoint {
+      accumulator
+        .save(path)
+        .map_err(|error| CliError::Checkpoint {
+          path: path.clone(),
+          error,
+        })?;
+    }
+  }
+  // A resumed checkpoint may already hold every sample.
+  if !rendered {
+    write_output(&accumulator.to_canvas())?;
+  }
+  return Ok(());
+}
+
+/// Runs the renderer with the command-line arguments, without the name of the program.
+pub fn run<I>(args: I) -> Result<(), CliError>
+where
+  I: IntoIterator<Item = String>,
+{
+  return match parse_args(args)? {
+    Command::Help => {
+      println!("{}", HELP);
+      Ok(())
+    }
+    Command::Render(options) => render(&options),
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use sorb_rust::formats::read_image;
+  use sorb_rust::geometry::{Matrix, Point};
+  use std::fs;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    return args.iter().map(|arg| arg.to_string()).collect();
+  }
+
+  fn options(list: &[&str]) -> Options {
+    return match parse_args(args(list)) {
+      Ok(Command::Render(options)) => options,
+      other => panic!("Expected render options, got {:?}", other),
+    };
+  }
+
+  fn usage_error(list: &[&str]) -> String {
+    return match parse_args(args(list)) {
+      Err(CliError::Usage(message)) => message,
+      other => panic!("Expected a usage error, got {:?}", other),
+    };
+  }
+
+  #[test]
+  fn parse_defaults() {
+    let options = options(&["room.scene", "room.png"]);
+    assert_eq!(options.scene, PathBuf::from("room.scene"));
+    assert_eq!(options.output, PathBuf::from("room.png"));
+    assert_eq!(options.width, None);
+    assert_eq!(options.height, None);
+    assert_eq!(options.samples, 1);
+    assert_eq!(options.passes, 1);
+    assert_eq!(options.checkpoint, None);
+    assert_eq!(options.aovs, None);
+    assert_eq!(options.turntable, None);
+    assert!(options.threads >= 1);
+    assert_eq!(options.format, OutputFormat::Png);
+    assert_eq!(options.exposure, 0.);
+    assert_eq!(options.curve, ToneCurve::Clamp);
+    assert!(!options.quiet);
+  }
+
+  #[test]
+  fn parse_options() {
+    let options = options(&[
+      "--width",
+      "320",
+      "--height=200",
+      "-s",
+      "16",
+      "-p",
+      "4",
+      "--checkpoint",
+      "room.samples",
+      "--aovs=depth,albedo",
+      "--threads=3",
+      "-f",
+      "png16",
+      "-e",
+      "-1.5",
+      "--tone",
+      "aces",
+      "-q",
+      "room.scene",
+      "room.img",
+    ]);
+    assert_eq!(options.width, Some(320));
+    assert_eq!(options.height, Some(200));
+    assert_eq!(options.samples, 16);
+    assert_eq!(options.passes, 4);
+    assert_eq!(options.checkpoint, Some(PathBuf::from("room.samples")));
+    assert_eq!(
+      options.aovs,
+      Some(vec![RenderPass::Depth, RenderPass::Albedo])
+    );
+    assert_eq!(options.threads, 3);
+    assert_eq!(options.format, OutputFormat::Png16);
+    assert_eq!(options.exposure, -1.5);
+    assert_eq!(options.curve, ToneCurve::Aces);
+    assert!(options.quiet);
+  }
+
+  #[test]
+  fn parse_help() {
+    assert_eq!(
+      parse_args(args(&["a.scene", "--help"])).unwrap(),
+      Command::Help
+    );
+    assert_eq!(parse_args(args(&["-h"])).unwrap(), Command::Help);
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(
+      usage_error(&["room.scene"]),
+      "expected a scene file and an output file"
+    );
+    assert_eq!(
+      usage_error(&["a.scene", "b.png", "c.png"]),
+      "unexpected argument `c.png`"
+    );
+    assert_eq!(
+      usage_error(&["--samples", "0", "a.scene", "b.png"]),
+      "`--samples` expects a positive whole number, got `0`"
+    );
+    assert_eq!(
+      usage_error(&["a.scene", "b.png", "--width"]),
+      "missing value for `--width`"
+    );
+    assert_eq!(
+      usage_error(&["--fast", "a.scene", "b.png"]),
+      "unknown option `--fast`"
+    );
+    assert_eq!(
+      usage_error(&["--tone", "filmic", "a.scene", "b.png"]),
+      "unknown tone curve `filmic`"
+    );
+    assert_eq!(
+      usage_error(&["--turntable", "-1", "a.scene", "b.png"]),
+      "invalid turntable length `-1`"
+    );
+    assert_eq!(
+      usage_error(&["--turntable", "2", "-p", "2", "a.scene", "b.png"]),
+      "--turntable cannot be combined with --passes or --checkpoint"
+    );
+    assert_eq!(
+      usage_error(&["--aovs", "depth,shadow", "a.scene", "b.png"]),
+      "unknown render pass `shadow`"
+    );
+    assert_eq!(
+      usage_error(&["a.scene", "b.jpg"]),
+      "cannot tell the format of `b.jpg` from its extension, use --format"
+    );
+  }
+
+  #[test]
+  fn format_from_path() {
+    assert_eq!(
+      OutputFormat::from_path(Path::new("out/render.PNG")),
+      Some(OutputFormat::Png)
+    );
+    assert_eq!(
+      OutputFormat::from_path(Path::new("render.exr")),
+      Some(OutputFormat::Exr)
+    );
+    assert_eq!(OutputFormat::from_path(Path::new("render.png16")), None);
+    assert_eq!(OutputFormat::from_path(Path::new("render")), None);
+  }
+
+  #[test]
+  fn image_size_keeps_aspect_ratio() {
+    let camera = Camera::new(640, 480, 60., Matrix::identity());
+    let mut options = options(&["a.scene", "b.png"]);
+    assert_eq!(image_size(&options, &camera), (640, 480));
+    options.width = Some(320);
+    assert_eq!(image_size(&options, &camera), (320, 240));
+    options.height = Some(100);
+    assert_eq!(image_size(&options, &camera), (320, 100));
+    options.width = None;
+    assert_eq!(image_size(&options, &camera), (133, 100));
+  }
+
+  #[test]
+  fn error_exit_codes() {
+    assert_eq!(CliError::Usage(String::new()).exit_code(), 2);
+    let error = CliError::Scene {
+      path: PathBuf::from("room.scene"),
+      error: SceneError::Invalid {
+        line: 2,
+        column: 5,
+        message: "unknown statement `cube`".to_string(),
+      },
+    };
+    assert_eq!(error.exit_code(), 1);
+    assert_eq!(
+      error.to_string(),
+      "room.scene: line 2, column 5: unknown statement `cube`"
+    );
+  }
+
+  #[test]
+  fn run_renders_scene_to_file() {
+    let directory = std::env::temp_dir().join(format!("sorb-cli-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let scene = directory.join("sphere.scene");
+    let output = directory.join("sphere.pfm");
+    fs::write(
+      &scene,
+      "camera { size 40 20; from 0 0 -5; to 0 0 0; up 0 1 0 }\nsphere",
+    )
+    .unwrap();
+
+    let arguments = [
+      "--height",
+      "8",
+      "-q",
+      "-t",
+      "2",
+      scene.to_str().unwrap(),
+      output.to_str().unwrap(),
+    ];
+    run(args(&arguments)).unwrap();
+    let canvas = read_image(&fs::read(&output).unwrap()).unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (16, 8));
+
+    let missing = directory.join("missing.scene");
+    let result = run(args(&[missing.to_str().unwrap(), output.to_str().unwrap()]));
+    assert!(matches!(result, Err(CliError::Scene { .. })));
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn run_writes_render_passes() {
+    let directory = std::env::temp_dir().join(format!("sorb-cli-aovs-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let scene = directory.join("sphere.scene");
+    let output = directory.join("sphere.pfm");
+    fs::write(
+      &scene,
+      "camera { size 8 4; fov 20; from 0 0 -5; to 0 0 0; up 0 1 0; aovs depth }\nsphere",
+    )
+    .unwrap();
+
+    let render = |extra: &[&str]| {
+      let mut arguments = vec!["-q", "-s", "4"];
+      arguments.extend_from_slice(extra);
+      arguments.extend_from_slice(&[scene.to_str().unwrap(), output.to_str().unwrap()]);
+      return run(args(&arguments));
+    };
+    render(&[]).unwrap();
+    let depth = read_image(&fs::read(directory.join("sphere.depth.pfm")).unwrap()).unwrap();
+    assert_eq!((depth.width(), depth.height()), (8, 4));
+    assert!(depth.pixel(4, 2).r > 3.);
+    assert!(!directory.join("sphere.normal.pfm").exists());
+
+    render(&["--aovs", "normal,object_id"]).unwrap();
+    assert!(directory.join("sphere.normal.pfm").exists());
+    assert!(directory.join("sphere.object_id.pfm").exists());
+    assert!(matches!(render(&["-p", "2"]), Err(CliError::Usage(_))));
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn pass_path_inserts_the_pass_name() {
+    assert_eq!(
+      pass_path(Path::new("out/room.exr"), RenderPass::Depth),
+      PathBuf::from("out/room.depth.exr")
+    );
+    assert_eq!(
+      pass_path(Path::new("room"), RenderPass::ObjectId),
+      PathBuf::from("room.object_id")
+    );
+  }
+
+  #[test]
+  fn run_renders_turntable_frames() {
+    let directory = std::env::temp_dir().join(format!("sorb-cli-turntable-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let scene = directory.join("sphere.scene");
+    let output = directory.join("frame-##.pfm");
+    fs::write(
+      &scene,
+      "camera { size 8 4; from 0 0 -5; to 0 0 0; up 0 1 0 }\nsphere",
+    )
+    .unwrap();
+
+    let arguments = [
+      "-q",
+      "--turntable",
+      "0.125",
+      scene.to_str().unwrap(),
+      output.to_str().unwrap(),
+    ];
+    run(args(&arguments)).unwrap();
+    for frame in 0..3 {
+      let path = directory.join(format!("frame-{:02}.pfm", frame));
+      let canvas = read_image(&fs::read(&path).unwrap()).unwrap();
+      assert_eq!((canvas.width(), canvas.height()), (8, 4));
+    }
+    assert!(!directory.join("frame-03.pfm").exists());
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn turntable_circles_the_origin() {
+    let camera_to_world = Matrix::identity().translate(0., 1., 5.);
+    let camera = Camera::new(4, 4, 60., camera_to_world);
+    let animation = turntable(&camera, 4);
+    let mut moved = Camera::new(4, 4, 60., camera_to_world);
+    animation.apply(1. / DEFAULT_FRAME_RATE, &mut moved, &mut World::default());
+    assert_eq!(
+      *moved.camera_to_world() * Point::origin(),
+      Point::new(5., 1., 0.)
+    );
+    animation.apply(0., &mut moved, &mut World::default());
+    assert_eq!(moved.camera_to_world(), &camera_to_world);
+  }
+
+  #[test]
+  fn run_resumes_from_checkpoint() {
+    let directory = std::env::temp_dir().join(format!("sorb-cli-resume-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let scene = directory.join("sphere.scene");
+    let output = directory.join("sphere.pfm");
+    let checkpoint = directory.join("sphere.samples");
+    fs::write(
+      &scene,
+      "camera { size 12 8; from 0 0 -5; to 0 0 0; up 0 1 0 }\nsphere",
+    )
+    .unwrap();
+    let render = |samples: &str, passes: &str| {
+      let arguments = [
+        "-q",
+        "-s",
+        samples,
+        "-p",
+        passes,
+        "--checkpoint",
+        checkpoint.to_str().unwrap(),
+        scene.to_str().unwrap(),
+        output.to_str().unwrap(),
+      ];
+      return run(args(&arguments));
+    };
+
+    render("2", "2").unwrap();
+    assert_eq!(
+      Accumulator::open(&checkpoint).unwrap().min_sample_count(),
+      2
+    );
+    render("6", "3").unwrap();
+    let accumulator = Accumulator::open(&checkpoint).unwrap();
+    assert_eq!(accumulator.min_sample_count(), 6);
+    let canvas = read_image(&fs::read(&output).unwrap()).unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (12, 8));
+
+    let arguments = [
+      "--width",
+      "6",
+      "--checkpoint",
+      checkpoint.to_str().unwrap(),
+      scene.to_str().unwrap(),
+      output.to_str().unwrap(),
+    ];
+    let result = run(args(&arguments));
+    assert!(matches!(result, Err(CliError::Checkpoint { .. })));
+
+    // A changed scene of the same size must not be mixed with the saved samples.
+    fs::write(
+      &scene,
+      "camera { size 12 8; from 0 0 -5; to 0 0 0; up 0 1 0 }\nsphere { transform { translate 1 0 0 } }",
+    )
+    .unwrap();
+    let result = render("8", "4");
+    assert!(matches!(result, Err(CliError::Checkpoint { .. })));
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
@@ -0,0 +1,121 @@
+use sorb_rust::render::{RenderObserver, RowEvent};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Width of the bar itself, in characters.
+const BAR_WIDTH: usize = 30;
+/// Minimum time between two redraws, so fast renders are not slowed down by the terminal.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress bar with an estimate of the remaining time, drawn on a single line of stderr.
+///
+/// Updates may come from several threads at once.
+pub struct ProgressBar {
+  total: usize,
+  start: Instant,
+  last_draw: Mutex<Option<Instant>>,
+}
+impl ProgressBar {
+  pub fn new(total: usize) -> Self {
+    return Self {
+      total,
+      start: Instant::now(),
+      last_draw: Mutex::new(None),
+    };
+  }
+
+  /// Redraws the bar with `done` steps finished, unless it was redrawn very recently.
+  pub fn update(&self, done: usize) {
+    let mut last_draw = self
+      .last_draw
+      .lock()
+      .expect("Progress bar lock is poisoned");
+    let now = Instant::now();
+    let recently = last_draw.is_some_and(|last| now - last < REDRAW_INTERVAL);
+    if recently && done < self.total {
+      return;
+    }
+    *last_draw = Some(now);
+    eprint!("\r{}", line(done, self.total, now - self.start));
+    let _ = io::stderr().flush();
+  }
+
+  /// Ends the line of the bar and reports the total time taken.
+  pub fn finish(&self) {
+    let _lock = self
+      .last_draw
+      .lock()
+      .expect("Progress bar lock is poisoned");
+    eprintln!("\nDone in {}", format_duration(self.start.elapsed()));
+  }
+}
+
+impl RenderObserver for ProgressBar {
+  fn row_finished(&self, event: &RowEvent) {
+    self.update(event.finished_rows);
+  }
+}
+
+/// Returns the text of the bar with `done` out of `total` steps finished after `elapsed`.
+fn line(done: usize, total: usize, elapsed: Duration) -> String {
+  let fraction = if total == 0 {
+    1.
+  } else {
+    done.min(total) as f64 / total as f64
+  };
+  let filled = (fraction * BAR_WIDTH as f64) as usize;
+  let eta = if done == 0 {
+    "--:--".to_string()
+  } else {
+    let remaining = elapsed.as_secs_f64() * (total.saturating_sub(done)) as f64 / done as f64;
+    format_duration(Duration::from_secs_f64(remaining))
+  };
+  return format!(
+    "[{}{}] {:>3}% {}/{} rows, ETA {}",
+    "#".repeat(filled),
+    "-".repeat(BAR_WIDTH - filled),
+    (fraction * 100.) as usize,
+    done,
+    total,
+    eta
+  );
+}
+
+/// Formats a duration as minutes and seconds, with hours only when needed.
+fn format_duration(duration: Duration) -> String {
+  let seconds = duration.as_secs_f64().round() as u64;
+  let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+  if hours > 0 {
+    return format!("{}:{:02}:{:02}", hours, minutes, seconds);
+  }
+  return format!("{:02}:{:02}", minutes, seconds);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn duration_formatting() {
+    assert_eq!(format_duration(Duration::from_secs(0)), "00:00");
+    assert_eq!(format_duration(Duration::from_millis(61_600)), "01:02");
+    assert_eq!(format_duration(Duration::from_secs(3_725)), "1:02:05");
+  }
+
+  #[test]
+  fn line_shows_progress_and_eta() {
+    assert_eq!(
+      line(0, 10, Duration::from_secs(0)),
+      "[------------------------------]   0% 0/10 rows, ETA --:--"
+    );
+    assert_eq!(
+      line(5, 20, Duration::from_secs(10)),
+      "[#######-----------------------]  25% 5/20 rows, ETA 00:30"
+    );
+    assert_eq!(
+      line(20, 20, Duration::from_secs(40)),
+      "[##############################] 100% 20/20 rows, ETA 00:00"
+    );
+  }
+}
@@ -1,15 +1,15 @@
-use crate::utils::{approx_equals, Lerp};
+use crate::utils::{approx_equals, Float, Lerp};
 use std::iter::Sum;
 use std::ops::{Add, Mul, Sub};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Color {
-  pub r: f64,
-  pub g: f64,
-  pub b: f64,
+  pub r: Float,
+  pub g: Float,
+  pub b: Float,
 }
 impl Color {
-  pub fn new(r: f64, g: f64, b: f64) -> Self {
+  pub fn new(r: Float, g: Float, b: Float) -> Self {
     return Self { r, g, b };
   }
 
@@ -51,6 +51,30 @@ impl Color {
     self.b = new.b;
   }
 
+  /// Returns the relative luminance of the color, using the Rec. 709 weights.
+  pub fn luminance(&self) -> Float {
+    return 0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b;
+  }
+
+  /// Converts linear light values to the nonlinear sRGB encoding used by most image files and
+  /// displays.
+  pub fn encode_srgb(&self) -> Self {
+    return Self::new(
+      srgb_encode(self.r),
+      srgb_encode(self.g),
+      srgb_encode(self.b),
+    );
+  }
+
+  /// Converts sRGB-encoded values back to linear light values.
+  pub fn decode_srgb(&self) -> Self {
+    return Self::new(
+      srgb_decode(self.r),
+      srgb_decode(self.g),
+      srgb_decode(self.b),
+    );
+  }
+
   /// Blends two colors by averaging their respective values.
   pub fn blend(&self, other: &Self) -> Self {
     return Color::new(
@@ -61,6 +85,22 @@ impl Color {
   }
 }
 
+fn srgb_encode(value: Float) -> Float {
+  return if value <= 0.003_130_8 {
+    12.92 * value
+  } else {
+    1.055 * value.powf(1. / 2.4) - 0.055
+  };
+}
+
+fn srgb_decode(value: Float) -> Float {
+  return if value <= 0.040_45 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  };
+}
+
 impl Default for Color {
   fn default() -> Self {
     return Self::black();
@@ -107,14 +147,14 @@ impl Sub for Color {
     return Self::new(self.r - other.r, self.g - other.g, self.b - other.b);
   }
 }
-impl Mul<f64> for Color {
+impl Mul<Float> for Color {
   type Output = Self;
 
-  fn mul(self, scalar: f64) -> Self {
+  fn mul(self, scalar: Float) -> Self {
     return Self::new(scalar * self.r, scalar * self.g, scalar * self.b);
   }
 }
-impl Mul<Color> for f64 {
+impl Mul<Color> for Float {
   type Output = Color;
 
   fn mul(self, color: Color) -> Color {
@@ -129,7 +169,7 @@ impl Mul for Color {
   }
 }
 impl Lerp for Color {
-  type Scalar = f64;
+  type Scalar = Float;
 
   fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
     return Color::new(
@@ -224,6 +264,7 @@ mod tests {
     assert_eq!(sum, Color::new(0.6, 0.6, 0.6));
   }
   #[test]
+  #[allow(clippy::useless_vec)]
   fn sum_references() {
     let colors = vec![
       Color::new(0.1, 0.1, 0.1),
@@ -279,6 +320,34 @@ mod tests {
     );
   }
 
+  #[test]
+  fn luminance() {
+    assert_eq!(Color::white().luminance(), 1.);
+    assert_eq!(Color::black().luminance(), 0.);
+    assert_eq!(Color::green().luminance(), 0.7152);
+    assert_eq!(Color::new(0.5, 0.5, 0.5).luminance(), 0.5);
+  }
+
+  #[test]
+  fn encode_srgb() {
+    assert_eq!(Color::black().encode_srgb(), Color::black());
+    assert_eq!(Color::white().encode_srgb(), Color::white());
+    assert_eq!(
+      Color::new(0.001, 0.18, 0.5).encode_srgb(),
+      Color::new(0.01292, 0.46135, 0.73536)
+    );
+  }
+
+  #[test]
+  fn decode_srgb_inverts_encode() {
+    let color = Color::new(0.002, 0.2, 0.9);
+    assert_eq!(color.encode_srgb().decode_srgb(), color);
+    assert_eq!(
+      Color::new(0.5, 0.5, 0.5).decode_srgb(),
+      Color::new(0.21404, 0.21404, 0.21404)
+    );
+  }
+
   #[test]
   fn lerp() {
     assert_eq!(
@@ -0,0 +1,204 @@
+use crate::render::{Canvas, DisplayTransform};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+pub mod checksum;
+pub use checksum::*;
+
+pub mod exr;
+pub use exr::*;
+
+pub mod pfm;
+pub use pfm::*;
+
+pub mod png;
+pub use png::*;
+
+pub mod ppm;
+pub use ppm::*;
+
+pub mod zlib;
+
+/// Error returned when an image file cannot be read or written.
+#[derive(Debug)]
+pub enum FormatError {
+  Io(io::Error),
+  /// The data does not follow the structure of the format it claims to be.
+  InvalidData(String),
+  /// The data is valid, but uses a feature this crate does not support.
+  Unsupported(String),
+}
+impl Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self {
+      FormatError::Io(error) => write!(f, "I/O error: {}", error),
+      FormatError::InvalidData(message) => write!(f, "invalid image data: {}", message),
+      FormatError::Unsupported(message) => write!(f, "unsupported image: {}", message),
+    };
+  }
+}
+impl Error for FormatError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    return match self {
+      FormatError::Io(error) => Some(error),
+      _ => None,
+    };
+  }
+}
+impl From<io::Error> for FormatError {
+  fn from(error: io::Error) -> Self {
+    return FormatError::Io(error);
+  }
+}
+
+/// Decodes an image in any of the supported formats, detected from its magic number.
+pub fn read_image(data: &[u8]) -> Result<Canvas, FormatError> {
+  if data.starts_with(b"\x89PNG") {
+    return read_png(data);
+  }
+  if data.starts_with(b"\x76\x2f\x31\x01") {
+    return read_exr(data);
+  }
+  return match data.get(0..2) {
+    Some(b"P3") | Some(b"P6") => read_ppm(data),
+    Some(b"PF") | Some(b"Pf") => read_pfm(data),
+    _ => Err(FormatError::Unsupported(
+      "unrecognized image format".to_string(),
+    )),
+  };
+}
+
+/// Format and options an image is written with, for writing canvases to files whose format is
+/// chosen at run time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+  Ppm,
+  PpmAscii,
+  Png,
+  Png16,
+  Pfm,
+  Exr,
+}
+impl OutputFormat {
+  pub fn from_name(name: &str) -> Option<Self> {
+    return match name {
+      "ppm" => Some(OutputFormat::Ppm),
+      "ppm-ascii" => Some(OutputFormat::PpmAscii),
+      "png" => Some(OutputFormat::Png),
+      "png16" => Some(OutputFormat::Png16),
+      "pfm" => Some(OutputFormat::Pfm),
+      "exr" => Some(OutputFormat::Exr),
+      _ => None,
+    };
+  }
+
+  /// Returns the 8-bit format matching the extension of `path`, if it is a known one.
+  pub fn from_path(path: &Path) -> Option<Self> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    return match extension.as_str() {
+      "ppm" | "png" | "pfm" | "exr" => OutputFormat::from_name(&extension),
+      _ => None,
+    };
+  }
+
+  /// Writes `canvas` to a new file at `path`, converting colors with `display` for formats
+  /// limited to `0.0..=1.0`.
+  pub fn write(
+    &self,
+    canvas: &Canvas,
+    path: &Path,
+    display: &DisplayTransform,
+  ) -> Result<(), FormatError> {
+    let writer = BufWriter::new(File::create(path)?);
+    return match self {
+      OutputFormat::Ppm => canvas.write_ppm(writer, PpmEncoding::Binary, display),
+      OutputFormat::PpmAscii => canvas.write_ppm(writer, PpmEncoding::Ascii, display),
+      OutputFormat::Png => canvas.write_png(writer, PngBitDepth::Eight, display),
+      OutputFormat::Png16 => canvas.write_png(writer, PngBitDepth::Sixteen, display),
+      OutputFormat::Pfm => canvas.write_pfm(writer),
+      OutputFormat::Exr => canvas.write_exr(writer, ExrCompression::Zip),
+    };
+  }
+}
+
+/// Returns the number of values in an image with `channels` values per pixel, or an error if
+/// the count overflows, so sizes from untrusted headers are safe to multiply.
+fn value_count(width: usize, height: usize, channels: usize) -> Result<usize, FormatError> {
+  return width
+    .checked_mul(height)
+    .and_then(|pixels| pixels.checked_mul(channels))
+    .ok_or_else(|| {
+      FormatError::InvalidData(format!("image size {}x{} is too large", width, height))
+    });
+}
+
+/// Reads whitespace-separated ASCII header tokens, skipping `#` comments, as used by the Netpbm
+/// family of formats.
+struct HeaderReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+impl<'a> HeaderReader<'a> {
+  fn new(data: &'a [u8]) -> Self {
+    return Self { data, position: 0 };
+  }
+
+  fn skip_whitespace_and_comments(&mut self) {
+    while self.position < self.data.len() {
+      let byte = self.data[self.position];
+      if byte == b'#' {
+        while self.position < self.data.len() && self.data[self.position] != b'\n' {
+          self.position += 1;
+        }
+      } else if byte.is_ascii_whitespace() {
+        self.position += 1;
+      } else {
+        break;
+      }
+    }
+  }
+
+  fn token(&mut self) -> Result<&'a str, FormatError> {
+    self.skip_whitespace_and_comments();
+    let start = self.position;
+    while self.position < self.data.len() && !self.data[self.position].is_ascii_whitespace() {
+      self.position += 1;
+    }
+    if start == self.position {
+      return Err(FormatError::InvalidData(
+        "unexpected end of header".to_string(),
+      ));
+    }
+    return std::str::from_utf8(&self.data[start..self.position])
+      .map_err(|_| FormatError::InvalidData("header is not ASCII".to_string()));
+  }
+
+  /// Returns the number of bytes after the current position.
+  fn remaining(&self) -> usize {
+    return self.data.len() - self.position;
+  }
+
+  fn number<T: std::str::FromStr>(&mut self, name: &str) -> Result<T, FormatError> {
+    let token = self.token()?;
+    return token
+      .parse()
+      .map_err(|_| FormatError::InvalidData(format!("invalid {} `{}`", name, token)));
+  }
+
+  /// Consumes the single whitespace byte separating the header from binary pixel data and
+  /// returns the remaining bytes.
+  fn binary_data(mut self) -> Result<&'a [u8], FormatError> {
+    match self.data.get(self.position) {
+      Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+      _ => {
+        return Err(FormatError::InvalidData(
+          "missing whitespace after header".to_string(),
+        ))
+      }
+    }
+    return Ok(&self.data[self.position..]);
+  }
+}
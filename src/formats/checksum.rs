@@ -0,0 +1,67 @@
+/// Computes the CRC-32 (ISO 3309) of `data`, as used by PNG chunks and gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+  return crc32_update(0, data);
+}
+
+/// Continues a CRC-32 computed over previous data with more data.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+  let mut crc = !crc;
+  for &byte in data {
+    crc ^= byte as u32;
+    for _ in 0..8 {
+      let mask = (crc & 1).wrapping_neg();
+      crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+    }
+  }
+  return !crc;
+}
+
+/// Computes the Adler-32 checksum of `data`, as used by zlib streams.
+pub fn adler32(data: &[u8]) -> u32 {
+  const MODULUS: u32 = 65521;
+  let (mut a, mut b) = (1u32, 0u32);
+  // Summing 5552 bytes at most between reductions keeps `b` from overflowing.
+  for chunk in data.chunks(5552) {
+    for &byte in chunk {
+      a += byte as u32;
+      b += a;
+    }
+    a %= MODULUS;
+    b %= MODULUS;
+  }
+  return (b << 16) | a;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn crc32_known_values() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+  }
+
+  #[test]
+  fn crc32_incremental() {
+    assert_eq!(crc32_update(crc32(b"1234"), b"56789"), crc32(b"123456789"));
+  }
+
+  #[test]
+  fn adler32_known_values() {
+    assert_eq!(adler32(b""), 1);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+  }
+
+  #[test]
+  fn adler32_long_input() {
+    let data = vec![255u8; 100_000];
+    let (mut a, mut b) = (1u64, 0u64);
+    for &byte in &data {
+      a = (a + byte as u64) % 65521;
+      b = (b + a) % 65521;
+    }
+    assert_eq!(adler32(&data), ((b << 16) | a) as u32);
+  }
+}
@@ -0,0 +1,546 @@
+use super::{value_count, zlib, FormatError};
+use crate::render::Canvas;
+use crate::utils::Float;
+use crate::Color;
+use std::convert::TryFrom;
+use std::io::{BufWriter, Write};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+/// File format version 2, single-part scanline image.
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+const PIXEL_TYPE_HALF: i32 = 1;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+/// Compression of the pixel data in an OpenEXR file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExrCompression {
+  Uncompressed,
+  /// Lossless zlib compression of blocks of 16 scanlines.
+  #[default]
+  Zip,
+}
+impl ExrCompression {
+  fn id(&self) -> u8 {
+    return match self {
+      ExrCompression::Uncompressed => 0,
+      ExrCompression::Zip => 3,
+    };
+  }
+
+  fn lines_per_block(id: u8) -> Option<usize> {
+    return match id {
+      0 | 2 => Some(1),
+      3 => Some(16),
+      _ => None,
+    };
+  }
+}
+
+/// Encodes a canvas as a scanline OpenEXR image with 32-bit float `R`, `G` and `B` channels.
+///
+/// Colors are stored unclamped and linear, as compositing software expects.
+// Samples are already `f32` in single precision.
+#[cfg_attr(feature = "f32", allow(clippy::unnecessary_cast))]
+pub fn write_exr<W: Write>(
+  canvas: &Canvas,
+  writer: W,
+  compression: ExrCompression,
+) -> Result<(), FormatError> {
+  let (width, height) = (canvas.width(), canvas.height());
+  if width == 0 || height == 0 || width > i32::MAX as usize || height > i32::MAX as usize {
+    return Err(FormatError::Unsupported(format!(
+      "OpenEXR images cannot be {}x{} pixels",
+      width, height
+    )));
+  }
+  let mut writer = BufWriter::new(writer);
+
+  let mut header = Vec::new();
+  header.extend_from_slice(&MAGIC);
+  header.extend_from_slice(&VERSION);
+  let mut channels = Vec::new();
+  // Channels are stored in alphabetical order.
+  for name in ["B", "G", "R"].iter() {
+    channels.extend_from_slice(name.as_bytes());
+    channels.push(0);
+    channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+    // Perceptually linear flag and reserved bytes, then x and y sampling.
+    channels.extend_from_slice(&[0, 0, 0, 0]);
+    channels.extend_from_slice(&1i32.to_le_bytes());
+    channels.extend_from_slice(&1i32.to_le_bytes());
+  }
+  channels.push(0);
+  write_attribute(&mut header, "channels", "chlist", &channels);
+  write_attribute(
+    &mut header,
+    "compression",
+    "compression",
+    &[compression.id()],
+  );
+  let mut window = Vec::new();
+  for value in [0, 0, width as i32 - 1, height as i32 - 1].iter() {
+    window.extend_from_slice(&value.to_le_bytes());
+  }
+  write_attribute(&mut header, "dataWindow", "box2i", &window);
+  write_attribute(&mut header, "displayWindow", "box2i", &window);
+  write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+  write_attribute(
+    &mut header,
+    "pixelAspectRatio",
+    "float",
+    &1f32.to_le_bytes(),
+  );
+  write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+  write_attribute(
+    &mut header,
+    "screenWindowWidth",
+    "float",
+    &1f32.to_le_bytes(),
+  );
+  header.push(0);
+
+  let lines_per_block =
+    ExrCompression::lines_per_block(compression.id()).expect("Known compression");
+  let mut blocks = Vec::new();
+  for first_line in (0..height).step_by(lines_per_block) {
+    let last_line = (first_line + lines_per_block).min(height);
+    let mut raw = Vec::with_capacity((last_line - first_line) * width * 12);
+    for y in first_line..last_line {
+      for channel in 0..3 {
+        for x in 0..width {
+          let pixel = canvas.pixel(x, y);
+          let sample = [pixel.b, pixel.g, pixel.r][channel] as f32;
+          raw.extend_from_slice(&sample.to_le_bytes());
+        }
+      }
+    }
+    let data = match compression {
+      ExrCompression::Uncompressed => raw,
+      ExrCompression::Zip => {
+        let compressed = zlib::compress(&predict(&interleave(&raw)));
+        // Blocks that do not shrink are stored raw, which readers detect from their size.
+        if compressed.len() < raw.len() {
+          compressed
+        } else {
+          raw
+        }
+      }
+    };
+    blocks.push((first_line, data));
+  }
+
+  // The offset table gives the position of every block from the start of the file.
+  let mut offset = (header.len() + blocks.len() * 8) as u64;
+  writer.write_all(&header)?;
+  for (_, data) in &blocks {
+    writer.write_all(&offset.to_le_bytes())?;
+    offset += 8 + data.len() as u64;
+  }
+  for (first_line, data) in &blocks {
+    writer.write_all(&(*first_line as i32).to_le_bytes())?;
+    writer.write_all(&(data.len() as i32).to_le_bytes())?;
+    writer.write_all(data)?;
+  }
+  writer.flush()?;
+  return Ok(());
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+  header.extend_from_slice(name.as_bytes());
+  header.push(0);
+  header.extend_from_slice(kind.as_bytes());
+  header.push(0);
+  header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+  header.extend_from_slice(value);
+}
+
+/// Splits the bytes into the even and the odd ones, which groups similar bytes of neighboring
+/// samples together before compression.
+fn interleave(data: &[u8]) -> Vec<u8> {
+  let mut output = Vec::with_capacity(data.len());
+  output.extend(data.iter().step_by(2));
+  output.extend(data.iter().skip(1).step_by(2));
+  return output;
+}
+
+fn deinterleave(data: &[u8]) -> Vec<u8> {
+  let half = data.len().div_ceil(2);
+  let mut output = Vec::with_capacity(data.len());
+  for i in 0..data.len() {
+    output.push(if i % 2 == 0 {
+      data[i / 2]
+    } else {
+      data[half + i / 2]
+    });
+  }
+  return output;
+}
+
+/// Replaces every byte by its difference from the previous one, offset by 128.
+fn predict(data: &[u8]) -> Vec<u8> {
+  let mut output = data.to_vec();
+  for i in 1..data.len() {
+    output[i] = data[i].wrapping_sub(data[i - 1]).wrapping_add(128);
+  }
+  return output;
+}
+
+fn unpredict(data: &mut [u8]) {
+  for i in 1..data.len() {
+    data[i] = data[i - 1].wrapping_add(data[i]).wrapping_sub(128);
+  }
+}
+
+/// Decodes a single-part scanline OpenEXR image with `R`, `G` and `B` or a `Y` channel, stored
+/// uncompressed or with ZIP compression.
+pub fn read_exr(data: &[u8]) -> Result<Canvas, FormatError> {
+  if data.len() < 8 || data[..4] != MAGIC {
+    return Err(invalid("missing OpenEXR magic number"));
+  }
+  if data[5] & 0x1e != 0 {
+    return Err(FormatError::Unsupported(
+      "tiled, deep or multi-part OpenEXR images".to_string(),
+    ));
+  }
+
+  let mut reader = Reader { data, position: 8 };
+  let mut channels = Vec::new();
+  let mut compression = 0;
+  let mut window = None;
+  loop {
+    let name = reader.string()?;
+    if name.is_empty() {
+      break;
+    }
+    let _kind = reader.string()?;
+    let size = reader.size()?;
+    let value = reader.bytes(size)?;
+    match name {
+      "channels" => channels = parse_channels(value)?,
+      "compression" if size == 1 => compression = value[0],
+      "dataWindow" if size == 16 => {
+        let mut values = value
+          .chunks_exact(4)
+          .map(|bytes| i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let mut next = || values.next().expect("Window has four values");
+        window = Some((next(), next(), next(), next()));
+      }
+      _ => {}
+    }
+  }
+
+  let (x_min, y_min, x_max, y_max) = window.ok_or_else(|| invalid("missing data window"))?;
+  if x_max < x_min || y_max < y_min {
+    return Err(invalid("empty data window"));
+  }
+  let width = (x_max as i64 - x_min as i64 + 1) as usize;
+  let height = (y_max as i64 - y_min as i64 + 1) as usize;
+  let lines_per_block = ExrCompression::lines_per_block(compression)
+    .ok_or_else(|| FormatError::Unsupported(format!("OpenEXR compression {}", compression)))?;
+  let find = |name: &str| channels.iter().position(|channel| channel.0 == name);
+  let sources = match (find("R"), find("G"), find("B"), find("Y")) {
+    (Some(r), Some(g), Some(b), _) => [r, g, b],
+    (_, _, _, Some(y)) => [y, y, y],
+    _ => {
+      return Err(FormatError::Unsupported(
+        "OpenEXR images without RGB or Y channels".to_string(),
+      ))
+    }
+  };
+  // The window comes from the header, so it is checked against the data before allocating.
+  let bytes_per_line = channels
+    .iter()
+    .try_fold(0, |sum: usize, channel| {
+      sum.checked_add(channel.1.checked_mul(width)?)
+    })
+    .ok_or_else(|| invalid("data window is too large"))?;
+  let image_bytes = value_count(bytes_per_line, height, 1)?;
+  let max_image_bytes = if compression == 0 {
+    data.len()
+  } else {
+    data.len().saturating_mul(zlib::MAX_RATIO)
+  };
+  if image_bytes > max_image_bytes {
+    return Err(invalid("data window is larger than the pixel data"));
+  }
+  let block_count = height.div_ceil(lines_per_block);
+  if block_count.saturating_mul(8) > data.len() - reader.position {
+    return Err(invalid("offset table is larger than the file"));
+  }
+  let mut canvas = Canvas::new(width, height);
+  for block in 0..block_count {
+    let mut offset_reader = Reader {
+      data,
+      position: reader.position + block * 8,
+    };
+    let offset = offset_reader.u64()?;
+    let mut block_reader = Reader {
+      data,
+      position: offset as usize,
+    };
+    let first_line = (block_reader.i32()? as i64 - y_min as i64) as usize;
+    let size = block_reader.size()?;
+    let stored = block_reader.bytes(size)?;
+    if first_line >= height {
+      return Err(invalid("scanline block outside of the data window"));
+    }
+    let line_count = lines_per_block.min(height - first_line);
+    let expected = line_count * bytes_per_line;
+    let raw = if size < expected {
+      let mut raw = zlib::decompress(stored, expected)?;
+      unpredict(&mut raw);
+      deinterleave(&raw)
+    } else {
+      stored.to_vec()
+    };
+    if raw.len() < expected {
+      return Err(invalid("scanline block is too short"));
+    }
+
+    for line in 0..line_count {
+      let mut position = line * bytes_per_line;
+      let mut samples = vec![Vec::with_capacity(width); channels.len()];
+      for (index, (_, bytes_per_sample)) in channels.iter().enumerate() {
+        for _ in 0..width {
+          let bytes = &raw[position..position + bytes_per_sample];
+          samples[index].push(if *bytes_per_sample == 2 {
+            half_to_float(u16::from_le_bytes([bytes[0], bytes[1]]))
+          } else {
+            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Float
+          });
+          position += bytes_per_sample;
+        }
+      }
+      let [red, green, blue] = sources.map(|source| &samples[source]);
+      for (x, ((r, g), b)) in red.iter().zip(green).zip(blue).enumerate() {
+        canvas.set_pixel(x, first_line + line, &Color::new(*r, *g, *b));
+      }
+    }
+  }
+  return Ok(canvas);
+}
+
+/// Returns the name and sample size in bytes of every channel in a `chlist` attribute.
+fn parse_channels(value: &[u8]) -> Result<Vec<(String, usize)>, FormatError> {
+  let mut reader = Reader {
+    data: value,
+    position: 0,
+  };
+  let mut channels = Vec::new();
+  loop {
+    let name = reader.string()?;
+    if name.is_empty() {
+      return Ok(channels);
+    }
+    let bytes_per_sample = match reader.i32()? {
+      PIXEL_TYPE_HALF => 2,
+      PIXEL_TYPE_FLOAT => 4,
+      pixel_type => {
+        return Err(FormatError::Unsupported(format!(
+          "OpenEXR pixel type {}",
+          pixel_type
+        )))
+      }
+    };
+    reader.bytes(4)?;
+    if reader.i32()? != 1 || reader.i32()? != 1 {
+      return Err(FormatError::Unsupported(
+        "subsampled OpenEXR channels".to_string(),
+      ));
+    }
+    channels.push((name.to_string(), bytes_per_sample));
+  }
+}
+
+fn half_to_float(half: u16) -> Float {
+  let sign = if half & 0x8000 != 0 { -1. } else { 1. };
+  let exponent = ((half >> 10) & 0x1f) as i32;
+  let mantissa = (half & 0x3ff) as Float;
+  return sign
+    * match exponent {
+      0 => mantissa * (2. as Float).powi(-24),
+      31 if mantissa == 0. => Float::INFINITY,
+      31 => Float::NAN,
+      _ => (1. + mantissa / 1024.) * (2. as Float).powi(exponent - 15),
+    };
+}
+
+/// Reads little-endian values and null-terminated strings.
+struct Reader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+impl<'a> Reader<'a> {
+  fn bytes(&mut self, count: usize) -> Result<&'a [u8], FormatError> {
+    let end = self
+      .position
+      .checked_add(count)
+      .filter(|&end| end <= self.data.len())
+      .ok_or_else(|| invalid("unexpected end of OpenEXR data"))?;
+    let bytes = &self.data[self.position..end];
+    self.position = end;
+    return Ok(bytes);
+  }
+
+  fn i32(&mut self) -> Result<i32, FormatError> {
+    let bytes = self.bytes(4)?;
+    return Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+  }
+
+  /// Reads a byte count, which must not be negative.
+  fn size(&mut self) -> Result<usize, FormatError> {
+    let size = self.i32()?;
+    return usize::try_from(size).map_err(|_| invalid("negative size in OpenEXR data"));
+  }
+
+  fn u64(&mut self) -> Result<u64, FormatError> {
+    let bytes = self.bytes(8)?;
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes);
+    return Ok(u64::from_le_bytes(value));
+  }
+
+  fn string(&mut self) -> Result<&'a str, FormatError> {
+    let length = self.data[self.position.min(self.data.len())..]
+      .iter()
+      .position(|&byte| byte == 0)
+      .ok_or_else(|| invalid("unterminated OpenEXR string"))?;
+    let bytes = self.bytes(length + 1)?;
+    return std::str::from_utf8(&bytes[..length]).map_err(|_| invalid("invalid OpenEXR string"));
+  }
+}
+
+fn invalid(message: &str) -> FormatError {
+  return FormatError::InvalidData(message.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn test_canvas(width: usize, height: usize) -> Canvas {
+    let mut canvas = Canvas::new(width, height);
+    for x in 0..width {
+      for y in 0..height {
+        let color = Color::new(x as Float * 3.5, y as Float / 7., -(x as Float) + 1e-3);
+        canvas.set_pixel(x, y, &color);
+      }
+    }
+    return canvas;
+  }
+
+  fn encode(canvas: &Canvas, compression: ExrCompression) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_exr(canvas, &mut data, compression).expect("Expected OpenEXR to be written");
+    return data;
+  }
+
+  #[test]
+  fn write_header() {
+    let data = encode(&test_canvas(2, 3), ExrCompression::Uncompressed);
+    assert_eq!(&data[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+    assert_eq!(&data[8..28], b"channels\0chlist\0\x37\0\0\0");
+    assert_eq!(&data[28..30], b"B\0");
+  }
+
+  #[test]
+  fn write_uncompressed_scanlines() {
+    let data = encode(&test_canvas(2, 3), ExrCompression::Uncompressed);
+    // One block per scanline, each holding the line number, size and 2 pixels of 3 floats.
+    let block_size = 4 + 4 + 2 * 12;
+    let last_block = &data[data.len() - block_size..];
+    assert_eq!(&last_block[..4], &2i32.to_le_bytes());
+    assert_eq!(&last_block[4..8], &24i32.to_le_bytes());
+    // Blue channel first.
+    assert_eq!(&last_block[8..12], &(0.001f32).to_le_bytes());
+  }
+
+  #[test]
+  fn round_trip_uncompressed() {
+    let canvas = test_canvas(5, 4);
+    let decoded =
+      read_exr(&encode(&canvas, ExrCompression::Uncompressed)).expect("Expected valid OpenEXR");
+    assert_eq!(decoded.width(), 5);
+    assert_eq!(decoded.height(), 4);
+    assert_eq!(decoded.pixels(), canvas.pixels());
+  }
+
+  #[test]
+  fn round_trip_zip() {
+    let canvas = test_canvas(37, 41);
+    let data = encode(&canvas, ExrCompression::Zip);
+    assert!(data.len() < encode(&canvas, ExrCompression::Uncompressed).len());
+    let decoded = read_exr(&data).expect("Expected valid OpenEXR");
+    assert_eq!(decoded.pixels(), canvas.pixels());
+  }
+
+  #[test]
+  fn round_trip_keeps_highlights() {
+    let mut canvas = Canvas::new(1, 1);
+    canvas.set_pixel(0, 0, &Color::new(250., 1.5, 0.));
+    let decoded = read_exr(&encode(&canvas, ExrCompression::Zip)).expect("Expected valid OpenEXR");
+    assert_eq!(decoded.pixel(0, 0), &Color::new(250., 1.5, 0.));
+  }
+
+  #[test]
+  fn interleave_and_predict_are_reversible() {
+    let data = [1, 200, 3, 4, 255, 0, 7];
+    let mut encoded = predict(&interleave(&data));
+    unpredict(&mut encoded);
+    assert_eq!(deinterleave(&encoded), data);
+  }
+
+  #[test]
+  #[cfg_attr(feature = "f32", allow(clippy::excessive_precision))]
+  fn half_conversion() {
+    assert_eq!(half_to_float(0x3c00), 1.);
+    assert_eq!(half_to_float(0xc000), -2.);
+    assert_eq!(half_to_float(0x3555), 0.333_251_953_125);
+    assert_eq!(half_to_float(0x0001), (2. as Float).powi(-24));
+    assert_eq!(half_to_float(0x7c00), Float::INFINITY);
+  }
+
+  #[test]
+  fn read_rejects_truncated_data() {
+    let data = encode(&test_canvas(3, 3), ExrCompression::Zip);
+    assert!(read_exr(&data[..data.len() - 5]).is_err());
+    assert!(read_exr(&data[..30]).is_err());
+  }
+
+  /// Returns the position of the value of an attribute in an encoded image.
+  fn attribute_value(data: &[u8], name: &str) -> usize {
+    let start = data
+      .windows(name.len() + 1)
+      .position(|window| window[..name.len()] == *name.as_bytes() && window[name.len()] == 0)
+      .expect("Attribute exists");
+    let kind_end = start
+      + name.len()
+      + 1
+      + data[start + name.len() + 1..]
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap();
+    return kind_end + 1 + 4;
+  }
+
+  #[test]
+  fn read_rejects_windows_larger_than_data() {
+    for compression in [ExrCompression::Uncompressed, ExrCompression::Zip].iter() {
+      let mut data = encode(&test_canvas(3, 3), *compression);
+      let window = attribute_value(&data, "dataWindow");
+      data[window + 8..window + 12].copy_from_slice(&i32::MAX.to_le_bytes());
+      data[window + 12..window + 16].copy_from_slice(&i32::MAX.to_le_bytes());
+      assert!(read_exr(&data).is_err());
+      data[window..window + 4].copy_from_slice(&i32::MIN.to_le_bytes());
+      assert!(read_exr(&data).is_err());
+    }
+  }
+
+  #[test]
+  fn read_rejects_negative_sizes() {
+    let mut data = encode(&test_canvas(3, 3), ExrCompression::Uncompressed);
+    let channels = attribute_value(&data, "channels");
+    data[channels - 4..channels].copy_from_slice(&(-1i32).to_le_bytes());
+    assert!(read_exr(&data).is_err());
+  }
+}
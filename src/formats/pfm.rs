@@ -0,0 +1,164 @@
+use super::{value_count, FormatError, HeaderReader};
+use crate::render::Canvas;
+use crate::utils::Float;
+use crate::Color;
+use std::io::{BufWriter, Write};
+
+/// Decodes a color (`PF`) or grayscale (`Pf`) Portable FloatMap image.
+///
+/// A negative scale in the header marks little-endian samples. Rows are stored bottom to top.
+pub fn read_pfm(data: &[u8]) -> Result<Canvas, FormatError> {
+  let mut header = HeaderReader::new(data);
+  let channels = match header.token()? {
+    "PF" => 3,
+    "Pf" => 1,
+    magic => {
+      return Err(FormatError::InvalidData(format!(
+        "expected PFM magic number `PF` or `Pf`, found `{}`",
+        magic
+      )))
+    }
+  };
+  let width: usize = header.number("width")?;
+  let height: usize = header.number("height")?;
+  let scale: Float = header.number("scale")?;
+  if scale == 0. || !scale.is_finite() {
+    return Err(FormatError::InvalidData(format!("invalid scale {}", scale)));
+  }
+  let little_endian = scale < 0.;
+
+  let data = header.binary_data()?;
+  let sample_count = value_count(width, height, channels)?;
+  let byte_count = value_count(width, height, channels * 4)?;
+  if data.len() < byte_count {
+    return Err(FormatError::InvalidData(format!(
+      "expected {} bytes of pixel data, found {}",
+      byte_count,
+      data.len()
+    )));
+  }
+  let samples: Vec<Float> = data
+    .chunks_exact(4)
+    .take(sample_count)
+    .map(|bytes| {
+      let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+      let sample = if little_endian {
+        f32::from_le_bytes(bytes)
+      } else {
+        f32::from_be_bytes(bytes)
+      };
+      sample as Float
+    })
+    .collect();
+
+  let mut canvas = Canvas::new(width, height);
+  for (index, pixel) in samples.chunks_exact(channels).enumerate() {
+    let color = match pixel {
+      [r, g, b] => Color::new(*r, *g, *b),
+      [value] => Color::new(*value, *value, *value),
+      _ => unreachable!(),
+    };
+    canvas.set_pixel(index % width, height - 1 - index / width, &color);
+  }
+  return Ok(canvas);
+}
+
+/// Encodes a canvas as a little-endian color (`PF`) Portable FloatMap image.
+///
+/// Colors are stored as unclamped 32-bit floats, so values above 1.0 survive the export.
+// Samples are already `f32` in single precision.
+#[cfg_attr(feature = "f32", allow(clippy::unnecessary_cast))]
+pub fn write_pfm<W: Write>(canvas: &Canvas, writer: W) -> Result<(), FormatError> {
+  let mut writer = BufWriter::new(writer);
+  write!(writer, "PF\n{} {}\n-1.0\n", canvas.width(), canvas.height())?;
+  let mut row = Vec::with_capacity(canvas.width() * 12);
+  for y in (0..canvas.height()).rev() {
+    row.clear();
+    for x in 0..canvas.width() {
+      let pixel = canvas.pixel(x, y);
+      for sample in [pixel.r, pixel.g, pixel.b].iter() {
+        row.extend_from_slice(&(*sample as f32).to_le_bytes());
+      }
+    }
+    writer.write_all(&row)?;
+  }
+  writer.flush()?;
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pfm(header: &str, samples: &[f32], little_endian: bool) -> Vec<u8> {
+    let mut data = header.as_bytes().to_vec();
+    for sample in samples {
+      if little_endian {
+        data.extend_from_slice(&sample.to_le_bytes());
+      } else {
+        data.extend_from_slice(&sample.to_be_bytes());
+      }
+    }
+    return data;
+  }
+
+  #[test]
+  fn read_color_little_endian() {
+    let data = pfm("PF\n1 2\n-1.0\n", &[1., 2., 3., 0.5, 0., 4.], true);
+    let canvas = read_pfm(&data).expect("Expected valid PFM");
+    assert_eq!(canvas.width(), 1);
+    assert_eq!(canvas.height(), 2);
+    assert_eq!(canvas.pixel(0, 1), &Color::new(1., 2., 3.));
+    assert_eq!(canvas.pixel(0, 0), &Color::new(0.5, 0., 4.));
+  }
+
+  #[test]
+  fn read_grayscale_big_endian() {
+    let data = pfm("Pf\n2 1\n1.0\n", &[0.25, 7.5], false);
+    let canvas = read_pfm(&data).expect("Expected valid PFM");
+    assert_eq!(canvas.pixel(0, 0), &Color::new(0.25, 0.25, 0.25));
+    assert_eq!(canvas.pixel(1, 0), &Color::new(7.5, 7.5, 7.5));
+  }
+
+  #[test]
+  fn write_color() {
+    let mut canvas = Canvas::new(1, 2);
+    canvas.set_pixel(0, 0, &Color::new(1., 2., 3.));
+    canvas.set_pixel(0, 1, &Color::new(-0.5, 0., 40.));
+    let mut data = Vec::new();
+    write_pfm(&canvas, &mut data).expect("Expected PFM to be written");
+    assert_eq!(
+      data,
+      pfm("PF\n1 2\n-1.0\n", &[-0.5, 0., 40., 1., 2., 3.], true)
+    );
+  }
+
+  #[test]
+  fn write_round_trip_keeps_highlights() {
+    let mut canvas = Canvas::new(3, 2);
+    canvas.set_pixel(2, 1, &Color::new(12.5, 1.25, 0.125));
+    canvas.set_pixel(0, 0, &Color::new(0.1, 1000., 3.));
+    let mut data = Vec::new();
+    write_pfm(&canvas, &mut data).expect("Expected PFM to be written");
+    let decoded = read_pfm(&data).expect("Expected valid PFM");
+    assert_eq!(decoded.pixels(), canvas.pixels());
+  }
+
+  #[test]
+  fn read_rejects_zero_scale() {
+    let data = pfm("Pf\n1 1\n0\n", &[1.], true);
+    assert!(read_pfm(&data).is_err());
+  }
+
+  #[test]
+  fn read_rejects_truncated_data() {
+    let data = pfm("PF\n2 2\n-1\n", &[1., 2., 3.], true);
+    assert!(read_pfm(&data).is_err());
+  }
+
+  #[test]
+  fn read_rejects_overflowing_size() {
+    let data = pfm("PF\n6148914691236517206 1\n-1\n", &[1., 2., 3.], true);
+    assert!(read_pfm(&data).is_err());
+  }
+}
@@ -0,0 +1,502 @@
+use super::{crc32, crc32_update, value_count, zlib, FormatError};
+use crate::render::{Canvas, DisplayTransform, TransferFunction};
+use crate::utils::{clamp_number, Float};
+use crate::Color;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// The gamma of the sRGB curve, as stored in `gAMA` chunks: 1 / 2.2 scaled by 100000.
+const SRGB_GAMMA: u32 = 45455;
+const LINEAR_GAMMA: u32 = 100000;
+
+/// Number of bits per channel of a PNG image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PngBitDepth {
+  #[default]
+  Eight,
+  Sixteen,
+}
+
+/// Encodes a canvas as an RGB PNG image.
+///
+/// Colors are converted with `display`, then clamped to `0.0..=1.0`. The transfer function of
+/// the transform is declared through `sRGB` and `gAMA` chunks, so colors survive a round trip.
+pub fn write_png<W: Write>(
+  canvas: &Canvas,
+  mut writer: W,
+  bit_depth: PngBitDepth,
+  display: &DisplayTransform,
+) -> Result<(), FormatError> {
+  let (width, height) = (canvas.width(), canvas.height());
+  if width == 0 || height == 0 || width > u32::MAX as usize || height > u32::MAX as usize {
+    return Err(FormatError::Unsupported(format!(
+      "PNG images cannot be {}x{} pixels",
+      width, height
+    )));
+  }
+  let bytes_per_channel = match bit_depth {
+    PngBitDepth::Eight => 1,
+    PngBitDepth::Sixteen => 2,
+  };
+  let bytes_per_pixel = 3 * bytes_per_channel;
+
+  writer.write_all(&SIGNATURE)?;
+  let mut header = Vec::with_capacity(13);
+  header.extend_from_slice(&(width as u32).to_be_bytes());
+  header.extend_from_slice(&(height as u32).to_be_bytes());
+  // Bit depth, truecolor, deflate compression, adaptive filtering, no interlacing.
+  header.extend_from_slice(&[8 * bytes_per_channel as u8, 2, 0, 0, 0]);
+  write_chunk(&mut writer, b"IHDR", &header)?;
+  match display.transfer() {
+    TransferFunction::Srgb => {
+      // Perceptual rendering intent.
+      write_chunk(&mut writer, b"sRGB", &[0])?;
+      write_chunk(&mut writer, b"gAMA", &SRGB_GAMMA.to_be_bytes())?;
+    }
+    TransferFunction::Linear => write_chunk(&mut writer, b"gAMA", &LINEAR_GAMMA.to_be_bytes())?,
+  }
+
+  let stride = width * bytes_per_pixel;
+  let mut filtered = Vec::with_capacity((stride + 1) * height);
+  let mut previous = vec![0u8; stride];
+  let mut row = Vec::with_capacity(stride);
+  for y in 0..height {
+    row.clear();
+    for x in 0..width {
+      let color = display.apply(canvas.pixel(x, y));
+      for channel in [color.r, color.g, color.b].iter() {
+        let value = clamp_number(*channel, 0., 1.);
+        match bit_depth {
+          PngBitDepth::Eight => row.push((value * 255.).round() as u8),
+          PngBitDepth::Sixteen => {
+            row.extend_from_slice(&((value * 65535.).round() as u16).to_be_bytes())
+          }
+        }
+      }
+    }
+    filter_row(&row, &previous, bytes_per_pixel, &mut filtered);
+    std::mem::swap(&mut row, &mut previous);
+  }
+  write_chunk(&mut writer, b"IDAT", &zlib::compress(&filtered))?;
+  write_chunk(&mut writer, b"IEND", &[])?;
+  writer.flush()?;
+  return Ok(());
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), FormatError> {
+  writer.write_all(&(data.len() as u32).to_be_bytes())?;
+  writer.write_all(kind)?;
+  writer.write_all(data)?;
+  writer.write_all(&crc32_update(crc32(kind), data).to_be_bytes())?;
+  return Ok(());
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+  let estimate = left as i16 + up as i16 - up_left as i16;
+  let (distance_left, distance_up, distance_up_left) = (
+    (estimate - left as i16).abs(),
+    (estimate - up as i16).abs(),
+    (estimate - up_left as i16).abs(),
+  );
+  return if distance_left <= distance_up && distance_left <= distance_up_left {
+    left
+  } else if distance_up <= distance_up_left {
+    up
+  } else {
+    up_left
+  };
+}
+
+/// Returns the predictor of filter type `filter` for byte `i` of a row.
+fn predict(filter: u8, row: &[u8], previous: &[u8], i: usize, bytes_per_pixel: usize) -> u8 {
+  let left = if i >= bytes_per_pixel {
+    row[i - bytes_per_pixel]
+  } else {
+    0
+  };
+  let up = previous[i];
+  let up_left = if i >= bytes_per_pixel {
+    previous[i - bytes_per_pixel]
+  } else {
+    0
+  };
+  return match filter {
+    1 => left,
+    2 => up,
+    3 => ((left as u16 + up as u16) / 2) as u8,
+    4 => paeth(left, up, up_left),
+    _ => 0,
+  };
+}
+
+/// Appends the row filtered with whichever filter type gives the smallest sum of absolute
+/// differences, a heuristic that tends to compress best.
+fn filter_row(row: &[u8], previous: &[u8], bytes_per_pixel: usize, output: &mut Vec<u8>) {
+  let mut best: Option<(u64, u8, Vec<u8>)> = None;
+  for filter in 0..5 {
+    let filtered: Vec<u8> = (0..row.len())
+      .map(|i| row[i].wrapping_sub(predict(filter, row, previous, i, bytes_per_pixel)))
+      .collect();
+    let cost = filtered
+      .iter()
+      .map(|&byte| (byte as i8).unsigned_abs() as u64)
+      .sum();
+    if best
+      .as_ref()
+      .is_none_or(|(best_cost, _, _)| cost < *best_cost)
+    {
+      best = Some((cost, filter, filtered));
+    }
+  }
+  let (_, filter, filtered) = best.expect("At least one filter was tried");
+  output.push(filter);
+  output.extend_from_slice(&filtered);
+}
+
+/// Decodes a non-interlaced grayscale or truecolor PNG image, with or without alpha, at 8 or 16
+/// bits per channel.
+///
+/// Samples are converted to linear values through the sRGB transfer function, or through the
+/// power law of the `gAMA` chunk if the image has one but no `sRGB` chunk. Alpha is ignored.
+pub fn read_png(data: &[u8]) -> Result<Canvas, FormatError> {
+  if data.len() < SIGNATURE.len() || data[..SIGNATURE.len()] != SIGNATURE {
+    return Err(invalid("missing PNG signature"));
+  }
+
+  let mut position = SIGNATURE.len();
+  let mut header = None;
+  let mut compressed = Vec::new();
+  let mut srgb = false;
+  let mut gamma = None;
+  loop {
+    if position + 12 > data.len() {
+      return Err(invalid("unexpected end of PNG data"));
+    }
+    let length = u32::from_be_bytes([
+      data[position],
+      data[position + 1],
+      data[position + 2],
+      data[position + 3],
+    ]) as usize;
+    let kind = &data[position + 4..position + 8];
+    let end = position + 8 + length;
+    if end + 4 > data.len() {
+      return Err(invalid("unexpected end of PNG data"));
+    }
+    let body = &data[position + 8..end];
+    let crc = u32::from_be_bytes([data[end], data[end + 1], data[end + 2], data[end + 3]]);
+    if crc != crc32_update(crc32(kind), body) {
+      return Err(invalid("PNG chunk checksum mismatch"));
+    }
+
+    match kind {
+      b"IHDR" => header = Some(Header::parse(body)?),
+      b"IDAT" => compressed.extend_from_slice(body),
+      b"sRGB" => srgb = true,
+      b"gAMA" if body.len() == 4 => {
+        gamma = Some(u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as Float / 100000.)
+      }
+      b"IEND" => break,
+      _ => {}
+    }
+    position = end + 4;
+  }
+
+  let header = header.ok_or_else(|| invalid("missing IHDR chunk"))?;
+  // The size comes from the header, so it is checked against the data before decompressing.
+  let bytes_per_pixel = header.channels * header.bytes_per_channel;
+  let stride = value_count(header.width, 1, bytes_per_pixel)?;
+  let image_bytes = value_count(stride + 1, header.height, 1)?;
+  if image_bytes > compressed.len().saturating_mul(zlib::MAX_RATIO) {
+    return Err(invalid("image size is larger than the pixel data"));
+  }
+  let raw = zlib::decompress(&compressed, image_bytes)?;
+  if raw.len() < image_bytes {
+    return Err(invalid("not enough image data"));
+  }
+
+  let decode = |value: Float| {
+    return match gamma {
+      Some(gamma) if !srgb && gamma > 0. => value.powf(1. / gamma),
+      _ => value,
+    };
+  };
+  let mut canvas = Canvas::new(header.width, header.height);
+  let mut previous = vec![0u8; stride];
+  let mut row = vec![0u8; stride];
+  for y in 0..header.height {
+    let line = &raw[y * (stride + 1)..(y + 1) * (stride + 1)];
+    let filter = line[0];
+    if filter > 4 {
+      return Err(invalid("invalid PNG filter type"));
+    }
+    for i in 0..stride {
+      let predictor = predict(filter, &row, &previous, i, bytes_per_pixel);
+      row[i] = line[i + 1].wrapping_add(predictor);
+    }
+
+    for x in 0..header.width {
+      let pixel = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+      let sample = |channel: usize| {
+        let offset = channel * header.bytes_per_channel;
+        return if header.bytes_per_channel == 1 {
+          pixel[offset] as Float / 255.
+        } else {
+          u16::from_be_bytes([pixel[offset], pixel[offset + 1]]) as Float / 65535.
+        };
+      };
+      let encoded = if header.channels >= 3 {
+        Color::new(sample(0), sample(1), sample(2))
+      } else {
+        Color::new(sample(0), sample(0), sample(0))
+      };
+      let color = if srgb || gamma.is_none() {
+        encoded.decode_srgb()
+      } else {
+        Color::new(decode(encoded.r), decode(encoded.g), decode(encoded.b))
+      };
+      canvas.set_pixel(x, y, &color);
+    }
+    std::mem::swap(&mut row, &mut previous);
+  }
+  return Ok(canvas);
+}
+
+struct Header {
+  width: usize,
+  height: usize,
+  channels: usize,
+  bytes_per_channel: usize,
+}
+impl Header {
+  fn parse(body: &[u8]) -> Result<Self, FormatError> {
+    if body.len() != 13 {
+      return Err(invalid("IHDR chunk has the wrong length"));
+    }
+    let width = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize;
+    let height = u32::from_be_bytes([body[4], body[5], body[6], body[7]]) as usize;
+    let (bit_depth, color_type, interlace) = (body[8], body[9], body[12]);
+
+    let channels = match color_type {
+      0 => 1,
+      2 => 3,
+      4 => 2,
+      6 => 4,
+      _ => {
+        return Err(FormatError::Unsupported(format!(
+          "PNG color type {}",
+          color_type
+        )))
+      }
+    };
+    let bytes_per_channel = match bit_depth {
+      8 => 1,
+      16 => 2,
+      _ => {
+        return Err(FormatError::Unsupported(format!(
+          "PNG bit depth {}",
+          bit_depth
+        )))
+      }
+    };
+    if interlace != 0 {
+      return Err(FormatError::Unsupported(
+        "interlaced PNG images".to_string(),
+      ));
+    }
+    return Ok(Self {
+      width,
+      height,
+      channels,
+      bytes_per_channel,
+    });
+  }
+}
+
+fn invalid(message: &str) -> FormatError {
+  return FormatError::InvalidData(message.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn test_canvas() -> Canvas {
+    let mut canvas = Canvas::new(7, 5);
+    for x in 0..7 {
+      for y in 0..5 {
+        let color = Color::new(
+          x as Float / 6.,
+          y as Float / 4.,
+          ((x * y) % 3) as Float / 2.,
+        );
+        canvas.set_pixel(x, y, &color);
+      }
+    }
+    return canvas;
+  }
+
+  fn encode(canvas: &Canvas, bit_depth: PngBitDepth) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_png(canvas, &mut data, bit_depth, &Default::default())
+      .expect("Expected PNG to be written");
+    return data;
+  }
+
+  fn chunk_types(data: &[u8]) -> Vec<String> {
+    let mut types = Vec::new();
+    let mut position = SIGNATURE.len();
+    while position < data.len() {
+      let length = u32::from_be_bytes([
+        data[position],
+        data[position + 1],
+        data[position + 2],
+        data[position + 3],
+      ]) as usize;
+      types.push(String::from_utf8_lossy(&data[position + 4..position + 8]).to_string());
+      position += 12 + length;
+    }
+    return types;
+  }
+
+  #[test]
+  fn write_structure() {
+    let data = encode(&test_canvas(), PngBitDepth::Eight);
+    assert_eq!(&data[..8], &SIGNATURE);
+    assert_eq!(chunk_types(&data), ["IHDR", "sRGB", "gAMA", "IDAT", "IEND"]);
+    assert_eq!(&data[16..20], &7u32.to_be_bytes());
+    assert_eq!(&data[20..24], &5u32.to_be_bytes());
+    assert_eq!(data[24], 8);
+    assert_eq!(data[25], 2);
+  }
+
+  #[test]
+  fn write_sixteen_bit_header() {
+    let data = encode(&test_canvas(), PngBitDepth::Sixteen);
+    assert_eq!(data[24], 16);
+  }
+
+  #[test]
+  fn write_rejects_empty_canvas() {
+    let result = write_png(
+      &Canvas::new(0, 3),
+      Vec::new(),
+      PngBitDepth::Eight,
+      &Default::default(),
+    );
+    assert!(matches!(result, Err(FormatError::Unsupported(_))));
+  }
+
+  #[test]
+  fn round_trip_eight_bit() {
+    let canvas = test_canvas();
+    let decoded = read_png(&encode(&canvas, PngBitDepth::Eight)).expect("Expected valid PNG");
+    assert_eq!(decoded.width(), 7);
+    assert_eq!(decoded.height(), 5);
+    for (expected, actual) in canvas.pixels().iter().zip(decoded.pixels()) {
+      // 8-bit sRGB steps are at most about 0.0055 in linear light near white.
+      assert!((expected.r - actual.r).abs() < 0.006);
+      assert!((expected.g - actual.g).abs() < 0.006);
+      assert!((expected.b - actual.b).abs() < 0.006);
+    }
+  }
+
+  #[test]
+  fn round_trip_sixteen_bit() {
+    let canvas = test_canvas();
+    let decoded = read_png(&encode(&canvas, PngBitDepth::Sixteen)).expect("Expected valid PNG");
+    for (expected, actual) in canvas.pixels().iter().zip(decoded.pixels()) {
+      assert!((expected.r - actual.r).abs() < 0.0001);
+      assert!((expected.g - actual.g).abs() < 0.0001);
+      assert!((expected.b - actual.b).abs() < 0.0001);
+    }
+  }
+
+  #[test]
+  fn round_trip_clamps_out_of_range_colors() {
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(0, 0, &Color::new(3., -1., 0.5));
+    let decoded = read_png(&encode(&canvas, PngBitDepth::Sixteen)).expect("Expected valid PNG");
+    assert!((decoded.pixel(0, 0).r - 1.).abs() < 0.0001);
+    assert!(decoded.pixel(0, 0).g.abs() < 0.0001);
+  }
+
+  #[test]
+  fn stores_srgb_encoded_values() {
+    let mut canvas = Canvas::new(1, 1);
+    canvas.set_pixel(0, 0, &Color::new(0.5, 0.5, 0.5));
+    let data = encode(&canvas, PngBitDepth::Eight);
+    let idat = 8 + 25 + 13 + 16;
+    let raw =
+      zlib::decompress(&data[idat + 8..data.len() - 16], usize::MAX).expect("Expected valid IDAT");
+    // The first byte of a row has no neighbors to predict from under any filter.
+    assert_eq!(raw[1], 188);
+  }
+
+  #[test]
+  fn write_linear() {
+    let mut canvas = Canvas::new(1, 1);
+    canvas.set_pixel(0, 0, &Color::new(0.5, 0.25, 1.));
+    let mut data = Vec::new();
+    write_png(
+      &canvas,
+      &mut data,
+      PngBitDepth::Sixteen,
+      &DisplayTransform::linear(),
+    )
+    .expect("Expected PNG to be written");
+    assert_eq!(chunk_types(&data), ["IHDR", "gAMA", "IDAT", "IEND"]);
+    let decoded = read_png(&data).expect("Expected valid PNG");
+    assert!((decoded.pixel(0, 0).r - 0.5).abs() < 0.0001);
+    assert!((decoded.pixel(0, 0).g - 0.25).abs() < 0.0001);
+  }
+
+  #[test]
+  fn read_rejects_corrupt_chunk() {
+    let mut data = encode(&test_canvas(), PngBitDepth::Eight);
+    data[20] ^= 0xff;
+    assert!(read_png(&data).is_err());
+  }
+
+  /// PNG with the given IHDR size and a single 16-bit RGBA pixel of image data.
+  fn png_with_size(width: u32, height: u32) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[16, 6, 0, 0, 0]);
+    let mut data = SIGNATURE.to_vec();
+    write_chunk(&mut data, b"IHDR", &header).expect("Expected chunk to be written");
+    write_chunk(&mut data, b"IDAT", &zlib::compress(&[0; 9]))
+      .expect("Expected chunk to be written");
+    write_chunk(&mut data, b"IEND", &[]).expect("Expected chunk to be written");
+    return data;
+  }
+
+  #[test]
+  fn read_rejects_oversized_header() {
+    assert!(read_png(&png_with_size(1, 1)).is_ok());
+    assert!(read_png(&png_with_size(u32::MAX, u32::MAX)).is_err());
+    assert!(read_png(&png_with_size(100_000, 100_000)).is_err());
+  }
+
+  #[test]
+  fn read_rejects_missing_signature() {
+    assert!(read_png(b"not a png").is_err());
+  }
+
+  #[test]
+  fn filter_round_trip() {
+    let previous: [u8; 6] = [10, 20, 30, 40, 50, 60];
+    let row: [u8; 6] = [12, 25, 29, 200, 3, 61];
+    for filter in 0..5u8 {
+      let filtered: Vec<u8> = (0..row.len())
+        .map(|i| row[i].wrapping_sub(predict(filter, &row, &previous, i, 3)))
+        .collect();
+      let mut decoded = [0u8; 6];
+      for i in 0..row.len() {
+        decoded[i] = filtered[i].wrapping_add(predict(filter, &decoded, &previous, i, 3));
+      }
+      assert_eq!(decoded, row);
+    }
+  }
+}
@@ -0,0 +1,325 @@
+use super::{value_count, FormatError, HeaderReader};
+use crate::render::{Canvas, DisplayTransform};
+use crate::utils::{clamp_number, Float};
+use crate::Color;
+use std::io::{BufWriter, Write};
+
+/// Maximum line length of ASCII PPM files, as required by many readers.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Sample encoding of a PPM file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PpmEncoding {
+  /// Plain text samples (`P3`), easy to inspect but large.
+  #[default]
+  Ascii,
+  /// Raw bytes (`P6`), a third of the size and much faster to read and write.
+  Binary,
+}
+
+/// Decodes an ASCII (`P3`) or binary (`P6`) PPM image.
+///
+/// Samples are scaled by the maximum value in the header to the `0.0..=1.0` range.
+pub fn read_ppm(data: &[u8]) -> Result<Canvas, FormatError> {
+  let mut header = HeaderReader::new(data);
+  let magic = header.token()?;
+  if magic != "P3" && magic != "P6" {
+    return Err(FormatError::InvalidData(format!(
+      "expected PPM magic number `P3` or `P6`, found `{}`",
+      magic
+    )));
+  }
+  let width: usize = header.number("width")?;
+  let height: usize = header.number("height")?;
+  let max_value: u32 = header.number("maximum value")?;
+  if max_value == 0 || max_value > 65535 {
+    return Err(FormatError::InvalidData(format!(
+      "maximum value {} is outside 1..=65535",
+      max_value
+    )));
+  }
+
+  let sample_count = value_count(width, height, 3)?;
+  let samples = if magic == "P3" {
+    // Every sample takes at least one byte, so larger counts cannot be satisfied by the file.
+    if header.remaining() < sample_count {
+      return Err(FormatError::InvalidData(format!(
+        "expected {} samples, found only {} bytes",
+        sample_count,
+        header.remaining()
+      )));
+    }
+    let mut samples = Vec::new();
+    for _ in 0..sample_count {
+      samples.push(header.number::<u32>("sample")?);
+    }
+    samples
+  } else {
+    let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+    let byte_count = value_count(width, height, 3 * bytes_per_sample)?;
+    let data = header.binary_data()?;
+    if data.len() < byte_count {
+      return Err(FormatError::InvalidData(format!(
+        "expected {} bytes of pixel data, found {}",
+        byte_count,
+        data.len()
+      )));
+    }
+    data
+      .chunks_exact(bytes_per_sample)
+      .take(sample_count)
+      .map(|bytes| match bytes {
+        [value] => *value as u32,
+        [high, low] => u32::from_be_bytes([0, 0, *high, *low]),
+        _ => unreachable!(),
+      })
+      .collect()
+  };
+
+  let mut canvas = Canvas::new(width, height);
+  let scale = max_value as Float;
+  for (index, rgb) in samples.chunks_exact(3).enumerate() {
+    if rgb.iter().any(|&sample| sample > max_value) {
+      return Err(FormatError::InvalidData(format!(
+        "sample exceeds maximum value {}",
+        max_value
+      )));
+    }
+    let color = Color::new(
+      rgb[0] as Float / scale,
+      rgb[1] as Float / scale,
+      rgb[2] as Float / scale,
+    );
+    canvas.set_pixel(index % width, index / width, &color);
+  }
+  return Ok(canvas);
+}
+
+/// Encodes a canvas as an 8-bit PPM image, writing it to `writer` one row at a time.
+///
+/// Colors are converted with `display`, then clamped to `0.0..=1.0`.
+pub fn write_ppm<W: Write>(
+  canvas: &Canvas,
+  writer: W,
+  encoding: PpmEncoding,
+  display: &DisplayTransform,
+) -> Result<(), FormatError> {
+  let mut writer = BufWriter::new(writer);
+  let magic = match encoding {
+    PpmEncoding::Ascii => "P3",
+    PpmEncoding::Binary => "P6",
+  };
+  write!(
+    writer,
+    "{}\n{} {}\n255\n",
+    magic,
+    canvas.width(),
+    canvas.height()
+  )?;
+
+  let mut row = Vec::with_capacity(canvas.width() * 3);
+  let mut line = String::with_capacity(MAX_LINE_LENGTH + 1);
+  for y in 0..canvas.height() {
+    row.clear();
+    for x in 0..canvas.width() {
+      let pixel = display.apply(canvas.pixel(x, y));
+      row.extend_from_slice(&[to_byte(pixel.r), to_byte(pixel.g), to_byte(pixel.b)]);
+    }
+
+    match encoding {
+      PpmEncoding::Binary => writer.write_all(&row)?,
+      PpmEncoding::Ascii => {
+        line.clear();
+        for sample in &row {
+          let sample = sample.to_string();
+          if !line.is_empty() && line.len() + 1 + sample.len() > MAX_LINE_LENGTH {
+            line.push('\n');
+            writer.write_all(line.as_bytes())?;
+            line.clear();
+          }
+          if !line.is_empty() {
+            line.push(' ');
+          }
+          line.push_str(&sample);
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+      }
+    }
+  }
+
+  writer.flush()?;
+  return Ok(());
+}
+
+fn to_byte(value: Float) -> u8 {
+  return clamp_number(value * 255., 0., 255.).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::render::ToneCurve;
+  use std::io;
+
+  /// Writer that fails after accepting a given number of bytes.
+  struct FailingWriter {
+    remaining: usize,
+  }
+  impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      if self.remaining == 0 {
+        return Err(io::Error::other("disk full"));
+      }
+      let written = buf.len().min(self.remaining);
+      self.remaining -= written;
+      return Ok(written);
+    }
+    fn flush(&mut self) -> io::Result<()> {
+      return Ok(());
+    }
+  }
+
+  fn test_canvas() -> Canvas {
+    let mut canvas = Canvas::new(5, 3);
+    canvas.set_pixel(0, 0, &Color::new(1.5, 0., 0.));
+    canvas.set_pixel(2, 1, &Color::new(0., 0.5, 0.));
+    canvas.set_pixel(4, 2, &Color::new(-0.5, 0., 1.));
+    return canvas;
+  }
+
+  fn write_to_vec(canvas: &Canvas, encoding: PpmEncoding) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_ppm(canvas, &mut data, encoding, &DisplayTransform::linear())
+      .expect("Expected PPM to be written");
+    return data;
+  }
+
+  #[test]
+  fn write_ascii() {
+    let data = String::from_utf8(write_to_vec(&test_canvas(), PpmEncoding::Ascii)).unwrap();
+    assert_eq!(
+      data,
+      "P3\n5 3\n255\n\
+       255 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n\
+       0 0 0 0 0 0 0 128 0 0 0 0 0 0 0\n\
+       0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n"
+    );
+  }
+
+  #[test]
+  fn write_ascii_wraps_long_lines() {
+    let mut canvas = Canvas::new(10, 2);
+    for x in 0..10 {
+      for y in 0..2 {
+        canvas.set_pixel(x, y, &Color::new(1., 0.8, 0.6));
+      }
+    }
+    let data = String::from_utf8(write_to_vec(&canvas, PpmEncoding::Ascii)).unwrap();
+    let lines: Vec<&str> = data.lines().collect();
+    assert_eq!(
+      lines[3],
+      "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204"
+    );
+    assert_eq!(
+      lines[4],
+      "153 255 204 153 255 204 153 255 204 153 255 204 153"
+    );
+    assert!(lines.iter().all(|line| line.len() <= MAX_LINE_LENGTH));
+    assert!(data.ends_with('\n'));
+  }
+
+  #[test]
+  fn write_binary() {
+    let data = write_to_vec(&test_canvas(), PpmEncoding::Binary);
+    let header = b"P6\n5 3\n255\n";
+    assert_eq!(&data[..header.len()], header);
+    assert_eq!(data.len(), header.len() + 5 * 3 * 3);
+    assert_eq!(&data[header.len()..header.len() + 3], &[255, 0, 0]);
+  }
+
+  #[test]
+  fn write_read_round_trip() {
+    for encoding in [PpmEncoding::Ascii, PpmEncoding::Binary].iter() {
+      let canvas = read_ppm(&write_to_vec(&test_canvas(), *encoding)).expect("Expected valid PPM");
+      assert_eq!(canvas.width(), 5);
+      assert_eq!(canvas.height(), 3);
+      assert_eq!(canvas.pixel(0, 0), &Color::red());
+      assert_eq!(canvas.pixel(2, 1), &Color::new(0., 128. / 255., 0.));
+      assert_eq!(canvas.pixel(4, 2), &Color::blue());
+    }
+  }
+
+  #[test]
+  fn write_applies_display_transform() {
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(0, 0, &Color::new(0.5, 0.5, 0.5));
+    canvas.set_pixel(1, 0, &Color::new(3., 1., 0.));
+    let display = DisplayTransform::default().with_curve(ToneCurve::Reinhard);
+    let mut data = Vec::new();
+    write_ppm(&canvas, &mut data, PpmEncoding::Binary, &display)
+      .expect("Expected PPM to be written");
+    assert_eq!(&data[11..], &[156, 156, 156, 225, 188, 0]);
+    assert_eq!(canvas.pixel(1, 0), &Color::new(3., 1., 0.));
+  }
+
+  #[test]
+  fn write_returns_io_errors() {
+    for encoding in [PpmEncoding::Ascii, PpmEncoding::Binary].iter() {
+      let writer = FailingWriter { remaining: 12 };
+      let result = write_ppm(&test_canvas(), writer, *encoding, &Default::default());
+      assert!(matches!(result, Err(FormatError::Io(_))));
+    }
+  }
+
+  #[test]
+  fn read_ascii() {
+    let data = b"P3\n# comment\n2 1\n255\n255 0 0  0 51 255\n";
+    let canvas = read_ppm(data).expect("Expected valid PPM");
+    assert_eq!(canvas.width(), 2);
+    assert_eq!(canvas.height(), 1);
+    assert_eq!(canvas.pixel(0, 0), &Color::red());
+    assert_eq!(canvas.pixel(1, 0), &Color::new(0., 0.2, 1.));
+  }
+
+  #[test]
+  fn read_binary() {
+    let mut data = b"P6 1 2 255\n".to_vec();
+    data.extend_from_slice(&[0, 255, 0, 255, 255, 255]);
+    let canvas = read_ppm(&data).expect("Expected valid PPM");
+    assert_eq!(canvas.pixel(0, 0), &Color::green());
+    assert_eq!(canvas.pixel(0, 1), &Color::white());
+  }
+
+  #[test]
+  fn read_binary_sixteen_bit() {
+    let mut data = b"P6 1 1 65535\n".to_vec();
+    data.extend_from_slice(&[0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+    let canvas = read_ppm(&data).expect("Expected valid PPM");
+    assert_eq!(canvas.pixel(0, 0), &Color::new(1., 32768. / 65535., 0.));
+  }
+
+  #[test]
+  fn read_rejects_wrong_magic() {
+    assert!(read_ppm(b"P5 1 1 255\n\0").is_err());
+  }
+
+  #[test]
+  fn read_rejects_truncated_data() {
+    assert!(read_ppm(b"P3 2 1 255 0 0 0").is_err());
+    assert!(read_ppm(b"P6 2 1 255\n\0\0\0").is_err());
+  }
+
+  #[test]
+  fn read_rejects_sizes_larger_than_data() {
+    assert!(read_ppm(b"P3\n100000 100000\n255\n1 2 3\n").is_err());
+    assert!(read_ppm(b"P6\n100000 100000\n255\n\0\0\0").is_err());
+    assert!(read_ppm(b"P3\n6148914691236517206 1\n255\n1 2 3\n").is_err());
+    assert!(read_ppm(b"P6\n6148914691236517206 1\n255\n\0\0\0").is_err());
+  }
+
+  #[test]
+  fn read_rejects_sample_above_maximum() {
+    assert!(read_ppm(b"P3 1 1 15 16 0 0").is_err());
+  }
+}
@@ -0,0 +1,545 @@
+use super::{adler32, FormatError};
+
+const LENGTH_BASE: [u16; 29] = [
+  3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+  163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+  0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+  1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049,
+  3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+  0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// Order in which code length code lengths are stored in dynamic Huffman block headers.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+  16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Largest ratio of decompressed to compressed size that deflate can reach.
+pub const MAX_RATIO: usize = 1032;
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: usize = 15;
+/// Number of earlier positions with the same hash to try before settling for the best match.
+const MAX_CHAIN: usize = 64;
+
+/// Compresses `data` into a zlib stream using LZ77 and the fixed Huffman codes of deflate.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+  let mut writer = BitWriter::new();
+  // CMF: deflate with a 32 KiB window. FLG: default compression, no dictionary.
+  writer.bytes.extend_from_slice(&[0x78, 0x9c]);
+  writer.write_bits(1, 1);
+  writer.write_bits(1, 2);
+
+  let mut head = vec![u32::MAX; 1 << HASH_BITS];
+  let mut previous = vec![u32::MAX; WINDOW_SIZE];
+  let hash = |position: usize| {
+    let value =
+      (data[position] as u32) << 16 | (data[position + 1] as u32) << 8 | data[position + 2] as u32;
+    return (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+  };
+  let insert = |position: usize, head: &mut Vec<u32>, previous: &mut Vec<u32>| {
+    if position + MIN_MATCH <= data.len() {
+      let key = hash(position);
+      previous[position % WINDOW_SIZE] = head[key];
+      head[key] = position as u32;
+    }
+  };
+
+  let mut position = 0;
+  while position < data.len() {
+    let mut best_length = 0;
+    let mut best_distance = 0;
+    if position + MIN_MATCH <= data.len() {
+      let max_length = MAX_MATCH.min(data.len() - position);
+      let mut candidate = head[hash(position)];
+      let mut chain = 0;
+      while candidate != u32::MAX && chain < MAX_CHAIN {
+        let candidate_position = candidate as usize;
+        let distance = position - candidate_position;
+        if distance > WINDOW_SIZE {
+          break;
+        }
+        let mut length = 0;
+        while length < max_length && data[candidate_position + length] == data[position + length] {
+          length += 1;
+        }
+        if length > best_length {
+          best_length = length;
+          best_distance = distance;
+          if length == max_length {
+            break;
+          }
+        }
+        let next = previous[candidate_position % WINDOW_SIZE];
+        if next != u32::MAX && next as usize >= candidate_position {
+          break;
+        }
+        candidate = next;
+        chain += 1;
+      }
+    }
+
+    if best_length >= MIN_MATCH {
+      writer.write_length(best_length);
+      writer.write_distance(best_distance);
+      for offset in 0..best_length {
+        insert(position + offset, &mut head, &mut previous);
+      }
+      position += best_length;
+    } else {
+      writer.write_literal(data[position] as u16);
+      insert(position, &mut head, &mut previous);
+      position += 1;
+    }
+  }
+  writer.write_literal(256);
+  writer.align();
+
+  writer.bytes.extend_from_slice(&adler32(data).to_be_bytes());
+  return writer.bytes;
+}
+
+/// Decompresses a zlib stream, supporting every deflate block type.
+///
+/// Fails once the output grows past `max_length` bytes, so streams from untrusted files cannot
+/// exhaust memory.
+pub fn decompress(data: &[u8], max_length: usize) -> Result<Vec<u8>, FormatError> {
+  if data.len() < 6 {
+    return Err(invalid("zlib stream is too short"));
+  }
+  let (cmf, flg) = (data[0], data[1]);
+  if cmf & 0x0f != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+    return Err(invalid("invalid zlib header"));
+  }
+  if flg & 0x20 != 0 {
+    return Err(FormatError::Unsupported(
+      "zlib preset dictionaries are not supported".to_string(),
+    ));
+  }
+
+  let mut reader = BitReader::new(&data[2..]);
+  let mut output = Vec::new();
+  loop {
+    let last = reader.bits(1)? == 1;
+    match reader.bits(2)? {
+      0 => {
+        reader.align();
+        let length = reader.bits(16)? as usize;
+        let complement = reader.bits(16)? as usize;
+        if length != !complement & 0xffff {
+          return Err(invalid("stored block length mismatch"));
+        }
+        if output.len() + length > max_length {
+          return Err(too_long());
+        }
+        for _ in 0..length {
+          output.push(reader.bits(8)? as u8);
+        }
+      }
+      1 => {
+        let (literals, distances) = fixed_huffman();
+        inflate_block(&mut reader, &mut output, max_length, &literals, &distances)?;
+      }
+      2 => {
+        let (literals, distances) = dynamic_huffman(&mut reader)?;
+        inflate_block(&mut reader, &mut output, max_length, &literals, &distances)?;
+      }
+      _ => return Err(invalid("invalid deflate block type")),
+    }
+    if last {
+      break;
+    }
+  }
+
+  reader.align();
+  let checksum = (0..4).try_fold(0u32, |checksum, _| {
+    return Ok::<u32, FormatError>(checksum << 8 | reader.bits(8)?);
+  })?;
+  if checksum != adler32(&output) {
+    return Err(invalid("zlib checksum mismatch"));
+  }
+  return Ok(output);
+}
+
+fn invalid(message: &str) -> FormatError {
+  return FormatError::InvalidData(message.to_string());
+}
+
+fn too_long() -> FormatError {
+  return invalid("zlib stream decompresses to more data than expected");
+}
+
+struct BitWriter {
+  bytes: Vec<u8>,
+  buffer: u32,
+  count: u32,
+}
+impl BitWriter {
+  fn new() -> Self {
+    return Self {
+      bytes: Vec::new(),
+      buffer: 0,
+      count: 0,
+    };
+  }
+
+  /// Writes the lowest `count` bits of `value`, least significant bit first.
+  fn write_bits(&mut self, value: u32, count: u32) {
+    self.buffer |= value << self.count;
+    self.count += count;
+    while self.count >= 8 {
+      self.bytes.push(self.buffer as u8);
+      self.buffer >>= 8;
+      self.count -= 8;
+    }
+  }
+
+  /// Writes a Huffman code, which is stored most significant bit first.
+  fn write_code(&mut self, code: u32, length: u32) {
+    let reversed = code.reverse_bits() >> (32 - length);
+    self.write_bits(reversed, length);
+  }
+
+  fn write_literal(&mut self, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+      0..=143 => self.write_code(0x30 + symbol, 8),
+      144..=255 => self.write_code(0x190 + symbol - 144, 9),
+      256..=279 => self.write_code(symbol - 256, 7),
+      _ => self.write_code(0xc0 + symbol - 280, 8),
+    }
+  }
+
+  fn write_length(&mut self, length: usize) {
+    let index = LENGTH_BASE
+      .iter()
+      .rposition(|&base| base as usize <= length)
+      .expect("Match length is at least 3");
+    self.write_literal(257 + index as u16);
+    let extra = LENGTH_EXTRA[index] as u32;
+    self.write_bits((length - LENGTH_BASE[index] as usize) as u32, extra);
+  }
+
+  fn write_distance(&mut self, distance: usize) {
+    let index = DISTANCE_BASE
+      .iter()
+      .rposition(|&base| base as usize <= distance)
+      .expect("Match distance is at least 1");
+    self.write_code(index as u32, 5);
+    let extra = DISTANCE_EXTRA[index] as u32;
+    self.write_bits((distance - DISTANCE_BASE[index] as usize) as u32, extra);
+  }
+
+  fn align(&mut self) {
+    if self.count > 0 {
+      self.write_bits(0, 8 - self.count);
+    }
+  }
+}
+
+struct BitReader<'a> {
+  data: &'a [u8],
+  position: usize,
+  bit: u32,
+}
+impl<'a> BitReader<'a> {
+  fn new(data: &'a [u8]) -> Self {
+    return Self {
+      data,
+      position: 0,
+      bit: 0,
+    };
+  }
+
+  fn bits(&mut self, count: u32) -> Result<u32, FormatError> {
+    let mut value = 0;
+    for i in 0..count {
+      let byte = *self
+        .data
+        .get(self.position)
+        .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+      value |= ((byte >> self.bit) as u32 & 1) << i;
+      self.bit += 1;
+      if self.bit == 8 {
+        self.bit = 0;
+        self.position += 1;
+      }
+    }
+    return Ok(value);
+  }
+
+  fn align(&mut self) {
+    if self.bit > 0 {
+      self.bit = 0;
+      self.position += 1;
+    }
+  }
+}
+
+/// Canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+  /// Number of codes of each length.
+  counts: [u16; 16],
+  /// Symbols ordered by code.
+  symbols: Vec<u16>,
+}
+impl Huffman {
+  fn new(lengths: &[u8]) -> Result<Self, FormatError> {
+    let mut counts = [0u16; 16];
+    for &length in lengths {
+      counts[length as usize] += 1;
+    }
+    counts[0] = 0;
+
+    let mut left: i32 = 1;
+    for &count in counts.iter().skip(1) {
+      left = left * 2 - count as i32;
+      if left < 0 {
+        return Err(invalid("over-subscribed Huffman code"));
+      }
+    }
+
+    let mut offsets = [0u16; 16];
+    for length in 1..15 {
+      offsets[length + 1] = offsets[length] + counts[length];
+    }
+    let mut symbols = vec![0; lengths.len()];
+    for (symbol, &length) in lengths.iter().enumerate() {
+      if length != 0 {
+        symbols[offsets[length as usize] as usize] = symbol as u16;
+        offsets[length as usize] += 1;
+      }
+    }
+    return Ok(Self { counts, symbols });
+  }
+
+  fn decode(&self, reader: &mut BitReader) -> Result<u16, FormatError> {
+    let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+    for length in 1..16 {
+      code |= reader.bits(1)? as i32;
+      let count = self.counts[length] as i32;
+      if code - count < first {
+        return Ok(self.symbols[(index + code - first) as usize]);
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+    return Err(invalid("invalid Huffman code"));
+  }
+}
+
+fn fixed_huffman() -> (Huffman, Huffman) {
+  let mut lengths = [0u8; 288];
+  for (symbol, length) in lengths.iter_mut().enumerate() {
+    *length = match symbol {
+      0..=143 => 8,
+      144..=255 => 9,
+      256..=279 => 7,
+      _ => 8,
+    };
+  }
+  let literals = Huffman::new(&lengths).expect("Fixed literal code is valid");
+  let distances = Huffman::new(&[5; 30]).expect("Fixed distance code is valid");
+  return (literals, distances);
+}
+
+fn dynamic_huffman(reader: &mut BitReader) -> Result<(Huffman, Huffman), FormatError> {
+  let literal_count = reader.bits(5)? as usize + 257;
+  let distance_count = reader.bits(5)? as usize + 1;
+  let code_length_count = reader.bits(4)? as usize + 4;
+
+  let mut code_length_lengths = [0u8; 19];
+  for &index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+    code_length_lengths[index] = reader.bits(3)? as u8;
+  }
+  let code_lengths = Huffman::new(&code_length_lengths)?;
+
+  let mut lengths = Vec::with_capacity(literal_count + distance_count);
+  while lengths.len() < literal_count + distance_count {
+    let symbol = code_lengths.decode(reader)?;
+    let (value, repeat) = match symbol {
+      0..=15 => (symbol as u8, 1),
+      16 => {
+        let previous = *lengths
+          .last()
+          .ok_or_else(|| invalid("repeated code length without a previous length"))?;
+        (previous, 3 + reader.bits(2)?)
+      }
+      17 => (0, 3 + reader.bits(3)?),
+      _ => (0, 11 + reader.bits(7)?),
+    };
+    for _ in 0..repeat {
+      lengths.push(value);
+    }
+  }
+  if lengths.len() > literal_count + distance_count {
+    return Err(invalid("too many code lengths"));
+  }
+
+  let literals = Huffman::new(&lengths[..literal_count])?;
+  let distances = Huffman::new(&lengths[literal_count..])?;
+  return Ok((literals, distances));
+}
+
+fn inflate_block(
+  reader: &mut BitReader,
+  output: &mut Vec<u8>,
+  max_length: usize,
+  literals: &Huffman,
+  distances: &Huffman,
+) -> Result<(), FormatError> {
+  loop {
+    let symbol = literals.decode(reader)? as usize;
+    if symbol < 256 {
+      if output.len() == max_length {
+        return Err(too_long());
+      }
+      output.push(symbol as u8);
+    } else if symbol == 256 {
+      return Ok(());
+    } else {
+      let index = symbol - 257;
+      if index >= LENGTH_BASE.len() {
+        return Err(invalid("invalid length symbol"));
+      }
+      let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+      let index = distances.decode(reader)? as usize;
+      if index >= DISTANCE_BASE.len() {
+        return Err(invalid("invalid distance symbol"));
+      }
+      let distance =
+        DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+      if distance > output.len() {
+        return Err(invalid("distance reaches before start of output"));
+      }
+
+      if output.len() + length > max_length {
+        return Err(too_long());
+      }
+      let start = output.len() - distance;
+      for offset in 0..length {
+        output.push(output[start + offset]);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::Rng;
+
+  fn assert_round_trip(data: &[u8]) {
+    let compressed = compress(data);
+    assert_eq!(
+      decompress(&compressed, usize::MAX).expect("Expected valid stream"),
+      data
+    );
+  }
+
+  #[test]
+  fn round_trip_empty() {
+    assert_round_trip(b"");
+  }
+
+  #[test]
+  fn round_trip_text() {
+    assert_round_trip(b"Hello, hello, hello! Ray tracing is fun, ray tracing is fun.");
+  }
+
+  #[test]
+  fn round_trip_random_bytes() {
+    let mut rng = Rng::new(1);
+    let data: Vec<u8> = (0..50_000).map(|_| rng.below(256) as u8).collect();
+    assert_round_trip(&data);
+  }
+
+  #[test]
+  fn round_trip_long_runs() {
+    let mut data = vec![7u8; 100_000];
+    data.extend((0..70_000).map(|i| (i % 251) as u8));
+    assert_round_trip(&data);
+  }
+
+  #[test]
+  fn compress_shrinks_repetitive_data() {
+    let data = vec![42u8; 10_000];
+    assert!(compress(&data).len() < 200);
+  }
+
+  #[test]
+  fn decompress_stored_block() {
+    let data = [
+      0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2c, 0x02,
+      0x15,
+    ];
+    assert_eq!(
+      decompress(&data, usize::MAX).expect("Expected valid stream"),
+      b"hello"
+    );
+  }
+
+  #[test]
+  fn decompress_dynamic_block() {
+    // Random text over a skewed alphabet, compressed by zlib with dynamic Huffman codes.
+    let expected = "abbaadbabbabadcaabaababcbaabcaabacdbababcaacbaacaccaabbddabcdaabcbadadaaaaaaabacbc\
+                    aabcababbabadabddacabbbabcabdbbabbabcbaaaaacbaaacaadaaaacacaabbcaabbabddabaaaaaab\
+                    baaaabaabacaaaadcaaacbabcbbbabcbaabbb";
+    let data = [
+      0x78, 0xda, 0x35, 0x8e, 0x89, 0x11, 0x00, 0x21, 0x0c, 0x02, 0x6b, 0xe5, 0xe9, 0xbf, 0x86,
+      0x03, 0x72, 0xaa, 0x93, 0x87, 0x84, 0x1d, 0x41, 0x02, 0x26, 0x92, 0x08, 0x0b, 0x89, 0x79,
+      0x54, 0xd3, 0x3a, 0x75, 0xb8, 0xba, 0x9a, 0xa0, 0xaa, 0xb4, 0x23, 0x19, 0x5b, 0x74, 0xee,
+      0x9d, 0x6c, 0xcf, 0x24, 0x3c, 0x20, 0xba, 0x99, 0x96, 0x43, 0xd0, 0xbc, 0x41, 0x51, 0x38,
+      0x62, 0xa6, 0xe7, 0x17, 0x46, 0x5e, 0x98, 0x8d, 0x3f, 0x94, 0x87, 0x2e, 0xbd, 0x55, 0x3f,
+      0x59, 0x67, 0x20, 0x0f, 0x95, 0xe2, 0x03, 0x05, 0x70, 0x4c, 0x6c,
+    ];
+    assert_eq!(
+      decompress(&data, usize::MAX).expect("Expected valid stream"),
+      expected.as_bytes()
+    );
+  }
+
+  #[test]
+  fn decompress_rejects_bad_checksum() {
+    let mut compressed = compress(b"checksum");
+    let last = compressed.len() - 1;
+    compressed[last] ^= 0xff;
+    assert!(decompress(&compressed, usize::MAX).is_err());
+  }
+
+  #[test]
+  fn decompress_rejects_output_past_limit() {
+    let compressed = compress(&[0u8; 1000]);
+    assert_eq!(
+      decompress(&compressed, 1000)
+        .expect("Expected valid stream")
+        .len(),
+      1000
+    );
+    assert!(decompress(&compressed, 999).is_err());
+  }
+
+  #[test]
+  fn decompress_rejects_bad_header() {
+    assert!(decompress(
+      &[0x78, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+      usize::MAX
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn decompress_rejects_truncated_stream() {
+    let compressed = compress(b"truncated stream of data");
+    assert!(decompress(&compressed[..compressed.len() / 2], usize::MAX).is_err());
+  }
+}
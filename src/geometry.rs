@@ -1,12 +1,21 @@
+pub mod aabb;
+pub use aabb::*;
+
 pub mod material;
 pub use material::*;
 
 pub mod matrix;
 pub use matrix::*;
 
+pub mod normal_map;
+pub use normal_map::*;
+
 pub mod point;
 pub use point::*;
 
+pub mod quaternion;
+pub use quaternion::*;
+
 pub mod ray;
 pub use ray::*;
 
@@ -0,0 +1,359 @@
+use crate::geometry::{Matrix, Point, Ray, Vector};
+use crate::simd;
+use crate::utils::Float;
+
+/// Axis-aligned bounding box, the region between the corners `min` and `max`.
+///
+/// Bounds can be infinite along any axis, like those of a [`Plane`](crate::shapes::Plane), and
+/// [`Aabb::empty`] contains no point at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+  pub min: Point,
+  pub max: Point,
+}
+impl Aabb {
+  /// Returns the box between two opposite corners, given in any order.
+  pub fn new(a: Point, b: Point) -> Self {
+    return Self {
+      min: Point::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+      max: Point::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+    };
+  }
+
+  /// Returns the box that contains no points. Its union with another box is the other box.
+  pub fn empty() -> Self {
+    return Self {
+      min: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+      max: Point::new(
+        Float::NEG_INFINITY,
+        Float::NEG_INFINITY,
+        Float::NEG_INFINITY,
+      ),
+    };
+  }
+
+  /// Returns the box that contains all of space.
+  pub fn infinite() -> Self {
+    return Self {
+      min: Point::new(
+        Float::NEG_INFINITY,
+        Float::NEG_INFINITY,
+        Float::NEG_INFINITY,
+      ),
+      max: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+    };
+  }
+
+  /// Returns the smallest box that contains every point.
+  pub fn from_points(points: &[Point]) -> Self {
+    return points.iter().fold(Self::empty(), |bounds, point| {
+      return bounds.union(&Self {
+        min: *point,
+        max: *point,
+      });
+    });
+  }
+
+  pub fn is_empty(&self) -> bool {
+    return self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z;
+  }
+
+  /// Returns whether the box has a finite size, which an empty box does not.
+  pub fn is_finite(&self) -> bool {
+    return !self.is_empty()
+      && [self.min, self.max]
+        .iter()
+        .all(|corner| corner.x.is_finite() && corner.y.is_finite() && corner.z.is_finite());
+  }
+
+  pub fn contains(&self, point: &Point) -> bool {
+    return (self.min.x..=self.max.x).contains(&point.x)
+      && (self.min.y..=self.max.y).contains(&point.y)
+      && (self.min.z..=self.max.z).contains(&point.z);
+  }
+
+  /// Returns the smallest box that contains both boxes.
+  pub fn union(&self, other: &Self) -> Self {
+    return Self {
+      min: Point::new(
+        self.min.x.min(other.min.x),
+        self.min.y.min(other.min.y),
+        self.min.z.min(other.min.z),
+      ),
+      max: Point::new(
+        self.max.x.max(other.max.x),
+        self.max.y.max(other.max.y),
+        self.max.z.max(other.max.z),
+      ),
+    };
+  }
+
+  pub fn size(&self) -> Vector {
+    return self.max - self.min;
+  }
+
+  pub fn center(&self) -> Point {
+    return self.min + self.size() / 2.;
+  }
+
+  /// Returns the smallest axis-aligned box that contains this box transformed by `matrix`.
+  pub fn transform(&self, matrix: &Matrix) -> Self {
+    if self.is_empty() {
+      return *self;
+    }
+    // Every coordinate of the transformed corners is a sum of one term per axis, so the extremes
+    // come from the extremes of every term.
+    let min = [self.min.x, self.min.y, self.min.z];
+    let max = [self.max.x, self.max.y, self.max.z];
+    let mut transformed_min = [0.; 3];
+    let mut transformed_max = [0.; 3];
+    for row in 0..3 {
+      transformed_min[row] = matrix[(row, 3)];
+      transformed_max[row] = matrix[(row, 3)];
+      for col in 0..3 {
+        let factor = matrix[(row, col)];
+        // Skipping zeros keeps infinite bounds from turning into NaN.
+        if factor == 0. {
+          continue;
+        }
+        let (a, b) = (factor * min[col], factor * max[col]);
+        transformed_min[row] += a.min(b);
+        transformed_max[row] += a.max(b);
+      }
+    }
+    return Self {
+      min: Point::new(transformed_min[0], transformed_min[1], transformed_min[2]),
+      max: Point::new(transformed_max[0], transformed_max[1], transformed_max[2]),
+    };
+  }
+
+  /// Returns the times at which a ray enters and leaves the box, which can be negative, or `None`
+  /// if it misses.
+  pub fn intersect(&self, ray: &Ray) -> Option<(Float, Float)> {
+    if self.is_empty() {
+      return None;
+    }
+    let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+    let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+    let min = [self.min.x, self.min.y, self.min.z];
+    let max = [self.max.x, self.max.y, self.max.z];
+    let (mut near, mut far) = (Float::NEG_INFINITY, Float::INFINITY);
+    for axis in 0..3 {
+      let inverse_direction = 1. / direction[axis];
+      let t1 = (min[axis] - origin[axis]) * inverse_direction;
+      let t2 = (max[axis] - origin[axis]) * inverse_direction;
+      // `min` and `max` ignore the NaN of a ray in the plane of a face.
+      near = near.max(t1.min(t2));
+      far = far.min(t1.max(t2));
+    }
+    return if near <= far { Some((near, far)) } else { None };
+  }
+}
+impl Default for Aabb {
+  fn default() -> Self {
+    return Self::empty();
+  }
+}
+
+/// Four axis-aligned boxes, stored by axis so a ray can be tested against all four at once with
+/// the vector instructions of [`simd`](crate::simd). `min[axis][index]` is the lower bound of box
+/// `index` along `axis`.
+///
+/// Unused slots can be left as in [`Boxes4::EMPTY`], which rays hit at most at infinity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Boxes4 {
+  pub min: [[Float; 4]; 3],
+  pub max: [[Float; 4]; 3],
+}
+impl Boxes4 {
+  /// Boxes shrunk to a point at infinity.
+  pub const EMPTY: Self = Self {
+    min: [[Float::INFINITY; 4]; 3],
+    max: [[Float::INFINITY; 4]; 3],
+  };
+
+  /// Sets box `index` to `bounds`. Empty bounds leave the slot empty.
+  pub fn set(&mut self, index: usize, bounds: &Aabb) {
+    if bounds.is_empty() {
+      return;
+    }
+    let min = [bounds.min.x, bounds.min.y, bounds.min.z];
+    let max = [bounds.max.x, bounds.max.y, bounds.max.z];
+    for axis in 0..3 {
+      self.min[axis][index] = min[axis];
+      self.max[axis][index] = max[axis];
+    }
+  }
+
+  /// Returns the time at which a ray enters each box within `t_min..=t_max`, or infinity for the
+  /// boxes it misses in that interval. `inverse_direction` holds the reciprocals of the
+  /// components of the ray's direction.
+  ///
+  /// The arrays are borrowed because a loop over many groups would otherwise copy them for every
+  /// group, and reading the copies back stalls the vector loads.
+  #[inline]
+  pub fn intersect(
+    &self,
+    origin: &[Float; 3],
+    inverse_direction: &[Float; 3],
+    t_min: Float,
+    t_max: Float,
+  ) -> [Float; 4] {
+    return simd::intersect_boxes4(origin, inverse_direction, self, t_min, t_max);
+  }
+}
+impl Default for Boxes4 {
+  fn default() -> Self {
+    return Self::EMPTY;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::consts::{FRAC_PI_4, SQRT_2};
+
+  fn unit_box() -> Aabb {
+    return Aabb::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.));
+  }
+
+  #[test]
+  fn init_new_orders_corners() {
+    let bounds = Aabb::new(Point::new(1., -2., 3.), Point::new(-1., 2., -3.));
+    assert_eq!(bounds.min, Point::new(-1., -2., -3.));
+    assert_eq!(bounds.max, Point::new(1., 2., 3.));
+    assert_eq!(bounds.size(), Vector::new(2., 4., 6.));
+    assert_eq!(bounds.center(), Point::origin());
+  }
+
+  #[test]
+  fn empty_and_infinite() {
+    assert!(Aabb::empty().is_empty());
+    assert!(!Aabb::empty().is_finite());
+    assert!(!Aabb::empty().contains(&Point::origin()));
+    assert!(!Aabb::infinite().is_empty());
+    assert!(!Aabb::infinite().is_finite());
+    assert!(Aabb::infinite().contains(&Point::new(1e30, -1e30, 0.)));
+    assert!(unit_box().is_finite());
+  }
+
+  #[test]
+  fn from_points_and_union() {
+    let bounds = Aabb::from_points(&[
+      Point::new(1., 0., 0.),
+      Point::new(-2., 3., 0.5),
+      Point::new(0., -1., 4.),
+    ]);
+    assert_eq!(
+      bounds,
+      Aabb::new(Point::new(-2., -1., 0.), Point::new(1., 3., 4.))
+    );
+    assert_eq!(Aabb::from_points(&[]), Aabb::empty());
+    assert_eq!(Aabb::empty().union(&unit_box()), unit_box());
+    assert_eq!(
+      unit_box().union(&Aabb::new(Point::new(2., 2., 2.), Point::new(3., 3., 3.))),
+      Aabb::new(Point::new(-1., -1., -1.), Point::new(3., 3., 3.))
+    );
+  }
+
+  #[test]
+  fn contains() {
+    assert!(unit_box().contains(&Point::new(1., 0., -1.)));
+    assert!(!unit_box().contains(&Point::new(1.1, 0., 0.)));
+  }
+
+  #[test]
+  fn transform() {
+    let matrix = Matrix::identity()
+      .scale(2., 1., 1.)
+      .rotate_z(FRAC_PI_4)
+      .translate(0., 0., 5.);
+    let bounds = unit_box().transform(&matrix);
+    let extent = 3. / SQRT_2;
+    assert_eq!(
+      bounds,
+      Aabb::new(
+        Point::new(-extent, -extent, 4.),
+        Point::new(extent, extent, 6.)
+      )
+    );
+  }
+
+  #[test]
+  fn transform_infinite_and_empty() {
+    let matrix = Matrix::identity().scale(2., 2., 2.).translate(1., 2., 3.);
+    assert_eq!(Aabb::infinite().transform(&matrix), Aabb::infinite());
+    assert!(Aabb::empty().transform(&matrix).is_empty());
+    // A slab infinite in x and z stays thin in y when only translated.
+    let slab = Aabb::new(
+      Point::new(Float::NEG_INFINITY, 0., Float::NEG_INFINITY),
+      Point::new(Float::INFINITY, 0., Float::INFINITY),
+    );
+    let moved = slab.transform(&Matrix::identity().translate(0., 1., 0.));
+    assert_eq!((moved.min.y, moved.max.y), (1., 1.));
+  }
+
+  #[test]
+  fn intersect() {
+    let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+    assert_eq!(unit_box().intersect(&ray), Some((4., 6.)));
+    let ray = Ray::new(Point::new(0., 0., 0.), Vector::new(0., 0., 1.));
+    assert_eq!(unit_box().intersect(&ray), Some((-1., 1.)));
+    let ray = Ray::new(Point::new(2., 0., -5.), Vector::new(0., 0., 1.));
+    assert_eq!(unit_box().intersect(&ray), None);
+    assert_eq!(Aabb::empty().intersect(&ray), None);
+    assert_eq!(
+      Aabb::infinite().intersect(&ray),
+      Some((Float::NEG_INFINITY, Float::INFINITY))
+    );
+  }
+
+  #[test]
+  fn intersect_four_boxes() {
+    let mut boxes = Boxes4::EMPTY;
+    boxes.set(
+      0,
+      &Aabb::new(Point::new(-1., -1., 4.), Point::new(1., 1., 6.)),
+    );
+    boxes.set(
+      1,
+      &Aabb::new(Point::new(2., 2., 4.), Point::new(3., 3., 6.)),
+    );
+    boxes.set(2, &unit_box());
+    boxes.set(3, &Aabb::empty());
+    let origin = [0., 0., 0.];
+    let inverse_direction = [Float::INFINITY, Float::INFINITY, 1.];
+    assert_eq!(
+      boxes.intersect(&origin, &inverse_direction, 0., Float::INFINITY),
+      [4., Float::INFINITY, 0., Float::INFINITY]
+    );
+  }
+
+  #[test]
+  fn intersect_four_boxes_within_interval() {
+    let mut boxes = Boxes4::EMPTY;
+    boxes.set(
+      0,
+      &Aabb::new(Point::new(-1., -1., 4.), Point::new(1., 1., 6.)),
+    );
+    boxes.set(
+      1,
+      &Aabb::new(Point::new(-1., -1., -6.), Point::new(1., 1., -4.)),
+    );
+    let origin = [0., 0., 0.];
+    let inverse_direction = [Float::INFINITY, Float::INFINITY, 1.];
+    let missed = Float::INFINITY;
+    assert_eq!(
+      boxes.intersect(&origin, &inverse_direction, 0., 3.),
+      [missed; 4]
+    );
+    assert_eq!(
+      boxes.intersect(&origin, &inverse_direction, 0., 5.),
+      [4., missed, missed, missed]
+    );
+    assert_eq!(
+      boxes.intersect(&origin, &inverse_direction, -5., 3.),
+      [missed, -5., missed, missed]
+    );
+  }
+}
@@ -1,22 +1,24 @@
-use crate::geometry::Point;
+use crate::geometry::{NormalMap, Point, TangentFrame, Vector};
 use crate::textures::Texture;
+use crate::utils::Float;
 use crate::Color;
 
 #[derive(Debug)]
 pub struct Material {
   texture: Box<dyn Texture>,
-  ambience: f64,
-  diffuse: f64,
-  specular: f64,
-  shininess: f64,
+  ambience: Float,
+  diffuse: Float,
+  specular: Float,
+  shininess: Float,
+  normal_map: Option<NormalMap>,
 }
 impl Material {
   pub fn new(
     texture: Box<dyn Texture>,
-    ambience: f64,
-    diffuse: f64,
-    specular: f64,
-    shininess: f64,
+    ambience: Float,
+    diffuse: Float,
+    specular: Float,
+    shininess: Float,
   ) -> Self {
     return Self {
       texture,
@@ -24,30 +26,76 @@ impl Material {
       diffuse,
       specular,
       shininess,
+      normal_map: None,
     };
   }
 
+  pub fn with_normal_map(mut self, normal_map: NormalMap) -> Self {
+    self.normal_map = Some(normal_map);
+    return self;
+  }
+
   pub fn color_at(&self, point: &Point) -> Color {
     return self.texture.color_at(point);
   }
 
-  pub fn ambience(&self) -> f64 {
+  pub fn ambience(&self) -> Float {
     return self.ambience;
   }
-  pub fn diffuse(&self) -> f64 {
+  pub fn set_ambience(&mut self, ambience: Float) {
+    self.ambience = ambience;
+  }
+  pub fn diffuse(&self) -> Float {
     return self.diffuse;
   }
-  pub fn specular(&self) -> f64 {
+  pub fn set_diffuse(&mut self, diffuse: Float) {
+    self.diffuse = diffuse;
+  }
+  pub fn specular(&self) -> Float {
     return self.specular;
   }
-  pub fn shininess(&self) -> f64 {
+  pub fn set_specular(&mut self, specular: Float) {
+    self.specular = specular;
+  }
+  pub fn shininess(&self) -> Float {
     return self.shininess;
   }
+  pub fn set_shininess(&mut self, shininess: Float) {
+    self.shininess = shininess;
+  }
+  pub fn normal_map(&self) -> Option<&NormalMap> {
+    return self.normal_map.as_ref();
+  }
+
+  /// Returns whether the transformations of the material's texture and normal map can be
+  /// inverted. See [`Texture::has_invertible_transforms`].
+  pub fn has_invertible_textures(&self) -> bool {
+    return self.texture.has_invertible_transforms()
+      && self
+        .normal_map
+        .iter()
+        .all(|normal_map| normal_map.texture().has_invertible_transforms());
+  }
+
+  /// Applies the material's normal map, if any, to the surface normal in `frame_world`.
+  ///
+  /// See [`NormalMap::perturb`](NormalMap::perturb).
+  pub fn perturb_normal(
+    &self,
+    point_object: &Point,
+    frame_object: &TangentFrame,
+    frame_world: &TangentFrame,
+  ) -> Vector {
+    return match &self.normal_map {
+      None => frame_world.normal,
+      Some(normal_map) => normal_map.perturb(point_object, frame_object, frame_world),
+    };
+  }
 
   /// Returns the material's shading properties as a convenient tuple.
   ///
   /// The tuple is formatted as `(ambience, diffuse, specular, shininess)`.  
-  pub fn shading_properties(&self) -> (f64, f64, f64, f64) {
+  pub fn shading_properties(&self) -> (Float, Float, Float, Float) {
     return (
       self.ambience(),
       self.diffuse(),
@@ -74,6 +122,44 @@ mod tests {
     assert_eq!(material.diffuse(), 0.3);
     assert_eq!(material.specular(), 0.8);
     assert_eq!(material.shininess(), 100.);
+    assert!(material.normal_map().is_none());
+  }
+
+  #[test]
+  fn set_shading_properties() {
+    let mut material = Material::default();
+    material.set_ambience(0.2);
+    material.set_diffuse(0.4);
+    material.set_specular(0.);
+    material.set_shininess(10.);
+    assert_eq!(material.shading_properties(), (0.2, 0.4, 0., 10.));
+  }
+
+  #[test]
+  fn init_with_normal_map() {
+    let material =
+      Material::default().with_normal_map(NormalMap::Tangent(Box::new(Color::white())));
+    assert!(matches!(material.normal_map(), Some(NormalMap::Tangent(_))));
+  }
+
+  #[test]
+  fn perturb_normal_without_normal_map() {
+    let frame = TangentFrame::new(Vector::new(1., 0., 0.), Vector::new(0., 1., 0.));
+    assert_eq!(
+      Material::default().perturb_normal(&Point::origin(), &frame, &frame),
+      Vector::new(0., 1., 0.)
+    );
+  }
+
+  #[test]
+  fn perturb_normal_with_normal_map() {
+    let frame = TangentFrame::new(Vector::new(1., 0., 0.), Vector::new(0., 1., 0.));
+    let material =
+      Material::default().with_normal_map(NormalMap::Tangent(Box::new(Color::new(0.5, 1., 0.5))));
+    assert_eq!(
+      material.perturb_normal(&Point::origin(), &frame, &frame),
+      Vector::new(0., 0., 1.)
+    );
   }
 
   #[test]
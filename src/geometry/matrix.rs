@@ -1,16 +1,34 @@
-use crate::geometry::{Point, Ray, Vector};
-use crate::utils::approx_equals;
+use crate::geometry::{Point, Quaternion, Ray, Vector};
+use crate::simd;
+use crate::utils::{approx_equals, Float, Lerp};
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::ops::{Index, IndexMut, Mul};
 
+/// Error returned when inverting a matrix that has no inverse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SingularMatrixError;
+impl Display for SingularMatrixError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return write!(f, "Matrix is singular and not invertible");
+  }
+}
+impl Error for SingularMatrixError {}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Matrix {
-  elements: [[f64; 4]; 4],
+  elements: [[Float; 4]; 4],
 }
 impl Matrix {
-  pub fn new(elements: [[f64; 4]; 4]) -> Self {
+  pub fn new(elements: [[Float; 4]; 4]) -> Self {
     return Self { elements };
   }
 
+  /// Returns the rows of the matrix.
+  pub fn elements(&self) -> &[[Float; 4]; 4] {
+    return &self.elements;
+  }
+
   pub fn identity() -> Self {
     return Self::new([
       [1., 0., 0., 0.],
@@ -29,49 +47,35 @@ impl Matrix {
     ]);
   }
 
+  /// Returns the inverse of the matrix.
+  ///
+  /// Panics if the matrix is singular. Use [`try_inverse`](Matrix::try_inverse) for matrices
+  /// that do not come from validated input.
   pub fn inverse(&self) -> Self {
-    let mut inverse = Self::identity();
-    let mut copy = *self;
-
-    for i in 0..4 {
-      let mut pivot = copy[(i, i)];
-
-      if pivot == 0. {
-        for j in i..4 {
-          if copy[(j, i)] != 0. {
-            copy.elements.swap(i, j);
-            inverse.elements.swap(i, j);
-          }
-        }
-
-        pivot = copy[(i, i)];
-        if pivot == 0. {
-          panic!("Matrix is singular and not invertible");
-        }
-      }
-
-      if pivot != 1. {
-        for j in 0..4 {
-          copy[(i, j)] /= pivot;
-          inverse[(i, j)] /= pivot;
-        }
-      }
-
-      for j in 0..4 {
-        if j != i && copy[(j, i)] != 0. {
-          let scalar = copy[(j, i)];
-          for k in 0..4 {
-            copy[(j, k)] -= scalar * copy[(i, k)];
-            inverse[(j, k)] -= scalar * inverse[(i, k)];
-          }
-        }
-      }
+    return match self.try_inverse() {
+      Ok(inverse) => inverse,
+      Err(error) => panic!("{}", error),
+    };
+  }
+
+  /// Returns the inverse of the matrix, or an error if it is singular or has elements that are
+  /// not finite.
+  pub fn try_inverse(&self) -> Result<Self, SingularMatrixError> {
+    if self
+      .elements
+      .iter()
+      .flatten()
+      .any(|element| !element.is_finite())
+    {
+      return Err(SingularMatrixError);
     }
-
-    return inverse;
+    return match simd::mat4_inverse(&self.elements) {
+      Some(elements) => Ok(Self::new(elements)),
+      None => Err(SingularMatrixError),
+    };
   }
 
-  pub fn translate(self, x: f64, y: f64, z: f64) -> Self {
+  pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
     let translation = Self::new([
       [1., 0., 0., x],
       [0., 1., 0., y],
@@ -82,7 +86,7 @@ impl Matrix {
     return translation * self;
   }
 
-  pub fn scale(self, x: f64, y: f64, z: f64) -> Self {
+  pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
     let scaling = Self::new([
       [x, 0., 0., 0.],
       [0., y, 0., 0.],
@@ -93,7 +97,7 @@ impl Matrix {
     return scaling * self;
   }
 
-  pub fn rotate_x(self, angle: f64) -> Self {
+  pub fn rotate_x(self, angle: Float) -> Self {
     let (angle_sin, angle_cos) = angle.sin_cos();
     let rotation = Self::new([
       [1., 0., 0., 0.],
@@ -105,7 +109,7 @@ impl Matrix {
     return rotation * self;
   }
 
-  pub fn rotate_y(self, angle: f64) -> Self {
+  pub fn rotate_y(self, angle: Float) -> Self {
     let (angle_sin, angle_cos) = angle.sin_cos();
     let rotation = Self::new([
       [angle_cos, 0., angle_sin, 0.],
@@ -117,7 +121,7 @@ impl Matrix {
     return rotation * self;
   }
 
-  pub fn rotate_z(self, angle: f64) -> Self {
+  pub fn rotate_z(self, angle: Float) -> Self {
     let (angle_sin, angle_cos) = angle.sin_cos();
     let rotation = Self::new([
       [angle_cos, -angle_sin, 0., 0.],
@@ -129,7 +133,11 @@ impl Matrix {
     return rotation * self;
   }
 
-  pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+  pub fn rotate(self, rotation: &Quaternion) -> Self {
+    return rotation.to_matrix() * self;
+  }
+
+  pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
     let shear = Self::new([
       [1., xy, xz, 0.],
       [yx, 1., yz, 0.],
@@ -140,6 +148,66 @@ impl Matrix {
     return shear * self;
   }
 
+  /// Splits an affine transformation into the parts it is built from, so that
+  /// [`Decomposition::to_matrix`] gives it back. Mirroring transformations get a negative x
+  /// scale.
+  pub fn decompose(&self) -> Decomposition {
+    let column = |col: usize| {
+      return Vector::new(self[(0, col)], self[(1, col)], self[(2, col)]);
+    };
+    let (column_x, column_y, column_z) = (column(0), column(1), column(2));
+
+    // Orthonormalizing the columns in order splits the upper 3x3 matrix into a rotation, whose
+    // columns are the new axes, and an upper triangular matrix of scale and shear.
+    let mut scale_x = column_x.magnitude();
+    let mut x = if scale_x == 0. {
+      Vector::new(1., 0., 0.)
+    } else {
+      column_x / scale_x
+    };
+    let mut sheared_xy = x.dot(&column_y);
+    let rest_y = column_y - x * sheared_xy;
+    let scale_y = rest_y.magnitude();
+    let y = if scale_y == 0. {
+      perpendicular(&x)
+    } else {
+      rest_y / scale_y
+    };
+    let mut sheared_xz = x.dot(&column_z);
+    let sheared_yz = y.dot(&column_z);
+    let rest_z = column_z - x * sheared_xz - y * sheared_yz;
+    let mut z = x.cross(&y);
+    let mut scale_z = z.dot(&rest_z);
+    if scale_z < 0. {
+      x = -x;
+      z = -z;
+      scale_x = -scale_x;
+      scale_z = -scale_z;
+      sheared_xy = -sheared_xy;
+      sheared_xz = -sheared_xz;
+    }
+
+    let ratio = |sheared: Float, scale: Float| {
+      return if scale == 0. { 0. } else { sheared / scale };
+    };
+    let axes = Self::new([
+      [x.x, y.x, z.x, 0.],
+      [x.y, y.y, z.y, 0.],
+      [x.z, y.z, z.z, 0.],
+      [0., 0., 0., 1.],
+    ]);
+    return Decomposition {
+      translation: column(3),
+      rotation: Quaternion::from_matrix(&axes),
+      scale: Vector::new(scale_x, scale_y, scale_z),
+      shear: [
+        ratio(sheared_xy, scale_x),
+        ratio(sheared_xz, scale_x),
+        ratio(sheared_yz, scale_y),
+      ],
+    };
+  }
+
   pub fn look_at(from: &Point, to: &Point, up: &Vector) -> Self {
     let forward = (*to - *from).normalize();
     let left = forward.cross(&up.normalize());
@@ -159,7 +227,7 @@ impl Default for Matrix {
   }
 }
 impl Index<(usize, usize)> for Matrix {
-  type Output = f64;
+  type Output = Float;
 
   fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
     return &self.elements[row][col];
@@ -186,16 +254,7 @@ impl Mul for Matrix {
   type Output = Self;
 
   fn mul(self, rhs: Self) -> Self {
-    let mut new_matrix = Self::identity();
-    for row in 0..4 {
-      for col in 0..4 {
-        new_matrix[(row, col)] = self[(row, 0)] * rhs[(0, col)]
-          + self[(row, 1)] * rhs[(1, col)]
-          + self[(row, 2)] * rhs[(2, col)]
-          + self[(row, 3)] * rhs[(3, col)]
-      }
-    }
-    return new_matrix;
+    return Self::new(simd::mat4_mul(&self.elements, &rhs.elements));
   }
 }
 impl Mul<Vector> for Matrix {
@@ -223,15 +282,79 @@ impl Mul<Point> for Matrix {
 impl Mul<Ray> for Matrix {
   type Output = Ray;
 
+  /// Keeps the interval of the ray, as times along a transformed ray reach the transformed
+  /// points.
   fn mul(self, ray: Ray) -> Ray {
-    return Ray::new(self * ray.origin, self * ray.direction);
+    return Ray::new(self * ray.origin, self * ray.direction).with_interval(ray.t_min, ray.t_max);
   }
 }
+/// Interpolates the parts of the [`Decomposition`] of two transformations separately, so an
+/// object rotating between them keeps its size and turns along the shortest path.
+impl Lerp for Matrix {
+  type Scalar = Float;
+
+  fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
+    return self.decompose().lerp(&other.decompose(), t).to_matrix();
+  }
+}
+
+/// Parts of an affine transformation, applied in the order shear, scale, rotation and
+/// translation.
+///
+/// Only the shear factors `xy`, `xz` and `yz` of [`Matrix::shear`] are needed, as the others can
+/// be expressed with a rotation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Decomposition {
+  pub translation: Vector,
+  pub rotation: Quaternion,
+  pub scale: Vector,
+  /// Shear factors `xy`, `xz` and `yz`.
+  pub shear: [Float; 3],
+}
+impl Decomposition {
+  pub fn to_matrix(&self) -> Matrix {
+    let [xy, xz, yz] = self.shear;
+    return Matrix::identity()
+      .shear(xy, xz, 0., yz, 0., 0.)
+      .scale(self.scale.x, self.scale.y, self.scale.z)
+      .rotate(&self.rotation)
+      .translate(self.translation.x, self.translation.y, self.translation.z);
+  }
+}
+impl Lerp for Decomposition {
+  type Scalar = Float;
+
+  fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
+    let [xy, xz, yz] = self.shear;
+    let [other_xy, other_xz, other_yz] = other.shear;
+    return Self {
+      translation: self.translation.lerp(&other.translation, t),
+      rotation: self.rotation.slerp(&other.rotation, *t),
+      scale: self.scale.lerp(&other.scale, t),
+      shear: [
+        xy.lerp(&other_xy, t),
+        xz.lerp(&other_xz, t),
+        yz.lerp(&other_yz, t),
+      ],
+    };
+  }
+}
+
+/// Returns a unit vector perpendicular to the unit vector `vector`.
+fn perpendicular(vector: &Vector) -> Vector {
+  let axis = if vector.x.abs() < 0.9 {
+    Vector::new(1., 0., 0.)
+  } else {
+    Vector::new(0., 1., 0.)
+  };
+  return (axis - *vector * vector.dot(&axis)).normalize();
+}
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use std::f64::consts::{PI, SQRT_2};
+  use crate::assert_ae;
+  use crate::utils::consts::{PI, SQRT_2};
 
   #[test]
   fn init_new() {
@@ -468,6 +591,49 @@ mod tests {
     matrix.inverse();
   }
 
+  #[test]
+  fn try_inverse_of_singular_matrices() {
+    assert_eq!(
+      Matrix::identity().scale(1., 0., 1.).try_inverse(),
+      Err(SingularMatrixError)
+    );
+    assert_eq!(
+      Matrix::identity()
+        .translate(Float::NAN, 0., 0.)
+        .try_inverse(),
+      Err(SingularMatrixError)
+    );
+    // Rows that are multiples of each other only after rounding.
+    let matrix = Matrix::new([
+      [0.1, 0.2, 0.3, 0.],
+      [0.3, 0.6, 0.9, 0.],
+      [1., 0., 1., 0.],
+      [0., 0., 0., 1.],
+    ]);
+    assert_eq!(matrix.try_inverse(), Err(SingularMatrixError));
+  }
+
+  #[test]
+  fn try_inverse_picks_largest_pivot() {
+    let matrix = Matrix::new([
+      [1e-17, 1., 0., 0.],
+      [1., 1., 0., 0.],
+      [0., 0., 1., 0.],
+      [0., 0., 0., 1.],
+    ]);
+    let inverse = matrix.try_inverse().unwrap();
+    assert_eq!(
+      inverse,
+      Matrix::new([
+        [-1., 1., 0., 0.],
+        [1., 0., 0., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+      ])
+    );
+    assert_eq!(matrix * inverse, Matrix::identity());
+  }
+
   #[test]
   fn multiplying_by_inverse_gives_identity_matrix() {
     let identity = Matrix::identity();
@@ -703,4 +869,110 @@ mod tests {
       ],)
     );
   }
+
+  #[test]
+  fn lerp_interpolates_translation_rotation_and_scale() {
+    let a = Matrix::identity().scale(1., 2., 1.);
+    let b = Matrix::identity()
+      .scale(3., 2., 1.)
+      .rotate_z(PI / 2.)
+      .translate(2., 0., -4.);
+    assert_eq!(a.lerp(&b, &0.), a);
+    assert_eq!(a.lerp(&b, &1.), b);
+    assert_eq!(
+      a.lerp(&b, &0.5),
+      Matrix::identity()
+        .scale(2., 2., 1.)
+        .rotate_z(PI / 4.)
+        .translate(1., 0., -2.)
+    );
+  }
+
+  #[test]
+  fn lerp_keeps_size_of_rotating_objects() {
+    let a = Matrix::identity().rotate_y(-PI / 3.);
+    let b = Matrix::identity().rotate_y(PI / 3.);
+    let middle = a.lerp(&b, &0.5);
+    assert_eq!(middle, Matrix::identity());
+    let quarter = a.lerp(&b, &0.25) * Vector::new(1., 0., 0.);
+    assert_ae!(quarter.magnitude(), 1.);
+  }
+
+  #[test]
+  fn lerp_keeps_mirroring() {
+    let mirror = Matrix::identity().scale(-1., 1., 2.).translate(0., 3., 0.);
+    assert_eq!(mirror.lerp(&mirror, &0.5), mirror);
+  }
+
+  #[test]
+  fn rotate_with_quaternion() {
+    let rotation = Quaternion::from_axis_angle(&Vector::new(0., 1., 0.), 0.6);
+    assert_eq!(
+      Matrix::identity().translate(1., 0., 0.).rotate(&rotation),
+      Matrix::identity().translate(1., 0., 0.).rotate_y(0.6)
+    );
+  }
+
+  #[test]
+  fn decompose_into_parts() {
+    let parts = Matrix::identity()
+      .scale(2., 3., 4.)
+      .rotate_x(0.4)
+      .rotate_z(-1.2)
+      .translate(5., -6., 7.)
+      .decompose();
+    assert_eq!(parts.translation, Vector::new(5., -6., 7.));
+    assert_eq!(parts.rotation, Quaternion::from_euler(0.4, 0., -1.2));
+    assert_eq!(parts.scale, Vector::new(2., 3., 4.));
+    for shear in parts.shear {
+      assert_ae!(shear, 0.);
+    }
+  }
+
+  #[test]
+  fn decompose_round_trip() {
+    let matrices = [
+      Matrix::identity(),
+      Matrix::identity()
+        .shear(1., 0.5, 0., -2., 0., 0.)
+        .scale(2., 0.5, 3.)
+        .rotate_y(2.)
+        .translate(1., 2., 3.),
+      Matrix::identity()
+        .shear(0., 0., 1., 0., 2., 3.)
+        .rotate_x(PI),
+      Matrix::identity().scale(-1., 1., 1.).rotate_z(0.5),
+      Matrix::identity().scale(0., 1., 0.),
+    ];
+    for matrix in matrices {
+      assert_eq!(matrix.decompose().to_matrix(), matrix);
+    }
+  }
+
+  #[test]
+  fn decompose_mirroring() {
+    let parts = Matrix::identity().scale(2., -1., 1.).decompose();
+    assert_eq!(parts.scale, Vector::new(-2., 1., 1.));
+    assert_eq!(
+      parts.rotation,
+      Quaternion::from_axis_angle(&Vector::new(0., 0., 1.), PI)
+    );
+  }
+
+  #[test]
+  fn lerp_takes_shortest_rotation() {
+    let a = Matrix::identity().rotate_z(-0.9 * PI);
+    let b = Matrix::identity().rotate_z(0.9 * PI);
+    assert_eq!(a.lerp(&b, &0.5), Matrix::identity().rotate_z(PI));
+  }
+
+  #[test]
+  fn lerp_interpolates_shear() {
+    let a = Matrix::identity();
+    let b = Matrix::identity().shear(2., 0., 0., 0., 0., 0.);
+    assert_eq!(
+      a.lerp(&b, &0.5),
+      Matrix::identity().shear(1., 0., 0., 0., 0., 0.)
+    );
+  }
 }
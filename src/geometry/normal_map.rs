@@ -0,0 +1,200 @@
+use crate::geometry::{Point, Vector};
+use crate::textures::Texture;
+use crate::utils::Float;
+
+/// Distance used for the finite differences of a bump map's height field.
+const BUMP_DELTA: Float = 0.0001;
+
+/// Orthonormal basis at a surface point.
+///
+/// `tangent` points towards increasing `u` and `bitangent` towards increasing `v`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TangentFrame {
+  pub tangent: Vector,
+  pub bitangent: Vector,
+  pub normal: Vector,
+}
+impl TangentFrame {
+  /// Builds a frame around `normal`, making `tangent` perpendicular to it.
+  ///
+  /// Falls back to an arbitrary perpendicular tangent if `tangent` is parallel to `normal`.
+  pub fn new(tangent: Vector, normal: Vector) -> Self {
+    let normal = normal.normalize();
+    let mut tangent = tangent - normal * normal.dot(&tangent);
+    if tangent.magnitude() < 1e-9 {
+      let axis = if normal.x.abs() < 0.9 {
+        Vector::new(1., 0., 0.)
+      } else {
+        Vector::new(0., 1., 0.)
+      };
+      tangent = axis - normal * normal.dot(&axis);
+    }
+    let tangent = tangent.normalize();
+    return Self {
+      tangent,
+      bitangent: tangent.cross(&normal),
+      normal,
+    };
+  }
+
+  /// Converts a vector from tangent space to the space of the frame.
+  pub fn transform_tangent(&self, vector: &Vector) -> Vector {
+    return self.tangent * vector.x + self.bitangent * vector.y + self.normal * vector.z;
+  }
+}
+
+/// Perturbs surface normals to add detail without modeling geometry.
+#[derive(Debug)]
+pub enum NormalMap {
+  /// Tilts the normal along the slope of a height field, taken from the luminance of a texture.
+  Bump {
+    height: Box<dyn Texture>,
+    strength: Float,
+  },
+  /// Replaces the normal with one read from a texture in tangent space, with the red, green and
+  /// blue channels mapping `0.0..=1.0` to `-1.0..=1.0` along the tangent, bitangent and normal.
+  Tangent(Box<dyn Texture>),
+}
+impl NormalMap {
+  /// Returns the texture the normals are read from.
+  pub fn texture(&self) -> &dyn Texture {
+    return match self {
+      NormalMap::Bump { height, .. } => height,
+      NormalMap::Tangent(texture) => texture,
+    };
+  }
+
+  /// Returns the perturbed world space normal.
+  ///
+  /// Textures are sampled at `point_object`, stepping along `frame_object` for bump maps, while
+  /// the result is expressed in `frame_world`.
+  pub fn perturb(
+    &self,
+    point_object: &Point,
+    frame_object: &TangentFrame,
+    frame_world: &TangentFrame,
+  ) -> Vector {
+    return match self {
+      NormalMap::Bump { height, strength } => {
+        let height_at = |point: Point| height.color_at(&point).luminance();
+        let center = height_at(*point_object);
+        let du =
+          (height_at(*point_object + frame_object.tangent * BUMP_DELTA) - center) / BUMP_DELTA;
+        let dv =
+          (height_at(*point_object + frame_object.bitangent * BUMP_DELTA) - center) / BUMP_DELTA;
+        let slope = Vector::new(-du * strength, -dv * strength, 1.);
+        frame_world.transform_tangent(&slope).normalize()
+      }
+      NormalMap::Tangent(texture) => {
+        let color = texture.color_at(point_object);
+        let normal = Vector::new(2. * color.r - 1., 2. * color.g - 1., 2. * color.b - 1.);
+        frame_world.transform_tangent(&normal).normalize()
+      }
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::textures::{gradient, Pattern};
+  use crate::utils::consts::SQRT_2;
+  use crate::Color;
+
+  fn plane_frame() -> TangentFrame {
+    return TangentFrame::new(Vector::new(1., 0., 0.), Vector::new(0., 1., 0.));
+  }
+
+  #[test]
+  fn frame_is_orthonormal() {
+    let frame = TangentFrame::new(Vector::new(1., 1., 0.), Vector::new(0., 2., 0.));
+    assert_eq!(frame.normal, Vector::new(0., 1., 0.));
+    assert_eq!(frame.tangent, Vector::new(1., 0., 0.));
+    assert_eq!(frame.bitangent, Vector::new(0., 0., 1.));
+  }
+
+  #[test]
+  fn frame_with_parallel_tangent() {
+    let frame = TangentFrame::new(Vector::new(0., 3., 0.), Vector::new(0., 1., 0.));
+    assert_eq!(frame.tangent.dot(&frame.normal), 0.);
+    assert_eq!(frame.tangent.magnitude(), 1.);
+  }
+
+  #[test]
+  fn transform_tangent() {
+    let frame = plane_frame();
+    assert_eq!(
+      frame.transform_tangent(&Vector::new(0., 0., 1.)),
+      Vector::new(0., 1., 0.)
+    );
+    assert_eq!(
+      frame.transform_tangent(&Vector::new(1., 2., 3.)),
+      Vector::new(1., 3., 2.)
+    );
+  }
+
+  #[test]
+  fn bump_constant_height_keeps_normal() {
+    let map = NormalMap::Bump {
+      height: Box::new(Color::white()),
+      strength: 1.,
+    };
+    let frame = plane_frame();
+    assert_eq!(
+      map.perturb(&Point::new(0.3, 0., 0.2), &frame, &frame),
+      Vector::new(0., 1., 0.)
+    );
+  }
+
+  #[test]
+  fn bump_tilts_against_slope() {
+    let map = NormalMap::Bump {
+      height: Box::new(Pattern::with_fn(gradient)),
+      strength: 1.,
+    };
+    let frame = plane_frame();
+    assert_eq!(
+      map.perturb(&Point::new(0.5, 0., 0.2), &frame, &frame),
+      Vector::new(SQRT_2 / 2., SQRT_2 / 2., 0.)
+    );
+  }
+
+  #[test]
+  fn bump_strength_scales_tilt() {
+    let map = NormalMap::Bump {
+      height: Box::new(Pattern::with_fn(gradient)),
+      strength: 0.,
+    };
+    let frame = plane_frame();
+    assert_eq!(
+      map.perturb(&Point::new(0.5, 0., 0.2), &frame, &frame),
+      Vector::new(0., 1., 0.)
+    );
+  }
+
+  #[test]
+  fn tangent_flat_color_keeps_normal() {
+    let map = NormalMap::Tangent(Box::new(Color::new(0.5, 0.5, 1.)));
+    let frame = plane_frame();
+    assert_eq!(
+      map.perturb(&Point::origin(), &frame, &frame),
+      Vector::new(0., 1., 0.)
+    );
+  }
+
+  #[test]
+  fn tangent_maps_channels_to_frame() {
+    let map = NormalMap::Tangent(Box::new(Color::new(1., 0.5, 0.5)));
+    let frame = plane_frame();
+    assert_eq!(
+      map.perturb(&Point::origin(), &frame, &frame),
+      Vector::new(1., 0., 0.)
+    );
+
+    let map = NormalMap::Tangent(Box::new(Color::new(0.5, 1., 0.5)));
+    assert_eq!(
+      map.perturb(&Point::origin(), &frame, &frame),
+      Vector::new(0., 0., 1.)
+    );
+  }
+}
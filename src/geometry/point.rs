@@ -1,15 +1,15 @@
 use crate::geometry::Vector;
-use crate::utils::approx_equals;
+use crate::utils::{approx_equals, Float, Lerp};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Point {
-  pub x: f64,
-  pub y: f64,
-  pub z: f64,
+  pub x: Float,
+  pub y: Float,
+  pub z: Float,
 }
 impl Point {
-  pub fn new(x: f64, y: f64, z: f64) -> Self {
+  pub fn new(x: Float, y: Float, z: Float) -> Self {
     return Self { x, y, z };
   }
 
@@ -58,27 +58,38 @@ impl Neg for Point {
     return Self::new(-self.x, -self.y, -self.z);
   }
 }
-impl Mul<f64> for Point {
+impl Mul<Float> for Point {
   type Output = Self;
 
-  fn mul(self, scalar: f64) -> Self {
+  fn mul(self, scalar: Float) -> Self {
     return Self::new(scalar * self.x, scalar * self.y, scalar * self.z);
   }
 }
-impl Mul<Point> for f64 {
+impl Mul<Point> for Float {
   type Output = Point;
 
   fn mul(self, point: Point) -> Point {
     return point * self;
   }
 }
-impl Div<f64> for Point {
+impl Div<Float> for Point {
   type Output = Self;
 
-  fn div(self, scalar: f64) -> Self {
+  fn div(self, scalar: Float) -> Self {
     return Self::new(self.x / scalar, self.y / scalar, self.z / scalar);
   }
 }
+impl Lerp for Point {
+  type Scalar = Float;
+
+  fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
+    return Self::new(
+      self.x.lerp(&other.x, t),
+      self.y.lerp(&other.y, t),
+      self.z.lerp(&other.z, t),
+    );
+  }
+}
 
 #[cfg(test)]
 mod tests {
@@ -149,4 +160,13 @@ mod tests {
   fn scalar_division() {
     assert_eq!(Point::new(1., -2., 3.) / 2., Point::new(0.5, -1., 1.5));
   }
+
+  #[test]
+  fn lerp() {
+    let a = Point::new(1., -2., 3.);
+    let b = Point::new(3., 2., 3.);
+    assert_eq!(a.lerp(&b, &0.), a);
+    assert_eq!(a.lerp(&b, &0.25), Point::new(1.5, -1., 3.));
+    assert_eq!(a.lerp(&b, &1.), b);
+  }
 }
@@ -0,0 +1,375 @@
+use crate::geometry::{Matrix, Vector};
+use crate::utils::{approx_equals, Float, Lerp, EPSILON};
+use std::ops::{Mul, Neg};
+
+/// Rotation in three dimensions, as a unit quaternion `w + xi + yj + zk`.
+///
+/// Unlike three angles, quaternions interpolate smoothly between any two rotations with
+/// [`slerp`](Quaternion::slerp), without flips or gimbal lock.
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+  pub w: Float,
+  pub x: Float,
+  pub y: Float,
+  pub z: Float,
+}
+impl Quaternion {
+  pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+    return Self { w, x, y, z };
+  }
+
+  /// Returns the rotation that does nothing.
+  pub fn identity() -> Self {
+    return Self::new(1., 0., 0., 0.);
+  }
+
+  /// Returns the rotation by `angle` radians around `axis`, counterclockwise when looking from
+  /// the tip of the axis towards the origin, like [`Matrix::rotate_x`].
+  pub fn from_axis_angle(axis: &Vector, angle: Float) -> Self {
+    let (sin, cos) = (angle / 2.).sin_cos();
+    let axis = axis.normalize() * sin;
+    return Self::new(cos, axis.x, axis.y, axis.z);
+  }
+
+  /// Returns the axis and angle in radians of the rotation, with the angle between 0 and π. The
+  /// identity rotation has an arbitrary axis.
+  pub fn to_axis_angle(&self) -> (Vector, Float) {
+    let rotation = if self.w < 0. { -*self } else { *self };
+    let axis = Vector::new(rotation.x, rotation.y, rotation.z);
+    let sin = axis.magnitude();
+    if sin == 0. {
+      return (Vector::new(1., 0., 0.), 0.);
+    }
+    return (axis / sin, 2. * sin.atan2(rotation.w));
+  }
+
+  /// Returns the rotation around the x axis, then the y axis, then the z axis, by the given
+  /// angles in radians. The same as `Matrix::identity().rotate_x(x).rotate_y(y).rotate_z(z)`.
+  pub fn from_euler(x: Float, y: Float, z: Float) -> Self {
+    return Self::from_axis_angle(&Vector::new(0., 0., 1.), z)
+      * Self::from_axis_angle(&Vector::new(0., 1., 0.), y)
+      * Self::from_axis_angle(&Vector::new(1., 0., 0.), x);
+  }
+
+  /// Returns the angles of [`from_euler`](Quaternion::from_euler) giving this rotation, with the
+  /// y angle between -π/2 and π/2.
+  ///
+  /// When the y angle is ±π/2, rotations around x and z turn around the same axis and only their
+  /// sum or difference matters, so the z angle is 0.
+  pub fn to_euler(&self) -> (Float, Float, Float) {
+    let m = self.to_matrix();
+    // More accurate near ±π/2 than the arcsine of `-m[(2, 0)]`.
+    let cos_y = m[(0, 0)].hypot(m[(1, 0)]);
+    let y = (-m[(2, 0)]).atan2(cos_y);
+    if cos_y < EPSILON {
+      return ((-m[(1, 2)]).atan2(m[(1, 1)]), y, 0.);
+    }
+    return (m[(2, 1)].atan2(m[(2, 2)]), y, m[(1, 0)].atan2(m[(0, 0)]));
+  }
+
+  /// Returns the rotation of a matrix without scale or shear, such as the rotation part of
+  /// [`Matrix::decompose`].
+  pub fn from_matrix(matrix: &Matrix) -> Self {
+    let m = |row: usize, col: usize| {
+      return matrix[(row, col)];
+    };
+    let trace = m(0, 0) + m(1, 1) + m(2, 2);
+    // Dividing by the largest component keeps the result accurate for every rotation.
+    let rotation = if trace > 0. {
+      let s = 2. * (1. + trace).sqrt();
+      Self::new(
+        s / 4.,
+        (m(2, 1) - m(1, 2)) / s,
+        (m(0, 2) - m(2, 0)) / s,
+        (m(1, 0) - m(0, 1)) / s,
+      )
+    } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+      let s = 2. * (1. + m(0, 0) - m(1, 1) - m(2, 2)).sqrt();
+      Self::new(
+        (m(2, 1) - m(1, 2)) / s,
+        s / 4.,
+        (m(0, 1) + m(1, 0)) / s,
+        (m(0, 2) + m(2, 0)) / s,
+      )
+    } else if m(1, 1) > m(2, 2) {
+      let s = 2. * (1. + m(1, 1) - m(0, 0) - m(2, 2)).sqrt();
+      Self::new(
+        (m(0, 2) - m(2, 0)) / s,
+        (m(0, 1) + m(1, 0)) / s,
+        s / 4.,
+        (m(1, 2) + m(2, 1)) / s,
+      )
+    } else {
+      let s = 2. * (1. + m(2, 2) - m(0, 0) - m(1, 1)).sqrt();
+      Self::new(
+        (m(1, 0) - m(0, 1)) / s,
+        (m(0, 2) + m(2, 0)) / s,
+        (m(1, 2) + m(2, 1)) / s,
+        s / 4.,
+      )
+    };
+    return rotation.normalize();
+  }
+
+  pub fn to_matrix(&self) -> Matrix {
+    let Self { w, x, y, z } = *self;
+    return Matrix::new([
+      [
+        1. - 2. * (y * y + z * z),
+        2. * (x * y - w * z),
+        2. * (x * z + w * y),
+        0.,
+      ],
+      [
+        2. * (x * y + w * z),
+        1. - 2. * (x * x + z * z),
+        2. * (y * z - w * x),
+        0.,
+      ],
+      [
+        2. * (x * z - w * y),
+        2. * (y * z + w * x),
+        1. - 2. * (x * x + y * y),
+        0.,
+      ],
+      [0., 0., 0., 1.],
+    ]);
+  }
+
+  pub fn dot(&self, other: &Self) -> Float {
+    return self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+  }
+  pub fn magnitude(&self) -> Float {
+    return self.dot(self).sqrt();
+  }
+  pub fn normalize(&self) -> Self {
+    let magnitude = self.magnitude();
+    return Self::new(
+      self.w / magnitude,
+      self.x / magnitude,
+      self.y / magnitude,
+      self.z / magnitude,
+    );
+  }
+  /// Returns the opposite rotation.
+  pub fn conjugate(&self) -> Self {
+    return Self::new(self.w, -self.x, -self.y, -self.z);
+  }
+
+  /// Interpolates between two rotations along the shortest path, at a constant angular speed.
+  pub fn slerp(&self, other: &Self, t: Float) -> Self {
+    // `q` and `-q` are the same rotation; the one closer to `self` gives the shortest path.
+    let (other, cos) = match self.dot(other) {
+      cos if cos < 0. => (-*other, -cos),
+      cos => (*other, cos),
+    };
+    // Nearly equal rotations are interpolated linearly, as the angle is too small to divide by.
+    let (weight_self, weight_other) = if cos > 1. - 1e-6 {
+      (1. - t, t)
+    } else {
+      let angle = cos.acos();
+      let sin = angle.sin();
+      (((1. - t) * angle).sin() / sin, (t * angle).sin() / sin)
+    };
+    return Self::new(
+      weight_self * self.w + weight_other * other.w,
+      weight_self * self.x + weight_other * other.x,
+      weight_self * self.y + weight_other * other.y,
+      weight_self * self.z + weight_other * other.z,
+    )
+    .normalize();
+  }
+}
+impl Default for Quaternion {
+  fn default() -> Self {
+    return Self::identity();
+  }
+}
+/// Quaternions are equal when they are the same rotation, so `q` equals `-q`.
+impl PartialEq for Quaternion {
+  fn eq(&self, other: &Self) -> bool {
+    let components_equal = |other: &Self| {
+      return approx_equals(self.w, other.w)
+        && approx_equals(self.x, other.x)
+        && approx_equals(self.y, other.y)
+        && approx_equals(self.z, other.z);
+    };
+    return components_equal(other) || components_equal(&-*other);
+  }
+}
+impl Neg for Quaternion {
+  type Output = Self;
+
+  fn neg(self) -> Self {
+    return Self::new(-self.w, -self.x, -self.y, -self.z);
+  }
+}
+/// Combines two rotations. Like matrices, `a * b` rotates by `b` first, then by `a`.
+impl Mul for Quaternion {
+  type Output = Self;
+
+  fn mul(self, other: Self) -> Self {
+    return Self::new(
+      self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+      self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+      self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+      self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+    );
+  }
+}
+impl Mul<Vector> for Quaternion {
+  type Output = Vector;
+
+  fn mul(self, vector: Vector) -> Vector {
+    let rotated = self * Self::new(0., vector.x, vector.y, vector.z) * self.conjugate();
+    return Vector::new(rotated.x, rotated.y, rotated.z);
+  }
+}
+impl Lerp for Quaternion {
+  type Scalar = Float;
+
+  fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
+    return self.slerp(other, *t);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::assert_ae;
+  use crate::utils::consts::{FRAC_PI_2, PI};
+
+  #[test]
+  fn identity_does_not_rotate() {
+    let vector = Vector::new(1., 2., 3.);
+    assert_eq!(Quaternion::identity() * vector, vector);
+    assert_eq!(Quaternion::default().to_matrix(), Matrix::identity());
+  }
+
+  #[test]
+  fn axis_angle_matches_matrix_rotations() {
+    let angle = 0.7;
+    assert_eq!(
+      Quaternion::from_axis_angle(&Vector::new(1., 0., 0.), angle).to_matrix(),
+      Matrix::identity().rotate_x(angle)
+    );
+    assert_eq!(
+      Quaternion::from_axis_angle(&Vector::new(0., 2., 0.), angle).to_matrix(),
+      Matrix::identity().rotate_y(angle)
+    );
+    assert_eq!(
+      Quaternion::from_axis_angle(&Vector::new(0., 0., 1.), angle).to_matrix(),
+      Matrix::identity().rotate_z(angle)
+    );
+  }
+
+  #[test]
+  fn equality_compares_components() {
+    let axis = Vector::new(0., 1., 0.);
+    let rotation = Quaternion::from_axis_angle(&axis, 1.);
+    assert_eq!(rotation, -rotation);
+    assert_ne!(rotation, Quaternion::from_axis_angle(&axis, 1.005));
+    assert_ne!(rotation, Quaternion::from_axis_angle(&axis, -1.));
+  }
+
+  #[test]
+  fn rotating_vectors() {
+    let rotation = Quaternion::from_axis_angle(&Vector::new(0., 0., 1.), FRAC_PI_2);
+    assert_eq!(rotation * Vector::new(1., 0., 0.), Vector::new(0., 1., 0.));
+  }
+
+  #[test]
+  fn to_axis_angle() {
+    let axis = Vector::new(1., -2., 2.).normalize();
+    let (result_axis, angle) = Quaternion::from_axis_angle(&axis, 2.).to_axis_angle();
+    assert_eq!(result_axis, axis);
+    assert_ae!(angle, 2.);
+
+    // Turning more than half a turn is the same as turning the other way.
+    let (result_axis, angle) = Quaternion::from_axis_angle(&axis, 1.5 * PI).to_axis_angle();
+    assert_eq!(result_axis, -axis);
+    assert_ae!(angle, 0.5 * PI);
+  }
+
+  #[test]
+  fn euler_angles_match_matrix_rotations() {
+    let rotation = Quaternion::from_euler(0.3, -1.1, 2.5);
+    assert_eq!(
+      rotation.to_matrix(),
+      Matrix::identity()
+        .rotate_x(0.3)
+        .rotate_y(-1.1)
+        .rotate_z(2.5)
+    );
+    let (x, y, z) = rotation.to_euler();
+    assert_ae!(x, 0.3);
+    assert_ae!(y, -1.1);
+    assert_ae!(z, 2.5);
+  }
+
+  #[test]
+  fn euler_angles_in_gimbal_lock() {
+    let rotation = Quaternion::from_euler(0.4, FRAC_PI_2, 0.1);
+    let (x, y, z) = rotation.to_euler();
+    assert_ae!(y, FRAC_PI_2);
+    assert_eq!(z, 0.);
+    assert_eq!(Quaternion::from_euler(x, y, z), rotation);
+  }
+
+  #[test]
+  fn from_matrix_round_trip() {
+    let rotations = [
+      Quaternion::identity(),
+      Quaternion::from_axis_angle(&Vector::new(1., 0., 0.), PI),
+      Quaternion::from_axis_angle(&Vector::new(0., 1., 0.), PI),
+      Quaternion::from_axis_angle(&Vector::new(0., 0., 1.), PI),
+      Quaternion::from_euler(0.3, -1.1, 2.5),
+    ];
+    for rotation in rotations {
+      assert_eq!(Quaternion::from_matrix(&rotation.to_matrix()), rotation);
+    }
+  }
+
+  #[test]
+  fn equality_ignores_sign() {
+    let rotation = Quaternion::from_euler(1., 2., 3.);
+    assert_eq!(rotation, -rotation);
+    assert_ne!(rotation, Quaternion::identity());
+  }
+
+  #[test]
+  fn multiplication_combines_rotations() {
+    let x = Quaternion::from_axis_angle(&Vector::new(1., 0., 0.), 0.5);
+    let y = Quaternion::from_axis_angle(&Vector::new(0., 1., 0.), 0.8);
+    assert_eq!(
+      (y * x).to_matrix(),
+      Matrix::identity().rotate_x(0.5).rotate_y(0.8)
+    );
+  }
+
+  #[test]
+  fn slerp_has_constant_angular_speed() {
+    let axis = Vector::new(0., 1., 0.);
+    let start = Quaternion::identity();
+    let end = Quaternion::from_axis_angle(&axis, 2.);
+    assert_eq!(start.slerp(&end, 0.), start);
+    assert_eq!(start.slerp(&end, 1.), end);
+    assert_eq!(
+      start.slerp(&end, 0.25),
+      Quaternion::from_axis_angle(&axis, 0.5)
+    );
+    assert_eq!(start.slerp(&start, 0.5), start);
+  }
+
+  #[test]
+  fn slerp_takes_shortest_path() {
+    let axis = Vector::new(0., 0., 1.);
+    let start = Quaternion::from_axis_angle(&axis, -0.9 * PI);
+    let end = Quaternion::from_axis_angle(&axis, 0.9 * PI);
+    assert_eq!(
+      start.slerp(&end, 0.5),
+      Quaternion::from_axis_angle(&axis, PI)
+    );
+    assert_eq!(start.lerp(&end, &0.5), start.slerp(&end, 0.5));
+  }
+}
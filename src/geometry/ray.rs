@@ -1,16 +1,38 @@
 use crate::geometry::{Point, Vector};
+use crate::utils::Float;
 
+/// Half-line from `origin` in `direction`. Only intersections at times between `t_min` and
+/// `t_max` count as hits, which by default is everything in front of the origin.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
   pub origin: Point,
   pub direction: Vector,
+  pub t_min: Float,
+  pub t_max: Float,
 }
 impl Ray {
   pub fn new(origin: Point, direction: Vector) -> Self {
-    return Self { origin, direction };
+    return Self {
+      origin,
+      direction,
+      t_min: 0.,
+      t_max: Float::INFINITY,
+    };
   }
 
-  pub fn position(&self, t: f64) -> Point {
+  /// Sets the times between which intersections count, inclusive.
+  pub fn with_interval(mut self, t_min: Float, t_max: Float) -> Self {
+    self.t_min = t_min;
+    self.t_max = t_max;
+    return self;
+  }
+
+  /// Returns whether an intersection at time `t` lies within the interval of the ray.
+  pub fn contains(&self, t: Float) -> bool {
+    return t >= self.t_min && t <= self.t_max;
+  }
+
+  pub fn position(&self, t: Float) -> Point {
     return self.origin + self.direction * t;
   }
 }
@@ -26,6 +48,17 @@ mod tests {
     let ray = Ray::new(origin, direction);
     assert_eq!(ray.origin, origin);
     assert_eq!(ray.direction, direction);
+    assert_eq!(ray.t_min, 0.);
+    assert_eq!(ray.t_max, Float::INFINITY);
+  }
+
+  #[test]
+  fn interval() {
+    let ray = Ray::new(Point::origin(), Vector::new(0., 0., 1.)).with_interval(1., 2.);
+    assert!(!ray.contains(0.5));
+    assert!(ray.contains(1.));
+    assert!(ray.contains(2.));
+    assert!(!ray.contains(2.5));
   }
 
   #[test]
@@ -1,14 +1,15 @@
-use crate::utils::approx_equals;
+use crate::simd;
+use crate::utils::{approx_equals, Float, Lerp};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Vector {
-  pub x: f64,
-  pub y: f64,
-  pub z: f64,
+  pub x: Float,
+  pub y: Float,
+  pub z: Float,
 }
 impl Vector {
-  pub fn new(x: f64, y: f64, z: f64) -> Self {
+  pub fn new(x: Float, y: Float, z: Float) -> Self {
     return Self { x, y, z };
   }
 
@@ -16,7 +17,7 @@ impl Vector {
     return Self::new(0., 0., 0.);
   }
 
-  pub fn magnitude(&self) -> f64 {
+  pub fn magnitude(&self) -> Float {
     return (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt();
   }
 
@@ -25,15 +26,12 @@ impl Vector {
     return Self::new(self.x / magnitude, self.y / magnitude, self.z / magnitude);
   }
 
-  pub fn dot(&self, other: &Vector) -> f64 {
-    return self.x * other.x + self.y * other.y + self.z * other.z;
+  pub fn dot(&self, other: &Vector) -> Float {
+    return simd::dot3([self.x, self.y, self.z], [other.x, other.y, other.z]);
   }
   pub fn cross(&self, other: &Vector) -> Vector {
-    return Vector::new(
-      self.y * other.z - self.z * other.y,
-      self.z * other.x - self.x * other.z,
-      self.x * other.y - self.y * other.x,
-    );
+    let [x, y, z] = simd::cross3([self.x, self.y, self.z], [other.x, other.y, other.z]);
+    return Vector::new(x, y, z);
   }
 
   pub fn reflect(&self, normal: &Self) -> Self {
@@ -74,32 +72,44 @@ impl Neg for Vector {
     return Self::new(-self.x, -self.y, -self.z);
   }
 }
-impl Mul<f64> for Vector {
+impl Mul<Float> for Vector {
   type Output = Self;
 
-  fn mul(self, scalar: f64) -> Self {
+  fn mul(self, scalar: Float) -> Self {
     return Self::new(scalar * self.x, scalar * self.y, scalar * self.z);
   }
 }
-impl Mul<Vector> for f64 {
+impl Mul<Vector> for Float {
   type Output = Vector;
 
   fn mul(self, vector: Vector) -> Vector {
     return vector * self;
   }
 }
-impl Div<f64> for Vector {
+impl Div<Float> for Vector {
   type Output = Self;
 
-  fn div(self, scalar: f64) -> Self {
+  fn div(self, scalar: Float) -> Self {
     return Self::new(self.x / scalar, self.y / scalar, self.z / scalar);
   }
 }
+impl Lerp for Vector {
+  type Scalar = Float;
+
+  fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
+    return Self::new(
+      self.x.lerp(&other.x, t),
+      self.y.lerp(&other.y, t),
+      self.z.lerp(&other.z, t),
+    );
+  }
+}
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use std::f64::consts::SQRT_2;
+  use crate::assert_ae;
+  use crate::utils::consts::SQRT_2;
 
   #[test]
   fn init_new() {
@@ -165,8 +175,11 @@ mod tests {
     assert_eq!(Vector::new(1., 0., 0.).magnitude(), 1.);
     assert_eq!(Vector::new(0., 1., 0.).magnitude(), 1.);
     assert_eq!(Vector::new(0., 0., 1.).magnitude(), 1.);
-    assert_eq!(Vector::new(1., 2., 3.).magnitude(), 14_f64.sqrt());
-    assert_eq!(Vector::new(-1., -2., -3.).magnitude(), 14_f64.sqrt());
+    assert_eq!(Vector::new(1., 2., 3.).magnitude(), (14. as Float).sqrt());
+    assert_eq!(
+      Vector::new(-1., -2., -3.).magnitude(),
+      (14. as Float).sqrt()
+    );
   }
 
   #[test]
@@ -174,13 +187,17 @@ mod tests {
     assert_eq!(Vector::new(4., 0., 0.).normalize(), Vector::new(1., 0., 0.));
     assert_eq!(
       Vector::new(1., 2., 3.).normalize(),
-      Vector::new(1. / 14_f64.sqrt(), 2. / 14_f64.sqrt(), 3. / 14_f64.sqrt(),)
+      Vector::new(
+        1. / (14. as Float).sqrt(),
+        2. / (14. as Float).sqrt(),
+        3. / (14. as Float).sqrt(),
+      )
     );
   }
 
   #[test]
   fn normalize_length() {
-    assert_eq!(Vector::new(1., 2., 3.).normalize().magnitude(), 1.);
+    assert_ae!(Vector::new(1., 2., 3.).normalize().magnitude(), 1.);
   }
 
   #[test]
@@ -215,10 +232,22 @@ mod tests {
   #[test]
   fn reflect_in_3d() {
     let vector = Vector::new(0., -1., 0.);
-    let normal = Vector::new(3_f64.sqrt() / 3., 3_f64.sqrt() / 3., 3_f64.sqrt() / 3.);
+    let normal = Vector::new(
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+    );
     assert_eq!(
       vector.reflect(&normal),
       Vector::new(2. / 3., -1. / 3., 2. / 3.)
     );
   }
+
+  #[test]
+  fn lerp() {
+    let a = Vector::new(1., 0., 0.);
+    let b = Vector::new(0., 1., -2.);
+    assert_eq!(a.lerp(&b, &0.5), Vector::new(0.5, 0.5, -1.));
+    assert_eq!(a.lerp(&b, &1.5), Vector::new(-0.5, 1.5, -3.));
+  }
 }
@@ -0,0 +1,48 @@
+//! Ray tracer written to learn computer graphics.
+//!
+//! A [`World`](render::World) holds shapes and point lights, and a [`Camera`](render::Camera)
+//! renders it to a [`Canvas`](render::Canvas), which can be written as PPM, PNG, PFM or OpenEXR.
+//! Worlds are built in code, or loaded from the text format of the [`scene`] module, and can be
+//! animated with the [`animation`] module.
+//!
+//! The [`prelude`] re-exports the types needed for most renders:
+//!
+//! ```
+//! use sorb_rust::prelude::*;
+//!
+//! let world = World::new(
+//!   vec![Box::new(Sphere::new(
+//!     Material::new(Box::new(Color::red()), 0.1, 0.9, 0.9, 200.),
+//!     Matrix::identity().translate(0., 0., -3.),
+//!   ))],
+//!   vec![PointLight::new(Point::new(-5., 5., 0.), Color::white())],
+//! );
+//! let canvas = Camera::new(32, 24, 60., Matrix::identity()).render(&world);
+//! assert_eq!(canvas.width(), 32);
+//! ```
+
+#![allow(clippy::needless_return)]
+
+pub mod color;
+pub use color::Color;
+
+pub mod animation;
+pub mod formats;
+pub mod geometry;
+pub mod render;
+pub mod scene;
+pub mod shapes;
+pub mod simd;
+pub mod textures;
+pub mod utils;
+
+/// Types used by most renders, to be imported with `use sorb_rust::prelude::*`.
+pub mod prelude {
+  pub use crate::color::Color;
+  pub use crate::formats::{FormatError, PngBitDepth, PpmEncoding};
+  pub use crate::geometry::{Material, Matrix, Point, Quaternion, Ray, Vector};
+  pub use crate::render::{Camera, Canvas, DisplayTransform, PointLight, ToneCurve, World};
+  pub use crate::scene::{Scene, SceneError};
+  pub use crate::shapes::{Plane, Shape, Sphere};
+  pub use crate::textures::Texture;
+}
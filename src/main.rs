@@ -1,10 +1,13 @@
+#![allow(clippy::needless_return)]
+
+mod cli;
+
 fn main() {
-  println!("Hello, world!");
+  if let Err(error) = cli::run(std::env::args().skip(1)) {
+    eprintln!("error: {}", error);
+    if let cli::CliError::Usage(_) = error {
+      eprintln!("{}\nRun with --help for more information.", cli::USAGE);
+    }
+    std::process::exit(error.exit_code());
+  }
 }
-
-mod color;
-mod geometry;
-mod render;
-mod shapes;
-mod textures;
-mod utils;
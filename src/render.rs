@@ -1,14 +1,32 @@
+pub mod accumulator;
+pub use accumulator::*;
+
 pub mod camera;
 pub use camera::*;
 
 pub mod canvas;
 pub use canvas::*;
 
+pub mod compare;
+pub use compare::*;
+
+pub mod display;
+pub use display::*;
+
 pub mod lighting;
 pub use lighting::*;
 
 pub mod lights;
 pub use lights::*;
 
+pub mod observer;
+pub use observer::*;
+
+pub mod passes;
+pub use passes::*;
+
+pub mod validation;
+pub use validation::*;
+
 pub mod world;
 pub use world::*;
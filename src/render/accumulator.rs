@@ -0,0 +1,225 @@
+use crate::formats::FormatError;
+use crate::render::Canvas;
+use crate::utils::Float;
+use crate::Color;
+use std::convert::TryInto;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Magic number and version at the start of a saved accumulator.
+const MAGIC: &[u8; 8] = b"SORBACC2";
+/// Bytes before the pixels in a saved accumulator: the magic number, width, height and
+/// fingerprint.
+const HEADER_BYTES: usize = 32;
+/// Bytes per pixel in a saved accumulator: three `f64` sums and a `u64` count, whatever the
+/// precision of [`Float`].
+const PIXEL_BYTES: usize = 32;
+
+/// Running sum and number of samples of every pixel, so a render can be refined in passes and
+/// continued after a restart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+  width: usize,
+  height: usize,
+  sums: Vec<Color>,
+  counts: Vec<u64>,
+  fingerprint: u64,
+}
+impl Accumulator {
+  pub fn new(width: usize, height: usize) -> Self {
+    return Self {
+      width,
+      height,
+      sums: vec![Color::black(); width * height],
+      counts: vec![0; width * height],
+      fingerprint: 0,
+    };
+  }
+
+  /// Sets a value identifying what is rendered, such as a hash of the scene, so samples of a
+  /// different scene are not mixed in when a saved accumulator is continued.
+  pub fn with_fingerprint(mut self, fingerprint: u64) -> Self {
+    self.fingerprint = fingerprint;
+    return self;
+  }
+
+  pub fn width(&self) -> usize {
+    return self.width;
+  }
+  pub fn height(&self) -> usize {
+    return self.height;
+  }
+  pub fn fingerprint(&self) -> u64 {
+    return self.fingerprint;
+  }
+
+  fn pixel_index(&self, x: usize, y: usize) -> usize {
+    return y * self.width() + x;
+  }
+  /// Returns the sum of the samples of a pixel.
+  pub fn sum(&self, x: usize, y: usize) -> &Color {
+    return &self.sums[self.pixel_index(x, y)];
+  }
+  pub fn sample_count(&self, x: usize, y: usize) -> u64 {
+    return self.counts[self.pixel_index(x, y)];
+  }
+  /// Returns the smallest number of samples of any pixel.
+  pub fn min_sample_count(&self) -> u64 {
+    return self.counts.iter().cloned().min().unwrap_or(0);
+  }
+
+  /// Adds `count` samples adding up to `sum` to a pixel.
+  pub fn add_samples(&mut self, x: usize, y: usize, sum: &Color, count: u64) {
+    let index = self.pixel_index(x, y);
+    self.sums[index] = self.sums[index] + *sum;
+    self.counts[index] += count;
+  }
+
+  /// Returns the average of the samples of every pixel. Pixels without samples are black.
+  pub fn to_canvas(&self) -> Canvas {
+    let mut canvas = Canvas::new(self.width(), self.height());
+    for y in 0..self.height() {
+      for x in 0..self.width() {
+        let count = self.sample_count(x, y);
+        if count > 0 {
+          canvas.set_pixel(x, y, &(*self.sum(x, y) * (1. / count as Float)));
+        }
+      }
+    }
+    return canvas;
+  }
+
+  /// Decodes an accumulator written by [`write`](Accumulator::write).
+  pub fn read(data: &[u8]) -> Result<Self, FormatError> {
+    if !data.starts_with(MAGIC) {
+      return Err(FormatError::InvalidData(
+        "not a saved accumulator".to_string(),
+      ));
+    }
+    let u64_at = |offset: usize| {
+      return u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    };
+    if data.len() < HEADER_BYTES {
+      return Err(FormatError::InvalidData(
+        "truncated accumulator header".to_string(),
+      ));
+    }
+    let (width, height) = (u64_at(8) as usize, u64_at(16) as usize);
+    let expected = width
+      .checked_mul(height)
+      .and_then(|pixels| pixels.checked_mul(PIXEL_BYTES))
+      .and_then(|bytes| bytes.checked_add(HEADER_BYTES));
+    if expected != Some(data.len()) {
+      return Err(FormatError::InvalidData(format!(
+        "expected {}x{} pixels of accumulated samples, found {} bytes",
+        width,
+        height,
+        data.len()
+      )));
+    }
+
+    let mut accumulator = Self::new(width, height).with_fingerprint(u64_at(24));
+    for (index, pixel) in data[HEADER_BYTES..].chunks_exact(PIXEL_BYTES).enumerate() {
+      let value = |offset: usize| {
+        return u64::from_le_bytes(pixel[offset..offset + 8].try_into().unwrap());
+      };
+      accumulator.sums[index] = Color::new(
+        f64::from_bits(value(0)) as Float,
+        f64::from_bits(value(8)) as Float,
+        f64::from_bits(value(16)) as Float,
+      );
+      accumulator.counts[index] = value(24);
+    }
+    return Ok(accumulator);
+  }
+
+  /// Writes the sums and counts losslessly to `writer`, in a format only this crate reads.
+  // Sums are already `f64` in double precision.
+  #[cfg_attr(not(feature = "f32"), allow(clippy::unnecessary_cast))]
+  pub fn write<W: Write>(&self, writer: W) -> Result<(), FormatError> {
+    let mut writer = BufWriter::new(writer);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(self.width() as u64).to_le_bytes())?;
+    writer.write_all(&(self.height() as u64).to_le_bytes())?;
+    writer.write_all(&self.fingerprint().to_le_bytes())?;
+    for (sum, count) in self.sums.iter().zip(&self.counts) {
+      for value in [sum.r, sum.g, sum.b] {
+        writer.write_all(&(value as f64).to_bits().to_le_bytes())?;
+      }
+      writer.write_all(&count.to_le_bytes())?;
+    }
+    writer.flush()?;
+    return Ok(());
+  }
+
+  /// Loads an accumulator saved with [`save`](Accumulator::save).
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FormatError> {
+    return Self::read(&fs::read(path)?);
+  }
+
+  /// Saves the accumulator to a file. The file is replaced only once the new contents are
+  /// completely written, so a render killed while saving keeps its previous checkpoint.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FormatError> {
+    let path = path.as_ref();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    self.write(fs::File::create(&temporary)?)?;
+    fs::rename(&temporary, path)?;
+    return Ok(());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn init_new() {
+    let accumulator = Accumulator::new(3, 2);
+    assert_eq!(accumulator.width(), 3);
+    assert_eq!(accumulator.height(), 2);
+    assert_eq!(accumulator.min_sample_count(), 0);
+    assert_eq!(accumulator.to_canvas().pixels(), Canvas::new(3, 2).pixels());
+  }
+
+  #[test]
+  fn to_canvas_averages_samples() {
+    let mut accumulator = Accumulator::new(2, 1);
+    accumulator.add_samples(0, 0, &Color::new(1., 0.5, 0.), 1);
+    accumulator.add_samples(0, 0, &Color::new(1., 2.5, 3.), 3);
+    assert_eq!(accumulator.sample_count(0, 0), 4);
+    assert_eq!(accumulator.min_sample_count(), 0);
+    let canvas = accumulator.to_canvas();
+    assert_eq!(canvas.pixel(0, 0), &Color::new(0.5, 0.75, 0.75));
+    assert_eq!(canvas.pixel(1, 0), &Color::black());
+  }
+
+  #[test]
+  fn write_read_round_trip() {
+    let mut accumulator = Accumulator::new(2, 3).with_fingerprint(0x5eed);
+    accumulator.add_samples(1, 2, &Color::new(0.1, 20., -3.), 7);
+    let mut data = Vec::new();
+    accumulator.write(&mut data).unwrap();
+    assert_eq!(data.len(), HEADER_BYTES + 6 * PIXEL_BYTES);
+    assert_eq!(Accumulator::read(&data).unwrap(), accumulator);
+  }
+
+  #[test]
+  fn read_rejects_invalid_data() {
+    assert!(Accumulator::read(b"P3\n1 1\n255\n").is_err());
+    let mut data = Vec::new();
+    Accumulator::new(2, 2).write(&mut data).unwrap();
+    assert!(Accumulator::read(&data[..data.len() - 1]).is_err());
+  }
+
+  #[test]
+  fn save_and_open() {
+    let path = std::env::temp_dir().join(format!("sorb-accumulator-{}", std::process::id()));
+    let mut accumulator = Accumulator::new(1, 1);
+    accumulator.add_samples(0, 0, &Color::white(), 2);
+    accumulator.save(&path).unwrap();
+    assert_eq!(Accumulator::open(&path).unwrap(), accumulator);
+    fs::remove_file(&path).unwrap();
+  }
+}
@@ -1,55 +1,91 @@
 use crate::geometry::{Matrix, Point, Ray, Vector};
-use crate::render::{Canvas, World};
+use crate::render::{
+  Accumulator, Canvas, PartialRender, RayHit, RenderLayers, RenderObserver, RenderPass, RowEvent,
+  World,
+};
+use crate::shapes::find_hit;
+use crate::utils::{r2_sequence, Float, Rng};
+use crate::Color;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 pub struct Camera {
   canvas_width: usize,
   canvas_height: usize,
-  fov: f64,
+  fov: Float,
   camera_to_world: Matrix,
+  samples: usize,
 }
 impl Camera {
-  pub fn new(canvas_width: usize, canvas_height: usize, fov: f64, camera_to_world: Matrix) -> Self {
+  pub fn new(
+    canvas_width: usize,
+    canvas_height: usize,
+    fov: Float,
+    camera_to_world: Matrix,
+  ) -> Self {
     return Self {
       canvas_width,
       canvas_height,
       fov,
       camera_to_world,
+      samples: 1,
     };
   }
 
+  /// Sets the number of rays traced per pixel. Rays are spread over the pixel to smooth jagged
+  /// edges, deterministically, so renders are reproducible.
+  pub fn with_samples(mut self, samples: usize) -> Self {
+    self.samples = samples.max(1);
+    return self;
+  }
+
   pub fn canvas_width(&self) -> usize {
     return self.canvas_width;
   }
   pub fn canvas_height(&self) -> usize {
     return self.canvas_height;
   }
-  pub fn aspect_ratio(&self) -> f64 {
-    return self.canvas_width() as f64 / self.canvas_height() as f64;
+  pub fn aspect_ratio(&self) -> Float {
+    return self.canvas_width() as Float / self.canvas_height() as Float;
   }
 
-  pub fn fov(&self) -> f64 {
+  pub fn fov(&self) -> Float {
     return self.fov;
   }
-  fn fov_scale(&self) -> f64 {
+  fn fov_scale(&self) -> Float {
     return (self.fov().to_radians() / 2.).tan();
   }
 
   pub fn camera_to_world(&self) -> &Matrix {
     return &self.camera_to_world;
   }
+  pub fn set_camera_to_world(&mut self, camera_to_world: Matrix) {
+    self.camera_to_world = camera_to_world;
+  }
+
+  pub fn samples(&self) -> usize {
+    return self.samples;
+  }
 
-  fn pixel_size(&self) -> f64 {
-    return self.fov_scale() * 2. / self.canvas_height() as f64;
+  fn pixel_size(&self) -> Float {
+    return self.fov_scale() * 2. / self.canvas_height() as Float;
   }
-  fn canvas_left_edge(&self) -> f64 {
+  // Mirrors `canvas_top_edge`.
+  #[allow(clippy::neg_multiply)]
+  fn canvas_left_edge(&self) -> Float {
     return -1. * self.aspect_ratio() * self.fov_scale();
   }
-  fn canvas_top_edge(&self) -> f64 {
+  fn canvas_top_edge(&self) -> Float {
     return 1. * self.fov_scale();
   }
   pub fn ray_for_pixel(&self, x_pixel: usize, y_pixel: usize) -> Ray {
-    let x_offset = (x_pixel as f64 + 0.5) * self.pixel_size();
-    let y_offset = (y_pixel as f64 + 0.5) * self.pixel_size();
+    return self.ray_through(x_pixel as Float + 0.5, y_pixel as Float + 0.5);
+  }
+
+  /// Returns the ray through a point on the canvas, in pixels from its top left corner.
+  pub fn ray_through(&self, x: Float, y: Float) -> Ray {
+    let x_offset = x * self.pixel_size();
+    let y_offset = y * self.pixel_size();
 
     let x_world = self.canvas_left_edge() + x_offset;
     let y_world = self.canvas_top_edge() - y_offset;
@@ -58,20 +94,268 @@ impl Camera {
     return *self.camera_to_world() * Ray::new(Point::origin(), direction);
   }
 
+  /// Returns the color of a pixel, averaged over all samples.
+  pub fn pixel_color(&self, world: &World, x_pixel: usize, y_pixel: usize) -> Color {
+    let sum: Color = (0..self.samples)
+      .map(|index| self.pixel_sample(world, x_pixel, y_pixel, index))
+      .sum();
+    return sum * (1. / self.samples as Float);
+  }
+
+  /// Returns the color seen by sample `index` of a pixel. Samples are spread evenly over the
+  /// pixel in sequence, so any number of consecutive samples covers it well.
+  pub fn pixel_sample(&self, world: &World, x_pixel: usize, y_pixel: usize, index: usize) -> Color {
+    return world.color_at(&self.sample_ray(x_pixel, y_pixel, index));
+  }
+
+  /// Returns the ray of sample `index` of a pixel. The first sample goes through the pixel
+  /// center, so a single sample gives the same image however the samples are taken.
+  fn sample_ray(&self, x_pixel: usize, y_pixel: usize, index: usize) -> Ray {
+    if index == 0 {
+      return self.ray_for_pixel(x_pixel, y_pixel);
+    }
+    // Shifting the sample pattern randomly per pixel trades aliasing for less visible noise.
+    let mut rng = Rng::new((y_pixel * self.canvas_width() + x_pixel) as u64);
+    let shift = (rng.next_float(), rng.next_float());
+    let (u, v) = r2_sequence(index);
+    let x = x_pixel as Float + (u + shift.0).fract();
+    let y = y_pixel as Float + (v + shift.1).fract();
+    return self.ray_through(x, y);
+  }
+
+  /// Returns the first surface seen through the center of a pixel, with the object it belongs
+  /// to, or `None` if the pixel shows no object.
+  pub fn pick<'a>(&self, world: &'a World, x_pixel: usize, y_pixel: usize) -> Option<RayHit<'a>> {
+    return world.first_hit(&self.ray_for_pixel(x_pixel, y_pixel));
+  }
+
   pub fn render(&self, world: &World) -> Canvas {
+    return self.render_observed(world, 1, &()).into_canvas();
+  }
+
+  /// Renders the world on `threads` threads, which take rows in turn. After every row,
+  /// `on_row` is called with the number of rows finished so far.
+  ///
+  /// The result does not depend on the number of threads.
+  pub fn render_parallel<F>(&self, world: &World, threads: usize, on_row: F) -> Canvas
+  where
+    F: Fn(usize) + Sync,
+  {
+    return self
+      .render_observed(world, threads, &RowCallback(on_row))
+      .into_canvas();
+  }
+
+  /// Renders the world on `threads` threads like
+  /// [`render_parallel`](Camera::render_parallel), reporting every finished row to `observer`
+  /// and stopping early when it asks to.
+  pub fn render_observed<O>(&self, world: &World, threads: usize, observer: &O) -> PartialRender
+  where
+    O: RenderObserver + ?Sized,
+  {
+    let rows = self.render_rows(threads, observer, |x, y| self.pixel_color(world, x, y));
     let mut canvas = Canvas::new(self.canvas_width(), self.canvas_height());
+    let mut finished = vec![false; self.canvas_width() * self.canvas_height()];
+    for (y, row) in rows {
+      for (x, color) in row.iter().enumerate() {
+        canvas.set_pixel(x, y, color);
+        finished[y * self.canvas_width() + x] = true;
+      }
+    }
+    return PartialRender::new(canvas, finished);
+  }
+
+  /// Adds samples to every pixel of `accumulator` until it has `samples` samples, continuing
+  /// the sample sequence of each pixel where it left off, on `threads` threads. Calling it with
+  /// a growing number of samples refines the whole image in passes.
+  ///
+  /// Rows are added whole, so when `observer` stops the pass early the accumulator stays
+  /// consistent, and the next call brings the remaining rows up to date. Returns whether the
+  /// pass was completed. Panics if the accumulator and the canvas of the camera have different
+  /// sizes.
+  pub fn accumulate<O>(
+    &self,
+    world: &World,
+    threads: usize,
+    accumulator: &mut Accumulator,
+    samples: u64,
+    observer: &O,
+  ) -> bool
+  where
+    O: RenderObserver + ?Sized,
+  {
+    assert!(
+      accumulator.width() == self.canvas_width() && accumulator.height() == self.canvas_height(),
+      "Cannot accumulate a {}x{} render into a {}x{} accumulator",
+      self.canvas_width(),
+      self.canvas_height(),
+      accumulator.width(),
+      accumulator.height()
+    );
+    let previous: &Accumulator = accumulator;
+    let rows = self.render_rows(threads, observer, |x, y| {
+      let start = previous.sample_count(x, y);
+      return (start..samples.max(start))
+        .map(|index| self.pixel_sample(world, x, y, index as usize))
+        .sum();
+    });
+
+    let complete = rows.len() == self.canvas_height();
+    for (y, row) in rows {
+      for (x, sum) in row.iter().enumerate() {
+        let added = samples.saturating_sub(accumulator.sample_count(x, y));
+        accumulator.add_samples(x, y, sum, added);
+      }
+    }
+    return complete;
+  }
+
+  /// Computes the pixels of rows with `shade` on `threads` threads, which take rows in turn
+  /// until all are done or `observer` asks to stop. Returns the finished rows in no particular
+  /// order.
+  fn render_rows<O, F, T>(&self, threads: usize, observer: &O, shade: F) -> Vec<(usize, Vec<T>)>
+  where
+    O: RenderObserver + ?Sized,
+    F: Fn(usize, usize) -> T + Sync,
+    T: Send,
+  {
+    let total_rows = self.canvas_height();
+    let next_row = AtomicUsize::new(0);
+    let finished_rows = AtomicUsize::new(0);
+    let render_rows = || {
+      let mut rows = Vec::new();
+      while !observer.should_stop() {
+        let y = next_row.fetch_add(1, Ordering::Relaxed);
+        if y >= total_rows {
+          break;
+        }
+        let row: Vec<T> = (0..self.canvas_width()).map(|x| shade(x, y)).collect();
+        rows.push((y, row));
+        observer.row_finished(&RowEvent {
+          y,
+          finished_rows: finished_rows.fetch_add(1, Ordering::Relaxed) + 1,
+          total_rows,
+        });
+      }
+      return rows;
+    };
+
+    return thread::scope(|scope| {
+      let handles: Vec<_> = (0..threads.max(1))
+        .map(|_| scope.spawn(render_rows))
+        .collect();
+      return handles
+        .into_iter()
+        .flat_map(|handle| handle.join().expect("Render thread panicked"))
+        .collect();
+    });
+  }
+
+  /// Renders the world like [`render`](Camera::render), also filling the requested passes from
+  /// the primary hits of every pixel.
+  pub fn render_passes(&self, world: &World, passes: &[RenderPass]) -> RenderLayers {
+    return self.render_passes_observed(world, 1, passes, &());
+  }
+
+  /// Renders the world and the requested passes on `threads` threads like
+  /// [`render_observed`](Camera::render_observed). Rows that were not finished when `observer`
+  /// stopped the render are black in every layer.
+  ///
+  /// Passes are averaged over the samples of each pixel like the shaded image, except for
+  /// [`ObjectId`](RenderPass::ObjectId), which comes from the first sample, through the pixel
+  /// center.
+  pub fn render_passes_observed<O>(
+    &self,
+    world: &World,
+    threads: usize,
+    passes: &[RenderPass],
+    observer: &O,
+  ) -> RenderLayers
+  where
+    O: RenderObserver + ?Sized,
+  {
+    let mut layers = RenderLayers::new(self.canvas_width(), self.canvas_height(), passes);
+    let passes: Vec<RenderPass> = layers.passes().iter().map(|(pass, _)| *pass).collect();
+    let rows = self.render_rows(threads, observer, |x, y| {
+      return self.pixel_layers(world, x, y, &passes);
+    });
+
+    for (y, row) in rows {
+      for (x, colors) in row.iter().enumerate() {
+        layers.beauty_mut().set_pixel(x, y, &colors[0]);
+        for (pass, color) in passes.iter().zip(&colors[1..]) {
+          if let Some(canvas) = layers.pass_mut(*pass) {
+            canvas.set_pixel(x, y, color);
+          }
+        }
+      }
+    }
+    return layers;
+  }
 
-    for i in 0..canvas.width() {
-      for j in 0..canvas.height() {
-        let ray = self.ray_for_pixel(i, j);
-        let color = world.color_at(&ray);
-        canvas.set_pixel(i, j, &color);
+  /// Returns the shaded color of a pixel followed by the color of every pass in `passes`.
+  fn pixel_layers(
+    &self,
+    world: &World,
+    x_pixel: usize,
+    y_pixel: usize,
+    passes: &[RenderPass],
+  ) -> Vec<Color> {
+    let forward = (*self.camera_to_world() * Vector::new(0., 0., -1.)).normalize();
+    let mut sums = vec![Color::black(); passes.len() + 1];
+    for index in 0..self.samples {
+      let ray = self.sample_ray(x_pixel, y_pixel, index);
+      let intersections = world.intersect(&ray);
+      let hit = match find_hit(&intersections) {
+        None => continue,
+        Some(hit) => hit,
+      };
+      let lighting = world.lighting(hit);
+      sums[0] = sums[0] + lighting.total();
+
+      for (sum, pass) in sums[1..].iter_mut().zip(passes) {
+        let color = match pass {
+          RenderPass::Depth => {
+            let depth = hit.time * ray.direction.dot(&forward);
+            Color::new(depth, depth, depth)
+          }
+          RenderPass::Normal => Color::new(hit.normal.x, hit.normal.y, hit.normal.z),
+          RenderPass::Albedo => hit.base_color(),
+          RenderPass::ObjectId if index > 0 => continue,
+          RenderPass::ObjectId => {
+            let id = world
+              .object_index(hit)
+              .map_or(0., |index| (index + 1) as Float);
+            Color::new(id, id, id)
+          }
+          RenderPass::Direct => lighting.direct(),
+          RenderPass::Indirect => lighting.indirect(),
+        };
+        *sum = *sum + color;
       }
     }
 
-    return canvas;
+    let scale = 1. / self.samples as Float;
+    sums[0] = sums[0] * scale;
+    for (sum, pass) in sums[1..].iter_mut().zip(passes) {
+      if *pass != RenderPass::ObjectId {
+        *sum = *sum * scale;
+      }
+    }
+    return sums;
+  }
+}
+/// Adapts a closure taking the number of finished rows to a [`RenderObserver`].
+struct RowCallback<F>(F);
+impl<F> RenderObserver for RowCallback<F>
+where
+  F: Fn(usize) + Sync,
+{
+  fn row_finished(&self, event: &RowEvent) {
+    (self.0)(event.finished_rows);
   }
 }
+
 impl Default for Camera {
   fn default() -> Self {
     return Camera::new(640, 640, 90., Matrix::identity());
@@ -81,6 +365,11 @@ impl Default for Camera {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::geometry::Material;
+  use crate::render::{Cancellation, PointLight, TimeBudget};
+  use crate::shapes::{Plane, Sphere};
+  use crate::utils::consts::PI;
+  use std::time::Duration;
 
   #[test]
   fn init_new() {
@@ -195,4 +484,249 @@ mod tests {
       )
     );
   }
+
+  fn test_world() -> World {
+    return World::new(
+      vec![
+        Box::new(Sphere::new(
+          Material::new(Box::new(Color::new(0.8, 1., 0.6)), 0.1, 0.7, 0.2, 200.),
+          Matrix::identity().translate(0., 0., -3.),
+        )),
+        Box::new(Plane::new(
+          Material::new(Box::new(Color::blue()), 1., 0., 0., 0.),
+          Matrix::identity().rotate_x(PI / 2.).translate(0., 0., -10.),
+        )),
+      ],
+      vec![PointLight::new(Point::new(0., 0., 0.), Color::white())],
+    );
+  }
+
+  #[test]
+  fn render_passes() {
+    let camera = Camera::new(11, 11, 90., Matrix::identity());
+    let world = test_world();
+    let layers = camera.render_passes(&world, &RenderPass::all());
+    assert_eq!(layers.passes().len(), 6);
+    assert_eq!(layers.beauty().pixels(), camera.render(&world).pixels());
+
+    let pass = |pass| layers.pass(pass).unwrap().pixel(5, 5);
+    assert_eq!(pass(RenderPass::Depth), &Color::new(2., 2., 2.));
+    assert_eq!(pass(RenderPass::Normal), &Color::new(0., 0., 1.));
+    assert_eq!(pass(RenderPass::Albedo), &Color::new(0.8, 1., 0.6));
+    assert_eq!(pass(RenderPass::ObjectId), &Color::new(1., 1., 1.));
+    assert_eq!(pass(RenderPass::Indirect), &Color::new(0.08, 0.1, 0.06));
+    assert_eq!(
+      *pass(RenderPass::Direct) + *pass(RenderPass::Indirect),
+      *layers.beauty().pixel(5, 5)
+    );
+
+    // The corner looks past the sphere at the plane, which only has ambient light.
+    let pass = |pass| layers.pass(pass).unwrap().pixel(0, 0);
+    assert_eq!(pass(RenderPass::ObjectId), &Color::new(2., 2., 2.));
+    assert_eq!(pass(RenderPass::Depth), &Color::new(10., 10., 10.));
+    assert_eq!(pass(RenderPass::Direct), &Color::black());
+    assert_eq!(pass(RenderPass::Indirect), &Color::blue());
+  }
+
+  #[test]
+  fn render_passes_with_samples() {
+    let world = test_world();
+    let camera = Camera::new(21, 21, 90., Matrix::identity()).with_samples(16);
+    let layers = camera.render_passes(&world, &RenderPass::all());
+    assert_eq!(layers.beauty().pixels(), camera.render(&world).pixels());
+    let threaded = camera.render_passes_observed(&world, 3, &RenderPass::all(), &());
+    for (pass, canvas) in layers.passes() {
+      assert_eq!(threaded.pass(*pass).unwrap().pixels(), canvas.pixels());
+    }
+
+    // A pixel on the edge of the sphere blends the depths and normals of its samples, but keeps
+    // the id of the object at its center.
+    let pass = |pass| layers.pass(pass).unwrap().pixel(6, 10);
+    let depth = pass(RenderPass::Depth).r;
+    assert!(depth > 2. && depth < 10.);
+    let normal = pass(RenderPass::Normal);
+    assert!(normal.r < 0. && normal.b > 0. && normal.b < 1.);
+    assert_eq!(
+      pass(RenderPass::ObjectId),
+      Camera::new(21, 21, 90., Matrix::identity())
+        .render_passes(&world, &[RenderPass::ObjectId])
+        .pass(RenderPass::ObjectId)
+        .unwrap()
+        .pixel(6, 10)
+    );
+    assert_eq!(
+      *pass(RenderPass::Direct) + *pass(RenderPass::Indirect),
+      *layers.beauty().pixel(6, 10)
+    );
+  }
+
+  #[test]
+  fn init_with_samples() {
+    let camera = Camera::default();
+    assert_eq!(camera.samples(), 1);
+    assert_eq!(camera.with_samples(16).samples(), 16);
+    assert_eq!(Camera::default().with_samples(0).samples(), 1);
+  }
+
+  #[test]
+  fn ray_through_pixel_center() {
+    let camera = Camera::new(201, 101, 90., Matrix::identity());
+    assert_eq!(
+      camera.ray_through(100.5, 50.5),
+      camera.ray_for_pixel(100, 50)
+    );
+  }
+
+  #[test]
+  fn pixel_color_averages_samples() {
+    let world = test_world();
+    let camera = Camera::new(11, 11, 90., Matrix::identity());
+    // A pixel showing only the flat, evenly lit plane is the same with any number of samples.
+    let camera = camera.with_samples(8);
+    assert_eq!(camera.pixel_color(&world, 0, 0), Color::blue());
+
+    // A pixel on the edge of the sphere blends the sphere with the background plane.
+    let camera = Camera::new(21, 21, 90., Matrix::identity()).with_samples(64);
+    let edge = camera.pixel_color(&world, 6, 10);
+    let sphere = Camera::new(21, 21, 90., Matrix::identity()).pixel_color(&world, 10, 10);
+    assert!(edge.b > sphere.b && edge.b < 1.);
+    assert!(edge.g > 0. && edge.g < sphere.g);
+  }
+
+  #[test]
+  fn render_parallel_matches_render() {
+    let world = test_world();
+    let camera = Camera::new(16, 9, 90., Matrix::identity()).with_samples(4);
+    let finished = AtomicUsize::new(0);
+    let canvas = camera.render_parallel(&world, 3, |rows| {
+      finished.fetch_max(rows, Ordering::Relaxed);
+    });
+    assert_eq!(finished.load(Ordering::Relaxed), 9);
+    assert_eq!(canvas.pixels(), camera.render(&world).pixels());
+  }
+
+  #[test]
+  fn render_observed_stops_when_asked() {
+    struct StopAfter(usize, Cancellation);
+    impl RenderObserver for StopAfter {
+      fn row_finished(&self, event: &RowEvent) {
+        if event.finished_rows >= self.0 {
+          self.1.cancel();
+        }
+      }
+      fn should_stop(&self) -> bool {
+        return self.1.is_cancelled();
+      }
+    }
+
+    let world = test_world();
+    let camera = Camera::new(8, 6, 90., Matrix::identity());
+    let render = camera.render_observed(&world, 1, &StopAfter(2, Cancellation::new()));
+    assert!(!render.is_complete());
+    assert_eq!(render.finished_pixels(), 16);
+    assert!(render.is_finished(7, 1));
+    assert!(!render.is_finished(0, 2));
+    assert_eq!(
+      render.canvas().pixel(3, 1),
+      camera.render(&world).pixel(3, 1)
+    );
+    assert_eq!(render.canvas().pixel(3, 4), &Color::black());
+  }
+
+  #[test]
+  fn render_observed_with_exhausted_budget() {
+    let camera = Camera::new(8, 6, 90., Matrix::identity());
+    let budget = TimeBudget::new(Duration::from_secs(0));
+    let render = camera.render_observed(&test_world(), 2, &budget);
+    assert_eq!(render.finished_pixels(), 0);
+
+    let budget = TimeBudget::new(Duration::from_secs(600));
+    assert!(camera
+      .render_observed(&test_world(), 2, &budget)
+      .is_complete());
+  }
+
+  #[test]
+  fn accumulate_matches_multisampled_render() {
+    let world = test_world();
+    let camera = Camera::new(8, 6, 90., Matrix::identity());
+    let mut accumulator = Accumulator::new(8, 6);
+    assert!(camera.accumulate(&world, 2, &mut accumulator, 4, &()));
+    assert_eq!(accumulator.min_sample_count(), 4);
+    let expected = camera.with_samples(4).render(&world);
+    assert_eq!(accumulator.to_canvas().pixels(), expected.pixels());
+  }
+
+  #[test]
+  fn accumulate_single_sample_matches_render() {
+    let world = test_world();
+    let camera = Camera::new(21, 21, 90., Matrix::identity());
+    let mut accumulator = Accumulator::new(21, 21);
+    assert!(camera.accumulate(&world, 2, &mut accumulator, 1, &()));
+    assert_eq!(
+      accumulator.to_canvas().pixels(),
+      camera.render(&world).pixels()
+    );
+  }
+
+  #[test]
+  fn accumulate_in_passes() {
+    let world = test_world();
+    let camera = Camera::new(8, 6, 90., Matrix::identity());
+    let mut single_pass = Accumulator::new(8, 6);
+    camera.accumulate(&world, 1, &mut single_pass, 6, &());
+
+    // A pass stopped after two rows is caught up by the next one.
+    let mut passes = Accumulator::new(8, 6);
+    camera.accumulate(&world, 1, &mut passes, 2, &());
+    let stop = Cancellation::new();
+    let stop_after_two_rows = (
+      RowCallback(|rows| {
+        if rows == 2 {
+          stop.cancel();
+        }
+      }),
+      stop.clone(),
+    );
+    assert!(!camera.accumulate(&world, 1, &mut passes, 4, &stop_after_two_rows));
+    assert_eq!(passes.sample_count(0, 1), 4);
+    assert_eq!(passes.sample_count(0, 2), 2);
+    assert!(camera.accumulate(&world, 1, &mut passes, 6, &()));
+
+    assert_eq!(passes.min_sample_count(), 6);
+    assert_eq!(passes.sample_count(3, 5), 6);
+    assert_eq!(
+      passes.to_canvas().pixels(),
+      single_pass.to_canvas().pixels()
+    );
+  }
+
+  #[test]
+  fn pick() {
+    let world = test_world();
+    let camera = Camera::new(11, 11, 90., Matrix::identity());
+    let hit = camera.pick(&world, 5, 5).unwrap();
+    assert_eq!(hit.object, world.handles()[0]);
+    assert_eq!(hit.point, Point::new(0., 0., -2.));
+    assert_eq!(
+      hit.material.color_at(&Point::origin()),
+      Color::new(0.8, 1., 0.6)
+    );
+    assert_eq!(
+      camera.pick(&world, 0, 0).unwrap().object,
+      world.handles()[1]
+    );
+
+    let empty = World::default();
+    assert!(camera.pick(&empty, 5, 5).is_none());
+  }
+
+  #[test]
+  fn render_passes_misses_are_black() {
+    let camera = Camera::new(3, 3, 90., Matrix::identity().rotate_y(PI));
+    let layers = camera.render_passes(&test_world(), &[RenderPass::Depth, RenderPass::Normal]);
+    for (_, canvas) in layers.passes() {
+      assert!(canvas.pixels().iter().all(|pixel| pixel == &Color::black()));
+    }
+  }
 }
@@ -1,7 +1,15 @@
 use crate::color::Color;
-use crate::utils::clamp_number;
+use crate::formats::{
+  read_image, write_exr, write_pfm, write_png, write_ppm, ExrCompression, FormatError, PngBitDepth,
+  PpmEncoding,
+};
+use crate::render::DisplayTransform;
+use crate::utils::Float;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Canvas {
   width: usize,
   height: usize,
@@ -16,14 +24,20 @@ impl Canvas {
     };
   }
 
+  /// Loads a PPM, PFM, PNG or OpenEXR image from disk.
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FormatError> {
+    let data = fs::read(path)?;
+    return read_image(&data);
+  }
+
   pub fn width(&self) -> usize {
     return self.width;
   }
   pub fn height(&self) -> usize {
     return self.height;
   }
-  pub fn aspect_ratio(&self) -> f64 {
-    return self.width() as f64 / self.height() as f64;
+  pub fn aspect_ratio(&self) -> Float {
+    return self.width() as Float / self.height() as Float;
   }
 
   fn pixel_index(&self, x: usize, y: usize) -> usize {
@@ -40,18 +54,48 @@ impl Canvas {
     self.pixels[index].set(color);
   }
 
-  pub fn to_ppm(&self) -> String {
-    let mut data = format!("P3\n{} {}\n255\n", self.width(), self.height());
-    for pixel in self.pixels() {
-      data.push_str(&format!(
-        "{} {} {} ",
-        clamp_number(pixel.r * 255., 0., 255.).round(),
-        clamp_number(pixel.g * 255., 0., 255.).round(),
-        clamp_number(pixel.b * 255., 0., 255.).round()
-      ));
-    }
+  /// Writes the canvas as a PPM image to `writer`, one row at a time, converting colors with
+  /// `display`.
+  pub fn write_ppm<W: Write>(
+    &self,
+    writer: W,
+    encoding: PpmEncoding,
+    display: &DisplayTransform,
+  ) -> Result<(), FormatError> {
+    return write_ppm(self, writer, encoding, display);
+  }
+
+  /// Writes the canvas as a PNG image to `writer`, converting colors with `display`.
+  pub fn write_png<W: Write>(
+    &self,
+    writer: W,
+    bit_depth: PngBitDepth,
+    display: &DisplayTransform,
+  ) -> Result<(), FormatError> {
+    return write_png(self, writer, bit_depth, display);
+  }
 
-    return data;
+  /// Writes the canvas as a PFM image to `writer`, keeping colors outside `0.0..=1.0`.
+  pub fn write_pfm<W: Write>(&self, writer: W) -> Result<(), FormatError> {
+    return write_pfm(self, writer);
+  }
+
+  /// Writes the canvas as an OpenEXR image to `writer`, keeping colors outside `0.0..=1.0`.
+  pub fn write_exr<W: Write>(
+    &self,
+    writer: W,
+    compression: ExrCompression,
+  ) -> Result<(), FormatError> {
+    return write_exr(self, writer, compression);
+  }
+
+  /// Returns the canvas as an ASCII PPM image with linear colors.
+  pub fn to_ppm(&self) -> String {
+    let mut data = Vec::new();
+    self
+      .write_ppm(&mut data, PpmEncoding::Ascii, &DisplayTransform::linear())
+      .expect("Writing to memory cannot fail");
+    return String::from_utf8(data).expect("ASCII PPM is valid UTF-8");
   }
 }
 
@@ -98,6 +142,58 @@ mod tests {
     assert_eq!(Canvas::new(10, 20).pixels().len(), 200);
   }
 
+  #[test]
+  fn to_ppm() {
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(1, 0, &Color::white());
+    assert_eq!(canvas.to_ppm(), "P3\n2 1\n255\n0 0 0 255 255 255\n");
+  }
+
+  #[test]
+  fn write_ppm_binary() {
+    let mut data = Vec::new();
+    let canvas = Canvas::new(1, 1);
+    canvas
+      .write_ppm(&mut data, PpmEncoding::Binary, &DisplayTransform::linear())
+      .expect("Expected PPM to be written");
+    assert_eq!(data, b"P6\n1 1\n255\n\0\0\0");
+  }
+
+  #[test]
+  fn write_png_round_trip() {
+    let mut data = Vec::new();
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(1, 0, &Color::new(1., 0.5, 0.));
+    canvas
+      .write_png(
+        &mut data,
+        PngBitDepth::Sixteen,
+        &DisplayTransform::default(),
+      )
+      .expect("Expected PNG to be written");
+    let decoded = read_image(&data).expect("Expected PNG to be read");
+    assert_eq!(decoded.pixel(0, 0), &Color::black());
+    assert_eq!(decoded.pixel(1, 0), &Color::new(1., 0.5, 0.));
+  }
+
+  #[test]
+  fn write_hdr_round_trip() {
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(1, 0, &Color::new(8., 0.5, -1.));
+    let mut pfm = Vec::new();
+    canvas
+      .write_pfm(&mut pfm)
+      .expect("Expected PFM to be written");
+    let mut exr = Vec::new();
+    canvas
+      .write_exr(&mut exr, ExrCompression::Zip)
+      .expect("Expected OpenEXR to be written");
+    for data in [pfm, exr].iter() {
+      let decoded = read_image(data).expect("Expected image to be read");
+      assert_eq!(decoded.pixels(), canvas.pixels());
+    }
+  }
+
   #[test]
   fn get_set_pixel() {
     let mut canvas = Canvas::new(10, 20);
@@ -0,0 +1,137 @@
+use crate::render::Canvas;
+use crate::utils::Float;
+use crate::Color;
+
+/// Differences between a canvas and a reference canvas of the same size, such as a render and
+/// a known good render of the same scene.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+  rmse: Float,
+  max_delta: Float,
+  differing_pixels: usize,
+  diff: Canvas,
+}
+impl Comparison {
+  /// Compares `actual` against `expected`. The difference of a pixel is the largest absolute
+  /// difference of its channels, and pixels differ when it exceeds `tolerance`.
+  ///
+  /// Panics if the canvases have different sizes.
+  pub fn new(actual: &Canvas, expected: &Canvas, tolerance: Float) -> Self {
+    assert!(
+      actual.width() == expected.width() && actual.height() == expected.height(),
+      "Cannot compare a {}x{} canvas with a {}x{} canvas",
+      actual.width(),
+      actual.height(),
+      expected.width(),
+      expected.height()
+    );
+
+    let mut squared_error = 0.;
+    let mut max_delta: Float = 0.;
+    let mut differing_pixels = 0;
+    let mut deltas = Vec::with_capacity(actual.pixels().len());
+    for (a, e) in actual.pixels().iter().zip(expected.pixels()) {
+      let channels = [(a.r - e.r).abs(), (a.g - e.g).abs(), (a.b - e.b).abs()];
+      squared_error += channels.iter().map(|delta| delta * delta).sum::<Float>();
+      let delta = channels.iter().cloned().fold(0., Float::max);
+      max_delta = max_delta.max(delta);
+      if delta > tolerance {
+        differing_pixels += 1;
+      }
+      deltas.push(delta);
+    }
+    let samples = (actual.pixels().len() * 3).max(1) as Float;
+
+    // Matching pixels are a dim grey copy of the reference, so the differing ones stand out in
+    // red, brighter the more they differ.
+    let mut diff = Canvas::new(expected.width(), expected.height());
+    for y in 0..expected.height() {
+      for x in 0..expected.width() {
+        let delta = deltas[y * expected.width() + x];
+        let color = if delta > tolerance {
+          Color::new(0.5 + 0.5 * delta / max_delta, 0., 0.)
+        } else {
+          let reference = expected.pixel(x, y);
+          let grey = 0.25 * (reference.r + reference.g + reference.b) / 3.;
+          Color::new(grey, grey, grey)
+        };
+        diff.set_pixel(x, y, &color);
+      }
+    }
+
+    return Self {
+      rmse: (squared_error / samples).sqrt(),
+      max_delta,
+      differing_pixels,
+      diff,
+    };
+  }
+
+  /// Root-mean-square difference over all channels of all pixels.
+  pub fn rmse(&self) -> Float {
+    return self.rmse;
+  }
+  /// Largest difference of any channel of any pixel.
+  pub fn max_delta(&self) -> Float {
+    return self.max_delta;
+  }
+  /// Number of pixels differing by more than the tolerance.
+  pub fn differing_pixels(&self) -> usize {
+    return self.differing_pixels;
+  }
+  /// Image highlighting the differing pixels in red over a dimmed copy of the reference.
+  pub fn diff(&self) -> &Canvas {
+    return &self.diff;
+  }
+
+  /// Returns whether no pixel differs by more than the tolerance.
+  pub fn is_match(&self) -> bool {
+    return self.differing_pixels == 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::assert_ae;
+
+  fn canvas(colors: &[Color]) -> Canvas {
+    let mut canvas = Canvas::new(colors.len(), 1);
+    for (x, color) in colors.iter().enumerate() {
+      canvas.set_pixel(x, 0, color);
+    }
+    return canvas;
+  }
+
+  #[test]
+  fn identical_canvases_match() {
+    let expected = canvas(&[Color::red(), Color::new(0.2, 0.4, 0.6)]);
+    let comparison = Comparison::new(&expected, &expected, 0.);
+    assert!(comparison.is_match());
+    assert_eq!(comparison.rmse(), 0.);
+    assert_eq!(comparison.max_delta(), 0.);
+    assert_eq!(
+      comparison.diff().pixel(0, 0),
+      &Color::new(1. / 12., 1. / 12., 1. / 12.)
+    );
+  }
+
+  #[test]
+  fn metrics_of_differing_canvases() {
+    let expected = canvas(&[Color::black(), Color::black()]);
+    let actual = canvas(&[Color::new(0.01, 0., 0.), Color::new(0., 0.6, 0.)]);
+    let comparison = Comparison::new(&actual, &expected, 0.05);
+    assert!(!comparison.is_match());
+    assert_eq!(comparison.differing_pixels(), 1);
+    assert_ae!(comparison.max_delta(), 0.6);
+    assert_ae!(comparison.rmse(), ((0.0001 + 0.36) / (6. as Float)).sqrt());
+    assert_eq!(comparison.diff().pixel(0, 0), &Color::black());
+    assert_eq!(comparison.diff().pixel(1, 0), &Color::red());
+  }
+
+  #[test]
+  #[should_panic(expected = "Cannot compare a 2x1 canvas with a 1x1 canvas")]
+  fn comparing_different_sizes_panics() {
+    Comparison::new(&Canvas::new(2, 1), &Canvas::new(1, 1), 0.);
+  }
+}
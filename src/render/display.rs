@@ -0,0 +1,229 @@
+use crate::render::Canvas;
+use crate::utils::Float;
+use crate::Color;
+
+/// Curve compressing the unbounded linear light values of a render into the displayable
+/// `0.0..=1.0` range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneCurve {
+  /// Leaves values unchanged, so everything above 1.0 clips.
+  #[default]
+  Clamp,
+  /// `x / (1 + x)`, which never clips but flattens highlights.
+  Reinhard,
+  /// Krzysztof Narkowicz's fit of the ACES filmic reference rendering transform.
+  Aces,
+  /// John Hable's filmic curve from Uncharted 2, with a toe that deepens shadows. Values above
+  /// its white point clip.
+  Hable,
+}
+impl ToneCurve {
+  pub fn apply(&self, value: Float) -> Float {
+    let value = value.max(0.);
+    return match self {
+      ToneCurve::Clamp => value,
+      ToneCurve::Reinhard => value / (1. + value),
+      ToneCurve::Aces => {
+        let mapped = (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14);
+        mapped.min(1.)
+      }
+      ToneCurve::Hable => {
+        (hable_partial(HABLE_EXPOSURE_BIAS * value) / hable_partial(HABLE_WHITE)).min(1.)
+      }
+    };
+  }
+}
+
+const HABLE_EXPOSURE_BIAS: Float = 2.;
+/// Linear value mapped to white by the Hable curve.
+const HABLE_WHITE: Float = 11.2;
+
+fn hable_partial(x: Float) -> Float {
+  let (a, b, c, d, e, f) = (0.15, 0.5, 0.1, 0.2, 0.02, 0.3);
+  return (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f;
+}
+
+/// Encoding of the tone-mapped values stored in an image file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TransferFunction {
+  /// Stores linear values, as raytracers traditionally write PPM files.
+  Linear,
+  /// Stores values with the sRGB curve that displays expect, brightening the midtones.
+  #[default]
+  Srgb,
+}
+
+/// Conversion from the linear colors of a canvas to the values written to an 8- or 16-bit image:
+/// an exposure adjustment, a tone curve and a transfer function, in that order.
+///
+/// The canvas itself is never modified, so the same render can be exported with different
+/// transforms.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayTransform {
+  exposure: Float,
+  curve: ToneCurve,
+  transfer: TransferFunction,
+}
+impl DisplayTransform {
+  pub fn new(exposure: Float, curve: ToneCurve, transfer: TransferFunction) -> Self {
+    return Self {
+      exposure,
+      curve,
+      transfer,
+    };
+  }
+
+  /// Returns the transform that passes colors through unchanged.
+  pub fn linear() -> Self {
+    return Self::default().with_transfer(TransferFunction::Linear);
+  }
+
+  /// Sets the exposure adjustment in stops, each doubling or halving the brightness.
+  pub fn with_exposure(mut self, exposure: Float) -> Self {
+    self.exposure = exposure;
+    return self;
+  }
+  pub fn with_curve(mut self, curve: ToneCurve) -> Self {
+    self.curve = curve;
+    return self;
+  }
+  pub fn with_transfer(mut self, transfer: TransferFunction) -> Self {
+    self.transfer = transfer;
+    return self;
+  }
+
+  pub fn exposure(&self) -> Float {
+    return self.exposure;
+  }
+  pub fn curve(&self) -> ToneCurve {
+    return self.curve;
+  }
+  pub fn transfer(&self) -> TransferFunction {
+    return self.transfer;
+  }
+
+  /// Returns the display value of a linear color. Values may still fall outside `0.0..=1.0`
+  /// with the `Clamp` curve, and are clamped when quantized.
+  pub fn apply(&self, color: &Color) -> Color {
+    let exposed = *color * (2. as Float).powf(self.exposure);
+    let mapped = Color::new(
+      self.curve.apply(exposed.r),
+      self.curve.apply(exposed.g),
+      self.curve.apply(exposed.b),
+    );
+    return match self.transfer {
+      TransferFunction::Linear => mapped,
+      TransferFunction::Srgb => mapped.encode_srgb(),
+    };
+  }
+
+  /// Returns a copy of the canvas with the transform applied to every pixel.
+  pub fn apply_to_canvas(&self, canvas: &Canvas) -> Canvas {
+    let mut display = Canvas::new(canvas.width(), canvas.height());
+    for y in 0..canvas.height() {
+      for x in 0..canvas.width() {
+        display.set_pixel(x, y, &self.apply(canvas.pixel(x, y)));
+      }
+    }
+    return display;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn curves_map_black_to_black() {
+    for curve in [
+      ToneCurve::Clamp,
+      ToneCurve::Reinhard,
+      ToneCurve::Aces,
+      ToneCurve::Hable,
+    ]
+    .iter()
+    {
+      assert!(curve.apply(0.).abs() < 1e-3);
+      assert!(curve.apply(-2.).abs() < 1e-3);
+    }
+  }
+
+  #[test]
+  fn curves_are_monotonic_and_bounded() {
+    for curve in [ToneCurve::Reinhard, ToneCurve::Aces, ToneCurve::Hable].iter() {
+      let mut previous = curve.apply(0.);
+      for step in 1..200 {
+        let value = curve.apply(step as Float * 0.1);
+        assert!(value >= previous);
+        assert!(value <= 1.001);
+        previous = value;
+      }
+    }
+  }
+
+  #[test]
+  fn reinhard() {
+    assert_eq!(ToneCurve::Reinhard.apply(1.), 0.5);
+    assert_eq!(ToneCurve::Reinhard.apply(3.), 0.75);
+  }
+
+  #[test]
+  fn aces() {
+    assert!((ToneCurve::Aces.apply(1.) - 0.80379).abs() < 1e-5);
+    assert!((ToneCurve::Aces.apply(100.) - 1.).abs() < 0.01);
+  }
+
+  #[test]
+  fn hable_maps_white_point_to_one() {
+    assert!((ToneCurve::Hable.apply(HABLE_WHITE / HABLE_EXPOSURE_BIAS) - 1.).abs() < 1e-9);
+  }
+
+  #[test]
+  fn default_transform() {
+    let transform = DisplayTransform::default();
+    assert_eq!(transform.exposure(), 0.);
+    assert_eq!(transform.curve(), ToneCurve::Clamp);
+    assert_eq!(transform.transfer(), TransferFunction::Srgb);
+    assert_eq!(
+      transform.apply(&Color::new(0.18, 1., 2.)),
+      Color::new(0.18, 1., 2.).encode_srgb()
+    );
+  }
+
+  #[test]
+  fn linear_transform_is_identity() {
+    let color = Color::new(0.25, 3., 0.);
+    assert_eq!(DisplayTransform::linear().apply(&color), color);
+  }
+
+  #[test]
+  fn exposure_in_stops() {
+    let transform = DisplayTransform::linear().with_exposure(2.);
+    assert_eq!(
+      transform.apply(&Color::new(0.1, 0.2, 0.25)),
+      Color::new(0.4, 0.8, 1.)
+    );
+    let transform = transform.with_exposure(-1.);
+    assert_eq!(
+      transform.apply(&Color::new(0.1, 0.2, 0.25)),
+      Color::new(0.05, 0.1, 0.125)
+    );
+  }
+
+  #[test]
+  fn transform_order() {
+    let transform = DisplayTransform::new(1., ToneCurve::Reinhard, TransferFunction::Srgb);
+    let expected = Color::new(0.5, 0.5, 0.5).encode_srgb();
+    assert_eq!(transform.apply(&Color::new(0.5, 0.5, 0.5)), expected);
+  }
+
+  #[test]
+  fn apply_to_canvas_leaves_canvas_untouched() {
+    let mut canvas = Canvas::new(2, 1);
+    canvas.set_pixel(1, 0, &Color::new(3., 1., 0.));
+    let transform = DisplayTransform::linear().with_curve(ToneCurve::Reinhard);
+    let display = transform.apply_to_canvas(&canvas);
+    assert_eq!(display.pixel(1, 0), &Color::new(0.75, 0.5, 0.));
+    assert_eq!(canvas.pixel(1, 0), &Color::new(3., 1., 0.));
+  }
+}
@@ -1,13 +1,78 @@
 use crate::geometry::Vector;
+use crate::utils::Float;
 use crate::Color;
+use std::iter::Sum;
+use std::ops::Add;
+
+/// The terms of the Phong reflection model, kept apart so render passes can separate them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PhongTerms {
+  pub ambient: Color,
+  pub diffuse: Color,
+  pub specular: Color,
+}
+impl PhongTerms {
+  pub fn new(ambient: Color, diffuse: Color, specular: Color) -> Self {
+    return Self {
+      ambient,
+      diffuse,
+      specular,
+    };
+  }
+
+  /// Returns the light arriving straight from light sources.
+  pub fn direct(&self) -> Color {
+    return self.diffuse + self.specular;
+  }
+  /// Returns the light arriving after bouncing off other surfaces, which the ambient term
+  /// approximates.
+  pub fn indirect(&self) -> Color {
+    return self.ambient;
+  }
+  pub fn total(&self) -> Color {
+    return self.ambient + self.diffuse + self.specular;
+  }
+}
+impl Add for PhongTerms {
+  type Output = Self;
+  fn add(self, other: Self) -> Self {
+    return Self::new(
+      self.ambient + other.ambient,
+      self.diffuse + other.diffuse,
+      self.specular + other.specular,
+    );
+  }
+}
+impl Sum for PhongTerms {
+  fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+    return iter.fold(Self::default(), |a, b| a + b);
+  }
+}
 
 pub fn phong(
   base_color: Color,
-  (ambience, diffuse, specular, shininess): (f64, f64, f64, f64),
-  (light_vector, normal, eye_vector): (Vector, Vector, Vector),
+  shading_properties: (Float, Float, Float, Float),
+  vectors: (Vector, Vector, Vector),
   light_color: Color,
   in_shadow: bool,
 ) -> Color {
+  return phong_terms(
+    base_color,
+    shading_properties,
+    vectors,
+    light_color,
+    in_shadow,
+  )
+  .total();
+}
+
+pub fn phong_terms(
+  base_color: Color,
+  (ambience, diffuse, specular, shininess): (Float, Float, Float, Float),
+  (light_vector, normal, eye_vector): (Vector, Vector, Vector),
+  light_color: Color,
+  in_shadow: bool,
+) -> PhongTerms {
   let effective_color = base_color * light_color;
 
   let ambient_color = effective_color * ambience;
@@ -25,7 +90,7 @@ pub fn phong(
     };
   };
 
-  return ambient_color + diffuse_color + specular_color;
+  return PhongTerms::new(ambient_color, diffuse_color, specular_color);
 }
 
 #[cfg(test)]
@@ -112,4 +177,34 @@ mod tests {
     let expected = Color::new(0.1, 0.1, 0.1);
     assert_eq!(actual, expected);
   }
+
+  #[test]
+  fn phong_terms_split() {
+    let terms = phong_terms(
+      Color::white(),
+      Material::default().shading_properties(),
+      (
+        Vector::new(0., 0., -1.),
+        Vector::new(0., 0., -1.),
+        Vector::new(0., 0., -1.),
+      ),
+      Color::white(),
+      false,
+    );
+    assert_eq!(terms.ambient, Color::new(0.1, 0.1, 0.1));
+    assert_eq!(terms.diffuse, Color::new(0.9, 0.9, 0.9));
+    assert_eq!(terms.specular, Color::new(0.9, 0.9, 0.9));
+    assert_eq!(terms.indirect(), Color::new(0.1, 0.1, 0.1));
+    assert_eq!(terms.direct(), Color::new(1.8, 1.8, 1.8));
+    assert_eq!(terms.total(), Color::new(1.9, 1.9, 1.9));
+  }
+
+  #[test]
+  fn phong_terms_sum() {
+    let a = PhongTerms::new(Color::red(), Color::green(), Color::blue());
+    let b = PhongTerms::new(Color::blue(), Color::red(), Color::green());
+    let sum: PhongTerms = vec![a, b].into_iter().sum();
+    assert_eq!(sum.total(), Color::new(2., 2., 2.));
+    assert_eq!(sum.ambient, Color::new(1., 0., 1.));
+  }
 }
@@ -14,9 +14,15 @@ impl PointLight {
   pub fn position(&self) -> &Point {
     return &self.position;
   }
+  pub fn set_position(&mut self, position: Point) {
+    self.position = position;
+  }
   pub fn color(&self) -> &Color {
     return &self.color;
   }
+  pub fn set_color(&mut self, color: Color) {
+    self.color = color;
+  }
 }
 impl Default for PointLight {
   fn default() -> Self {
@@ -0,0 +1,210 @@
+use crate::render::Canvas;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Progress of a render after a row of pixels is finished.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RowEvent {
+  /// The row that was just finished.
+  pub y: usize,
+  /// Number of rows finished so far, including this one.
+  pub finished_rows: usize,
+  /// Number of rows in the image.
+  pub total_rows: usize,
+}
+
+/// Receives events while a [`Camera`](crate::render::Camera) renders, and can stop the render
+/// early.
+///
+/// Renders may run on several threads, so events can arrive from any of them, in any order of
+/// rows.
+pub trait RenderObserver: Sync {
+  /// Called after every finished row.
+  fn row_finished(&self, _event: &RowEvent) {}
+
+  /// Polled before every row. Once it returns `true`, no new rows are started and the render
+  /// returns what was finished so far.
+  fn should_stop(&self) -> bool {
+    return false;
+  }
+}
+
+/// Observes nothing and never stops the render.
+impl RenderObserver for () {}
+
+impl<T> RenderObserver for &T
+where
+  T: RenderObserver + ?Sized,
+{
+  fn row_finished(&self, event: &RowEvent) {
+    (**self).row_finished(event);
+  }
+  fn should_stop(&self) -> bool {
+    return (**self).should_stop();
+  }
+}
+
+/// Combines two observers: both receive every event, and either can stop the render.
+impl<A, B> RenderObserver for (A, B)
+where
+  A: RenderObserver,
+  B: RenderObserver,
+{
+  fn row_finished(&self, event: &RowEvent) {
+    self.0.row_finished(event);
+    self.1.row_finished(event);
+  }
+  fn should_stop(&self) -> bool {
+    return self.0.should_stop() || self.1.should_stop();
+  }
+}
+
+/// Flag to stop a render from another thread. Clones share the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+  cancelled: Arc<AtomicBool>,
+}
+impl Cancellation {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+  pub fn is_cancelled(&self) -> bool {
+    return self.cancelled.load(Ordering::Relaxed);
+  }
+}
+impl RenderObserver for Cancellation {
+  fn should_stop(&self) -> bool {
+    return self.is_cancelled();
+  }
+}
+
+/// Stops a render once a wall-clock duration has passed since the budget was created.
+///
+/// Rows already started are finished, so a render can overrun the budget by the time it takes to
+/// render a row.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeBudget {
+  deadline: Instant,
+}
+impl TimeBudget {
+  pub fn new(duration: Duration) -> Self {
+    return Self {
+      deadline: Instant::now() + duration,
+    };
+  }
+
+  pub fn deadline(&self) -> Instant {
+    return self.deadline;
+  }
+  pub fn is_exhausted(&self) -> bool {
+    return Instant::now() >= self.deadline;
+  }
+}
+impl RenderObserver for TimeBudget {
+  fn should_stop(&self) -> bool {
+    return self.is_exhausted();
+  }
+}
+
+/// Result of a render that may have been stopped early: the canvas, where unfinished pixels are
+/// black, and a mask of the finished pixels.
+#[derive(Clone, Debug)]
+pub struct PartialRender {
+  canvas: Canvas,
+  finished: Vec<bool>,
+}
+impl PartialRender {
+  /// Panics if `finished` does not have one value per pixel of `canvas`, in rows from the top.
+  pub fn new(canvas: Canvas, finished: Vec<bool>) -> Self {
+    assert_eq!(
+      finished.len(),
+      canvas.width() * canvas.height(),
+      "The mask must have one value per pixel"
+    );
+    return Self { canvas, finished };
+  }
+
+  pub fn canvas(&self) -> &Canvas {
+    return &self.canvas;
+  }
+  pub fn into_canvas(self) -> Canvas {
+    return self.canvas;
+  }
+
+  /// Returns for every pixel, in rows from the top, whether it was finished.
+  pub fn mask(&self) -> &[bool] {
+    return &self.finished;
+  }
+  pub fn is_finished(&self, x: usize, y: usize) -> bool {
+    return self.finished[y * self.canvas.width() + x];
+  }
+  pub fn finished_pixels(&self) -> usize {
+    return self.finished.iter().filter(|finished| **finished).count();
+  }
+  /// Returns whether the render was not stopped early.
+  pub fn is_complete(&self) -> bool {
+    return self.finished.iter().all(|finished| *finished);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  #[derive(Default)]
+  struct Recorder {
+    rows: Mutex<Vec<usize>>,
+  }
+  impl RenderObserver for Recorder {
+    fn row_finished(&self, event: &RowEvent) {
+      self.rows.lock().unwrap().push(event.y);
+    }
+  }
+
+  #[test]
+  fn cancellation_is_shared_between_clones() {
+    let cancellation = Cancellation::new();
+    let clone = cancellation.clone();
+    assert!(!cancellation.should_stop());
+    clone.cancel();
+    assert!(cancellation.should_stop());
+  }
+
+  #[test]
+  fn time_budget_stops_after_deadline() {
+    assert!(!TimeBudget::new(Duration::from_secs(60)).should_stop());
+    assert!(TimeBudget::new(Duration::from_secs(0)).should_stop());
+  }
+
+  #[test]
+  fn pair_forwards_events_and_stops_with_either() {
+    let recorder = Recorder::default();
+    let cancellation = Cancellation::new();
+    let pair = (&recorder, cancellation.clone());
+    let event = RowEvent {
+      y: 3,
+      finished_rows: 1,
+      total_rows: 4,
+    };
+    pair.row_finished(&event);
+    assert_eq!(*recorder.rows.lock().unwrap(), vec![3]);
+    assert!(!pair.should_stop());
+    cancellation.cancel();
+    assert!(pair.should_stop());
+  }
+
+  #[test]
+  fn partial_render_mask() {
+    let render = PartialRender::new(Canvas::new(2, 2), vec![true, true, false, false]);
+    assert!(render.is_finished(1, 0));
+    assert!(!render.is_finished(0, 1));
+    assert_eq!(render.finished_pixels(), 2);
+    assert!(!render.is_complete());
+  }
+}
@@ -0,0 +1,143 @@
+use crate::render::Canvas;
+
+/// An extra output of a render besides the shaded image, taken from the primary hits of each
+/// pixel and averaged over its samples like the shaded image. Samples that miss every object count
+/// as black, so pixels that show no object are black in every pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderPass {
+  /// Distance of the hit along the viewing direction of the camera, in all three channels.
+  Depth,
+  /// World-space surface normal, with its x, y and z components in the red, green and blue
+  /// channels.
+  Normal,
+  /// Base color of the surface, before lighting.
+  Albedo,
+  /// One plus the index of the hit object in the world, in all three channels. It is taken from
+  /// the first sample of each pixel, through its center, since averaged ids would name no
+  /// object.
+  ObjectId,
+  /// Diffuse and specular light arriving straight from light sources.
+  Direct,
+  /// Light arriving after bouncing off other surfaces, approximated by the ambient term.
+  Indirect,
+}
+impl RenderPass {
+  pub fn all() -> [Self; 6] {
+    return [
+      RenderPass::Depth,
+      RenderPass::Normal,
+      RenderPass::Albedo,
+      RenderPass::ObjectId,
+      RenderPass::Direct,
+      RenderPass::Indirect,
+    ];
+  }
+
+  /// Returns the pass with the given [`name`](RenderPass::name), if there is one.
+  pub fn from_name(name: &str) -> Option<Self> {
+    return RenderPass::all()
+      .iter()
+      .copied()
+      .find(|pass| pass.name() == name);
+  }
+
+  /// Returns a short lowercase name for the pass, suitable for file names.
+  pub fn name(&self) -> &'static str {
+    return match self {
+      RenderPass::Depth => "depth",
+      RenderPass::Normal => "normal",
+      RenderPass::Albedo => "albedo",
+      RenderPass::ObjectId => "object_id",
+      RenderPass::Direct => "direct",
+      RenderPass::Indirect => "indirect",
+    };
+  }
+}
+
+/// The shaded image of a render together with the requested passes.
+#[derive(Clone, Debug)]
+pub struct RenderLayers {
+  beauty: Canvas,
+  passes: Vec<(RenderPass, Canvas)>,
+}
+impl RenderLayers {
+  /// Returns black layers of the given size for the shaded image and every pass in `passes`.
+  pub fn new(width: usize, height: usize, passes: &[RenderPass]) -> Self {
+    let mut layers = Self {
+      beauty: Canvas::new(width, height),
+      passes: Vec::with_capacity(passes.len()),
+    };
+    for pass in passes {
+      if layers.pass(*pass).is_none() {
+        layers.passes.push((*pass, Canvas::new(width, height)));
+      }
+    }
+    return layers;
+  }
+
+  pub fn beauty(&self) -> &Canvas {
+    return &self.beauty;
+  }
+  pub fn beauty_mut(&mut self) -> &mut Canvas {
+    return &mut self.beauty;
+  }
+
+  /// Returns the canvas of a pass, or `None` if it was not requested.
+  pub fn pass(&self, pass: RenderPass) -> Option<&Canvas> {
+    return self
+      .passes
+      .iter()
+      .find(|(kind, _)| *kind == pass)
+      .map(|(_, canvas)| canvas);
+  }
+  pub fn pass_mut(&mut self, pass: RenderPass) -> Option<&mut Canvas> {
+    return self
+      .passes
+      .iter_mut()
+      .find(|(kind, _)| *kind == pass)
+      .map(|(_, canvas)| canvas);
+  }
+
+  /// Returns the requested passes in the order they were requested.
+  pub fn passes(&self) -> &[(RenderPass, Canvas)] {
+    return &self.passes;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn init_new() {
+    let layers = RenderLayers::new(3, 2, &[RenderPass::Normal, RenderPass::Depth]);
+    assert_eq!(layers.beauty().width(), 3);
+    assert_eq!(layers.beauty().height(), 2);
+    assert_eq!(layers.passes().len(), 2);
+    assert_eq!(layers.passes()[0].0, RenderPass::Normal);
+    assert!(layers.pass(RenderPass::Depth).is_some());
+    assert!(layers.pass(RenderPass::Albedo).is_none());
+  }
+
+  #[test]
+  fn init_new_ignores_duplicates() {
+    let layers = RenderLayers::new(1, 1, &[RenderPass::Albedo, RenderPass::Albedo]);
+    assert_eq!(layers.passes().len(), 1);
+  }
+
+  #[test]
+  fn from_name() {
+    for pass in RenderPass::all().iter() {
+      assert_eq!(RenderPass::from_name(pass.name()), Some(*pass));
+    }
+    assert_eq!(RenderPass::from_name("beauty"), None);
+  }
+
+  #[test]
+  fn pass_names_are_unique() {
+    let names: Vec<&str> = RenderPass::all().iter().map(|pass| pass.name()).collect();
+    for (i, name) in names.iter().enumerate() {
+      assert!(!names[i + 1..].contains(name));
+    }
+  }
+}
@@ -0,0 +1,157 @@
+use crate::geometry::Point;
+use crate::render::{LightHandle, ObjectHandle};
+use crate::utils::Float;
+use crate::Color;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+/// Object or light of a [`World`](crate::render::World) with a [`Problem`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Subject {
+  Object {
+    handle: ObjectHandle,
+    name: Option<String>,
+  },
+  Light(LightHandle),
+}
+impl Display for Subject {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self {
+      Subject::Object {
+        name: Some(name), ..
+      } => write!(f, "object `{}`", name),
+      Subject::Object { handle, .. } => write!(f, "object {}", handle.id()),
+      Subject::Light(handle) => write!(f, "light {}", handle.id()),
+    };
+  }
+}
+
+/// Value that would make a render panic or produce NaN pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+  /// The object's transformation cannot be inverted, for example because it scales an axis by
+  /// zero.
+  SingularTransform,
+  /// The transformation of a texture of the object's material cannot be inverted, so the
+  /// texture is black.
+  SingularTextureTransform,
+  /// The light's position has NaN or infinite coordinates.
+  InvalidPosition(Point),
+  /// The light's color, or the color of the material at the object's origin, is NaN, infinite
+  /// or negative.
+  InvalidColor(Color),
+  /// A shading property of the material is NaN, infinite or negative.
+  InvalidMaterial {
+    property: &'static str,
+    value: Float,
+  },
+}
+impl Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self {
+      Problem::SingularTransform => write!(f, "transformation is not invertible"),
+      Problem::SingularTextureTransform => {
+        write!(f, "texture transformation is not invertible")
+      }
+      Problem::InvalidPosition(position) => write!(
+        f,
+        "invalid position ({}, {}, {})",
+        position.x, position.y, position.z
+      ),
+      Problem::InvalidColor(color) => {
+        write!(f, "invalid color ({}, {}, {})", color.r, color.g, color.b)
+      }
+      Problem::InvalidMaterial { property, value } => {
+        write!(f, "invalid material {} {}", property, value)
+      }
+    };
+  }
+}
+
+/// Problem found by [`World::validate`](crate::render::World::validate), and what it was found
+/// in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+  pub subject: Subject,
+  pub problem: Problem,
+}
+impl Display for Issue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return write!(f, "{}: {}", self.subject, self.problem);
+  }
+}
+
+/// Error returned by [`World::validate`](crate::render::World::validate), with every problem
+/// found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+  issues: Vec<Issue>,
+}
+impl ValidationError {
+  /// Panics if `issues` is empty.
+  pub fn new(issues: Vec<Issue>) -> Self {
+    assert!(!issues.is_empty(), "A validation error needs an issue");
+    return Self { issues };
+  }
+
+  pub fn issues(&self) -> &[Issue] {
+    return &self.issues;
+  }
+}
+impl Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for (index, issue) in self.issues.iter().enumerate() {
+      if index > 0 {
+        write!(f, "; ")?;
+      }
+      write!(f, "{}", issue)?;
+    }
+    return Ok(());
+  }
+}
+impl Error for ValidationError {}
+
+/// Returns whether a color can be rendered.
+pub(crate) fn is_valid_color(color: &Color) -> bool {
+  return [color.r, color.g, color.b]
+    .iter()
+    .all(|channel| channel.is_finite() && *channel >= 0.);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::render::{PointLight, World};
+  use crate::shapes::Sphere;
+
+  #[test]
+  fn error_display() {
+    let mut world = World::default();
+    let object = world.add_object(Box::new(Sphere::default()));
+    let light = world.add_light(PointLight::default());
+    let error = ValidationError::new(vec![
+      Issue {
+        subject: Subject::Object {
+          handle: object,
+          name: Some("ball".to_string()),
+        },
+        problem: Problem::SingularTransform,
+      },
+      Issue {
+        subject: Subject::Light(light),
+        problem: Problem::InvalidColor(Color::new(Float::NAN, 0., 1.)),
+      },
+    ]);
+    assert_eq!(
+      error.to_string(),
+      "object `ball`: transformation is not invertible; light 1: invalid color (NaN, 0, 1)"
+    );
+  }
+
+  #[test]
+  fn valid_colors() {
+    assert!(is_valid_color(&Color::new(0., 2., 0.5)));
+    assert!(!is_valid_color(&Color::new(0., -0.1, 0.)));
+    assert!(!is_valid_color(&Color::new(Float::INFINITY, 0., 0.)));
+  }
+}
@@ -1,33 +1,358 @@
-use crate::geometry::{Point, Ray};
-use crate::render::{phong, PointLight};
+use crate::geometry::{Aabb, Boxes4, Material, Matrix, Point, Ray, SingularMatrixError, Vector};
+use crate::render::{
+  is_valid_color, phong_terms, Issue, PhongTerms, PointLight, Problem, Subject, ValidationError,
+};
 use crate::shapes::{find_hit, Intersection, Shape};
+use crate::utils::{Float, EPSILON};
 use crate::Color;
 use std::cmp::Ordering::Equal;
+use std::sync::OnceLock;
 
+/// Identifies an object in a [`World`]. A handle keeps referring to the same object for the
+/// lifetime of the world, and is never reused for another object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectHandle(u64);
+impl ObjectHandle {
+  pub fn id(&self) -> u64 {
+    return self.0;
+  }
+}
+
+/// The first surface hit by a ray, as reported by [`World::first_hit`].
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit<'a> {
+  pub object: ObjectHandle,
+  pub material: &'a Material,
+  /// Distance along the ray, in multiples of its direction.
+  pub time: Float,
+  pub point: Point,
+  /// World-space surface normal at the point.
+  pub normal: Vector,
+  /// Texture coordinates of the surface at the point.
+  pub uv: (Float, Float),
+}
+
+/// Identifies a light in a [`World`], like [`ObjectHandle`] does for objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LightHandle(u64);
+impl LightHandle {
+  pub fn id(&self) -> u64 {
+    return self.0;
+  }
+}
+
+/// Objects and lights to be rendered.
+///
+/// Objects and lights can be added, changed and removed at any time through their handles. Data
+/// derived from them, like the bounds used to skip objects a ray misses, is rebuilt after every
+/// change, so every change is seen by the next query or render.
 #[derive(Default)]
 pub struct World {
   objects: Vec<Box<dyn Shape>>,
+  /// Handles of `objects`, in the same order. Handles only increase, so this stays sorted.
+  handles: Vec<ObjectHandle>,
+  names: Vec<Option<String>>,
   lights: Vec<PointLight>,
+  light_handles: Vec<LightHandle>,
+  next_handle: u64,
+  revision: u64,
+  /// World bounds of `objects`, four to a group, built by the first query after a change.
+  object_bounds: OnceLock<Vec<Boxes4>>,
 }
 impl World {
   pub fn new(objects: Vec<Box<dyn Shape>>, lights: Vec<PointLight>) -> Self {
-    return Self { objects, lights };
+    let mut world = Self::default();
+    for object in objects {
+      world.add_object(object);
+    }
+    for light in lights {
+      world.add_light(light);
+    }
+    world.revision = 0;
+    return world;
   }
 
   pub fn objects(&self) -> &[Box<dyn Shape>] {
     return &self.objects;
   }
 
+  /// Returns the handles of the objects, in the same order as [`objects`](World::objects).
+  pub fn handles(&self) -> &[ObjectHandle] {
+    return &self.handles;
+  }
+
+  /// Returns the object with the given handle, or `None` if it is not in the world.
+  pub fn object(&self, handle: ObjectHandle) -> Option<&dyn Shape> {
+    return self
+      .index_of(handle)
+      .map(|index| self.objects[index].as_ref());
+  }
+
+  /// Returns the object with the given handle to change it in place.
+  pub fn object_mut(&mut self, handle: ObjectHandle) -> Option<&mut dyn Shape> {
+    let index = self.index_of(handle)?;
+    self.changed();
+    return Some(self.objects[index].as_mut());
+  }
+
+  /// Returns the index in [`objects`](World::objects) of the object with the given handle.
+  pub fn index_of(&self, handle: ObjectHandle) -> Option<usize> {
+    return self.handles.binary_search(&handle).ok();
+  }
+
+  /// Returns the handle of an object added with a name. If several objects have the name, the
+  /// one added first is returned.
+  pub fn find(&self, name: &str) -> Option<ObjectHandle> {
+    let index = self
+      .names
+      .iter()
+      .position(|object_name| object_name.as_deref() == Some(name))?;
+    return Some(self.handles[index]);
+  }
+
+  /// Returns the name an object was added with.
+  pub fn name(&self, handle: ObjectHandle) -> Option<&str> {
+    return self.names[self.index_of(handle)?].as_deref();
+  }
+
+  pub fn add_object(&mut self, object: Box<dyn Shape>) -> ObjectHandle {
+    let handle = ObjectHandle(self.next_handle);
+    self.next_handle += 1;
+    self.objects.push(object);
+    self.handles.push(handle);
+    self.names.push(None);
+    self.changed();
+    return handle;
+  }
+
+  /// Adds an object that can be found again with [`find`](World::find).
+  pub fn add_named_object(&mut self, name: &str, object: Box<dyn Shape>) -> ObjectHandle {
+    let handle = self.add_object(object);
+    if let Some(last) = self.names.last_mut() {
+      *last = Some(name.to_string());
+    }
+    return handle;
+  }
+
+  /// Removes an object and returns it, or `None` if it is not in the world. The handles of the
+  /// other objects stay valid, but their indices after it shift down.
+  pub fn remove_object(&mut self, handle: ObjectHandle) -> Option<Box<dyn Shape>> {
+    let index = self.index_of(handle)?;
+    self.handles.remove(index);
+    self.names.remove(index);
+    self.changed();
+    return Some(self.objects.remove(index));
+  }
+
+  /// Replaces the object-to-world transform of an object. Returns `false` if the object is not
+  /// in the world, and an error, leaving the object as it is, if the transform is singular.
+  pub fn set_transform(
+    &mut self,
+    handle: ObjectHandle,
+    object_to_world: Matrix,
+  ) -> Result<bool, SingularMatrixError> {
+    object_to_world.try_inverse()?;
+    return Ok(match self.object_mut(handle) {
+      None => false,
+      Some(object) => {
+        object.set_object_to_world(object_to_world);
+        true
+      }
+    });
+  }
+
+  /// Replaces the material of an object. Returns `false` if the object is not in the world.
+  pub fn set_material(&mut self, handle: ObjectHandle, material: Material) -> bool {
+    return match self.object_mut(handle) {
+      None => false,
+      Some(object) => {
+        object.set_material(material);
+        true
+      }
+    };
+  }
+
   pub fn lights(&self) -> &[PointLight] {
     return &self.lights;
   }
 
-  pub fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
-    let mut intersections: Vec<Intersection> = self
-      .objects()
-      .iter()
-      .flat_map(|object| object.intersect(&ray))
-      .collect();
+  /// Returns the handles of the lights, in the same order as [`lights`](World::lights).
+  pub fn light_handles(&self) -> &[LightHandle] {
+    return &self.light_handles;
+  }
+
+  pub fn light(&self, handle: LightHandle) -> Option<&PointLight> {
+    let index = self.light_handles.binary_search(&handle).ok()?;
+    return Some(&self.lights[index]);
+  }
+
+  /// Returns the light with the given handle to change it in place.
+  pub fn light_mut(&mut self, handle: LightHandle) -> Option<&mut PointLight> {
+    let index = self.light_handles.binary_search(&handle).ok()?;
+    self.changed();
+    return Some(&mut self.lights[index]);
+  }
+
+  pub fn add_light(&mut self, light: PointLight) -> LightHandle {
+    let handle = LightHandle(self.next_handle);
+    self.next_handle += 1;
+    self.lights.push(light);
+    self.light_handles.push(handle);
+    self.changed();
+    return handle;
+  }
+
+  pub fn remove_light(&mut self, handle: LightHandle) -> Option<PointLight> {
+    let index = self.light_handles.binary_search(&handle).ok()?;
+    self.light_handles.remove(index);
+    self.changed();
+    return Some(self.lights.remove(index));
+  }
+
+  /// Records a change of the objects or lights.
+  fn changed(&mut self) {
+    self.revision += 1;
+    self.object_bounds = OnceLock::new();
+  }
+
+  /// Returns a number that changes whenever the world may have changed, so data computed from
+  /// it, such as accumulated samples, can be discarded. Getting mutable access to an object or
+  /// a light counts as a change.
+  pub fn revision(&self) -> u64 {
+    return self.revision;
+  }
+
+  /// Returns a box in world space that contains every object. It is infinite if the world has a
+  /// plane, and empty if it has no objects.
+  pub fn bounds(&self) -> Aabb {
+    return self.objects.iter().fold(Aabb::empty(), |bounds, object| {
+      bounds.union(&object.world_bounds())
+    });
+  }
+
+  /// Checks every object and light for values that would make a render panic or produce NaN
+  /// pixels, such as transformations that cannot be inverted, and returns all problems found.
+  ///
+  /// The colors of textures are only checked at the origin of their object.
+  pub fn validate(&self) -> Result<(), ValidationError> {
+    let mut issues = Vec::new();
+    for (object, handle) in self.objects.iter().zip(&self.handles) {
+      let subject = Subject::Object {
+        handle: *handle,
+        name: self.name(*handle).map(str::to_string),
+      };
+      let mut report = |problem: Problem| {
+        issues.push(Issue {
+          subject: subject.clone(),
+          problem,
+        });
+      };
+
+      if object.world_to_object().is_none() {
+        report(Problem::SingularTransform);
+      }
+      let material = object.material();
+      let properties = [
+        ("ambience", material.ambience()),
+        ("diffuse", material.diffuse()),
+        ("specular", material.specular()),
+        ("shininess", material.shininess()),
+      ];
+      for (property, value) in properties {
+        if !value.is_finite() || value < 0. {
+          report(Problem::InvalidMaterial { property, value });
+        }
+      }
+      if !material.has_invertible_textures() {
+        report(Problem::SingularTextureTransform);
+      } else {
+        let color = material.color_at(&Point::origin());
+        if !is_valid_color(&color) {
+          report(Problem::InvalidColor(color));
+        }
+      }
+    }
+
+    for (light, handle) in self.lights.iter().zip(&self.light_handles) {
+      let position = light.position();
+      if ![position.x, position.y, position.z]
+        .iter()
+        .all(|coordinate| coordinate.is_finite())
+      {
+        issues.push(Issue {
+          subject: Subject::Light(*handle),
+          problem: Problem::InvalidPosition(*position),
+        });
+      }
+      if !is_valid_color(light.color()) {
+        issues.push(Issue {
+          subject: Subject::Light(*handle),
+          problem: Problem::InvalidColor(*light.color()),
+        });
+      }
+    }
+
+    return if issues.is_empty() {
+      Ok(())
+    } else {
+      Err(ValidationError::new(issues))
+    };
+  }
+
+  /// Calls `f` with the index of every object whose bounds the ray enters within its interval,
+  /// until `f` returns `true`. Returns whether it did.
+  fn find_candidate<F>(&self, ray: &Ray, mut f: F) -> bool
+  where
+    F: FnMut(usize) -> bool,
+  {
+    let object_bounds = self.object_bounds.get_or_init(|| {
+      return self
+        .objects
+        .chunks(4)
+        .map(|objects| {
+          let mut boxes = Boxes4::EMPTY;
+          for (index, object) in objects.iter().enumerate() {
+            boxes.set(index, &object.world_bounds());
+          }
+          return boxes;
+        })
+        .collect();
+    });
+    let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+    // Directions parallel to an axis get a huge instead of an infinite inverse, so a ray in the
+    // plane of a face gives a time of zero there instead of NaN.
+    let inverse = |direction: Float| (1. / direction).clamp(-Float::MAX, Float::MAX);
+    let inverse_direction = [
+      inverse(ray.direction.x),
+      inverse(ray.direction.y),
+      inverse(ray.direction.z),
+    ];
+    for (group, boxes) in object_bounds.iter().enumerate() {
+      let entries = boxes.intersect(&origin, &inverse_direction, ray.t_min, ray.t_max);
+      for (index, entry) in entries.iter().enumerate() {
+        let object = 4 * group + index;
+        if *entry < Float::INFINITY && object < self.objects.len() && f(object) {
+          return true;
+        }
+      }
+    }
+    return false;
+  }
+
+  /// Returns the intersections of the ray with every object within the ray's interval, sorted by
+  /// time.
+  pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+    let mut intersections = Vec::new();
+    self.find_candidate(ray, |index| {
+      let handle = self.handles[index];
+      intersections.extend(
+        self.objects[index]
+          .intersect(ray)
+          .into_iter()
+          .map(|intersection| intersection.with_object(handle)),
+      );
+      return false;
+    });
     intersections.sort_unstable_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Equal));
     return intersections;
   }
@@ -36,22 +361,23 @@ impl World {
     let point_to_light = *light.position() - *point;
     let direction = point_to_light.normalize();
     let distance = point_to_light.magnitude();
+    return self.occluded(&Ray::new(*point, direction).with_interval(0., distance));
+  }
 
-    let intersections = &self.intersect(&Ray::new(*point, direction));
-    let hit = find_hit(intersections);
-    return match hit {
-      None => false,
-      Some(intersection) => intersection.time <= distance,
-    };
+  /// Returns whether any object intersects the ray within its interval. Stops at the first
+  /// intersection found, so it is cheaper than [`intersect`](World::intersect) for shadow rays.
+  pub fn occluded(&self, ray: &Ray) -> bool {
+    return self.find_candidate(ray, |index| self.objects[index].intersects(ray));
   }
 
-  fn shade_hit(&self, hit: &Intersection) -> Color {
+  /// Returns the Phong terms at the hit, summed over every light.
+  pub fn lighting(&self, hit: &Intersection) -> PhongTerms {
     return self
       .lights()
       .iter()
       .map(|light| {
         let light_vector = (*light.position() - hit.point).normalize();
-        phong(
+        phong_terms(
           hit.base_color(),
           hit.material.shading_properties(),
           (light_vector, hit.normal, hit.outgoing),
@@ -61,8 +387,45 @@ impl World {
       })
       .sum();
   }
+
+  fn shade_hit(&self, hit: &Intersection) -> Color {
+    return self.lighting(hit).total();
+  }
+
+  /// Returns the index in `objects` of the object that was hit.
+  pub fn object_index(&self, hit: &Intersection) -> Option<usize> {
+    return hit.object.and_then(|handle| self.index_of(handle));
+  }
+
+  /// Returns the first surface in front of the ray's origin, with the object it belongs to.
+  pub fn first_hit(&self, ray: &Ray) -> Option<RayHit<'_>> {
+    let intersections = self.intersect(ray);
+    let hit = find_hit(&intersections)?;
+    let object = hit.object?;
+    let shape = self.object(object)?;
+    return Some(RayHit {
+      object,
+      material: shape.material(),
+      time: hit.time,
+      point: hit.point,
+      normal: hit.normal,
+      uv: shape.uv_at_object_space(&hit.point_object),
+    });
+  }
+
+  /// Returns whether nothing lies on the straight line between two points. Surfaces at the
+  /// points themselves do not block the line, so points picked on surfaces can be tested.
+  pub fn is_visible(&self, from: &Point, to: &Point) -> bool {
+    let from_to = *to - *from;
+    let distance = from_to.magnitude();
+    if distance < EPSILON {
+      return true;
+    }
+    let ray = Ray::new(*from, from_to.normalize()).with_interval(EPSILON, distance - EPSILON);
+    return !self.occluded(&ray);
+  }
   pub fn color_at(&self, ray: &Ray) -> Color {
-    let intersections = self.intersect(&ray);
+    let intersections = self.intersect(ray);
     let hit = find_hit(&intersections);
     return match hit {
       None => Color::black(),
@@ -72,12 +435,13 @@ impl World {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
   use super::*;
   use crate::geometry::{Material, Matrix, Point, Vector};
   use crate::render::PointLight;
   use crate::shapes::{Plane, Sphere};
-  use crate::textures::{stripes, Pattern};
+  use crate::textures::{stripes, Pattern, Texture, UvMapping};
   use crate::Color;
 
   fn test_world() -> World {
@@ -120,10 +484,24 @@ mod tests {
     assert_eq!(world.lights().len(), 0);
   }
 
+  #[test]
+  fn bounds() {
+    let world = test_world();
+    assert_eq!(
+      world.bounds(),
+      Aabb::new(Point::new(-1., -6., -1.), Point::new(1., 1., 1.))
+    );
+    assert!(World::default().bounds().is_empty());
+    let mut world = world;
+    world.add_object(Box::new(Plane::default()));
+    assert_eq!(world.bounds(), Aabb::infinite());
+  }
+
   #[test]
   fn intersect() {
     let world = test_world();
-    let ray = Ray::new(Point::new(0., -2., 0.), Vector::new(0., 1., 0.));
+    let ray = Ray::new(Point::new(0., -2., 0.), Vector::new(0., 1., 0.))
+      .with_interval(Float::NEG_INFINITY, Float::INFINITY);
     let intersections = world.intersect(&ray);
     assert_eq!(intersections.len(), 6);
     assert_eq!(intersections[0].time, -4.);
@@ -134,6 +512,53 @@ mod tests {
     assert_eq!(intersections[5].time, 3.);
   }
 
+  #[test]
+  fn intersect_within_ray_interval() {
+    let world = test_world();
+    let ray = Ray::new(Point::new(0., -2., 0.), Vector::new(0., 1., 0.));
+    let times: Vec<Float> = world.intersect(&ray).iter().map(|hit| hit.time).collect();
+    assert_eq!(times, vec![1., 1.5, 2.5, 3.]);
+    let ray = ray.with_interval(1.2, 2.8);
+    let times: Vec<Float> = world.intersect(&ray).iter().map(|hit| hit.time).collect();
+    assert_eq!(times, vec![1.5, 2.5]);
+  }
+
+  #[test]
+  fn intersect_skips_objects_by_bounds() {
+    let mut world = World::default();
+    let handles: Vec<ObjectHandle> = (0..6)
+      .map(|index| {
+        let sphere = Sphere::new(
+          Material::default(),
+          Matrix::identity().translate(3. * index as Float, 0., 0.),
+        );
+        return world.add_object(Box::new(sphere));
+      })
+      .collect();
+    let ray = Ray::new(Point::new(15., 0., -5.), Vector::new(0., 0., 1.));
+    let hits = world.intersect(&ray);
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].object, Some(handles[5]));
+    assert!(world.occluded(&ray));
+
+    // The bounds follow changes to the objects.
+    world
+      .set_transform(handles[5], Matrix::identity().translate(0., 10., 0.))
+      .unwrap();
+    assert!(world.intersect(&ray).is_empty());
+    assert!(!world.occluded(&ray));
+  }
+
+  #[test]
+  fn occluded() {
+    let world = test_world();
+    let ray = Ray::new(Point::new(0., -2., 0.), Vector::new(0., 1., 0.));
+    assert!(world.occluded(&ray));
+    assert!(world.occluded(&ray.with_interval(0., 1.)));
+    assert!(!world.occluded(&ray.with_interval(0., 0.5)));
+    assert!(!world.occluded(&Ray::new(Point::new(0., -2., 0.), Vector::new(1., 0., 0.))));
+  }
+
   #[test]
   fn is_shadowed_nothing_between_point_and_light() {
     let world = test_world();
@@ -202,6 +627,164 @@ mod tests {
     assert_eq!(color, Color::new(0.08, 0.1, 0.06));
   }
 
+  #[test]
+  fn lighting_in_shadow_is_indirect_only() {
+    let world = test_world();
+    let ray = Ray::new(Point::new(1., -1., 1.), Vector::new(-1., 1., -1.));
+    let intersections = world.intersect(&ray);
+    let hit = find_hit(&intersections).unwrap();
+    let terms = world.lighting(hit);
+    assert_eq!(terms.direct(), Color::black());
+    assert_eq!(terms.indirect(), Color::new(0.08, 0.1, 0.06));
+  }
+
+  #[test]
+  fn object_index() {
+    let world = test_world();
+    let ray = Ray::new(Point::new(0., 0., -2.), Vector::new(0., 0., 1.));
+    let intersections = world.intersect(&ray);
+    assert_eq!(
+      world.object_index(find_hit(&intersections).unwrap()),
+      Some(0)
+    );
+
+    let ray = Ray::new(Point::new(0., 0., 0.75), Vector::new(0., 0., -1.));
+    let intersections = world.intersect(&ray);
+    assert_eq!(
+      world.object_index(find_hit(&intersections).unwrap()),
+      Some(1)
+    );
+
+    let material = Material::default();
+    let foreign = Intersection::new(
+      1.,
+      Point::origin(),
+      Point::origin(),
+      Vector::new(0., 0., 1.),
+      Vector::new(0., 0., 1.),
+      &material,
+    );
+    assert_eq!(world.object_index(&foreign), None);
+  }
+
+  #[test]
+  fn handles_identify_objects() {
+    let world = test_world();
+    assert_eq!(world.handles().len(), 3);
+    let handle = world.handles()[2];
+    assert_eq!(world.index_of(handle), Some(2));
+    assert!(std::ptr::eq(
+      world.object(handle).unwrap().material(),
+      world.objects()[2].material()
+    ));
+    assert!(World::default().object(handle).is_none());
+  }
+
+  #[test]
+  fn first_hit() {
+    let world = test_world();
+    let ray = Ray::new(Point::new(0., 0., -2.), Vector::new(0., 0., 1.));
+    let hit = world.first_hit(&ray).unwrap();
+    assert_eq!(hit.object, world.handles()[0]);
+    assert!(std::ptr::eq(hit.material, world.objects()[0].material()));
+    assert_eq!(hit.time, 1.);
+    assert_eq!(hit.point, Point::new(0., 0., -1.));
+    assert_eq!(hit.normal, Vector::new(0., 0., -1.));
+    assert_eq!(hit.uv, UvMapping::Spherical.map(&Point::new(0., 0., -1.)));
+
+    let ray = Ray::new(Point::new(0., 0., 0.75), Vector::new(0., 0., -1.));
+    assert_eq!(world.first_hit(&ray).unwrap().object, world.handles()[1]);
+    let ray = Ray::new(Point::new(0., 0., -2.), Vector::new(0., 1., 0.));
+    assert!(world.first_hit(&ray).is_none());
+  }
+
+  #[test]
+  fn is_visible() {
+    let world = test_world();
+    let eye = Point::new(0., 0., -5.);
+    assert!(!world.is_visible(&eye, &Point::new(0., 0., 5.)));
+    assert!(world.is_visible(&eye, &Point::new(0., 5., 0.)));
+    assert!(world.is_visible(&eye, &eye));
+    // Points on the surfaces facing each other.
+    assert!(world.is_visible(&eye, &Point::new(0., 0., -1.)));
+    assert!(world.is_visible(&Point::new(0., 0., -1.), &eye));
+    assert!(!world.is_visible(&Point::new(0., 0., -1.), &Point::new(0., 0., 1.)));
+  }
+
+  #[test]
+  fn add_and_remove_objects() {
+    let mut world = test_world();
+    let revision = world.revision();
+    let first = world.handles()[0];
+    let added = world.add_named_object("ball", Box::new(Sphere::default()));
+    assert_eq!(world.objects().len(), 4);
+    assert_eq!(world.index_of(added), Some(3));
+    assert_eq!(world.find("ball"), Some(added));
+    assert_eq!(world.name(added), Some("ball"));
+    assert_eq!(world.find("cube"), None);
+    assert!(world.revision() > revision);
+
+    assert!(world.remove_object(first).is_some());
+    assert!(world.remove_object(first).is_none());
+    assert_eq!(world.objects().len(), 3);
+    assert_eq!(world.index_of(added), Some(2));
+    assert_eq!(world.find("ball"), Some(added));
+
+    // Handles of removed objects are never reused.
+    let another = world.add_object(Box::new(Sphere::default()));
+    assert_ne!(another, first);
+    assert!(world.object(first).is_none());
+  }
+
+  #[test]
+  fn update_objects_in_place() {
+    let mut world = test_world();
+    let ray = Ray::new(Point::new(0., 0., -2.), Vector::new(0., 0., 1.));
+    let handle = world.handles()[0];
+    assert_eq!(
+      world.set_transform(handle, Matrix::identity().translate(0., 0., 1.)),
+      Ok(true)
+    );
+    assert_eq!(
+      world.set_transform(handle, Matrix::identity().scale(0., 1., 1.)),
+      Err(SingularMatrixError)
+    );
+    assert!(world.set_material(handle, Material::default()));
+    let hit = world.first_hit(&ray).unwrap();
+    assert_eq!(hit.object, world.handles()[1]);
+
+    world.remove_object(world.handles()[1]);
+    let hit = world.first_hit(&ray).unwrap();
+    assert_eq!(hit.object, handle);
+    assert_eq!(hit.time, 2.);
+    assert_eq!(hit.material.ambience(), Material::default().ambience());
+
+    world.remove_object(handle);
+    assert_eq!(world.set_transform(handle, Matrix::identity()), Ok(false));
+    assert!(!world.set_material(handle, Material::default()));
+  }
+
+  #[test]
+  fn add_update_and_remove_lights() {
+    let mut world = test_world();
+    let point = Point::new(10., -10., 10.);
+    assert!(world.is_shadowed(&point, &world.lights()[0]));
+
+    let handle = world.light_handles()[0];
+    *world.light_mut(handle).unwrap() = PointLight::new(Point::new(10., -10., 20.), Color::white());
+    assert!(!world.is_shadowed(&point, &world.lights()[0]));
+
+    let added = world.add_light(PointLight::default());
+    assert_eq!(world.lights().len(), 2);
+    assert_eq!(world.light(added), Some(&PointLight::default()));
+    assert_eq!(
+      world.remove_light(handle).map(|light| *light.position()),
+      Some(Point::new(10., -10., 20.))
+    );
+    assert_eq!(world.light_handles(), &[added]);
+    assert!(world.light(handle).is_none());
+  }
+
   fn test_plane_world(plane_transformation: Matrix) -> World {
     return World::new(
       vec![Box::new(Plane::new(
@@ -235,4 +818,106 @@ mod tests {
     let color = world.color_at(&ray);
     assert_eq!(color, Color::white());
   }
+
+  #[test]
+  fn validate_reports_every_problem() {
+    let mut world = test_world();
+    assert_eq!(world.validate(), Ok(()));
+
+    let flat = world.add_named_object(
+      "flat",
+      Box::new(Sphere::new(
+        Material::new(Box::new(Color::white()), 0.1, -0.5, Float::NAN, 200.),
+        Matrix::identity().scale(1., 0., 1.),
+      )),
+    );
+    let light = world.add_light(PointLight::new(
+      Point::new(Float::INFINITY, 0., 0.),
+      Color::new(1., Float::NAN, 1.),
+    ));
+    let error = world.validate().unwrap_err();
+    let flat_subject = Subject::Object {
+      handle: flat,
+      name: Some("flat".to_string()),
+    };
+    let problems: Vec<(Subject, Problem)> = error
+      .issues()
+      .iter()
+      .map(|issue| (issue.subject.clone(), issue.problem.clone()))
+      .collect();
+    assert_eq!(problems.len(), 5);
+    assert_eq!(
+      problems[0],
+      (flat_subject.clone(), Problem::SingularTransform)
+    );
+    assert_eq!(
+      problems[1],
+      (
+        flat_subject,
+        Problem::InvalidMaterial {
+          property: "diffuse",
+          value: -0.5
+        }
+      )
+    );
+    assert!(matches!(
+      problems[2].1,
+      Problem::InvalidMaterial {
+        property: "specular",
+        ..
+      }
+    ));
+    assert!(matches!(
+      problems[3],
+      (Subject::Light(handle), Problem::InvalidPosition(_)) if handle == light
+    ));
+    assert!(matches!(
+      problems[4],
+      (Subject::Light(handle), Problem::InvalidColor(_)) if handle == light
+    ));
+  }
+
+  #[test]
+  fn validate_checks_texture_transforms() {
+    let mut world = World::default();
+    let pattern = Pattern::with_transformation(
+      stripes,
+      vec![Box::new(Color::white()), Box::new(Color::black())],
+      Matrix::identity().scale(0., 1., 1.),
+    );
+    let handle = world.add_object(Box::new(Sphere::new(
+      Material::new(Box::new(pattern), 0.1, 0.9, 0.9, 200.),
+      Matrix::identity(),
+    )));
+    let error = world.validate().unwrap_err();
+    assert_eq!(error.issues().len(), 1);
+    assert_eq!(
+      error.issues()[0].subject,
+      Subject::Object { handle, name: None }
+    );
+    assert_eq!(error.issues()[0].problem, Problem::SingularTextureTransform);
+  }
+
+  #[test]
+  fn validate_looks_up_colors_in_object_space() {
+    /// Texture that is only valid at the origin of its object.
+    #[derive(Debug)]
+    struct ValidAtOrigin;
+    impl Texture for ValidAtOrigin {
+      fn color_at(&self, point: &Point) -> Color {
+        return if *point == Point::origin() {
+          Color::white()
+        } else {
+          Color::new(Float::NAN, 0., 0.)
+        };
+      }
+    }
+
+    let mut world = World::default();
+    world.add_object(Box::new(Sphere::new(
+      Material::new(Box::new(ValidAtOrigin), 0.1, 0.9, 0.9, 200.),
+      Matrix::identity().translate(5., 0., 0.),
+    )));
+    assert_eq!(world.validate(), Ok(()));
+  }
 }
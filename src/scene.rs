@@ -0,0 +1,187 @@
+//! Text format describing a camera and a world, so scenes can be written without Rust.
+//!
+//! A scene is a list of statements, separated by newlines or semicolons. A statement is a name
+//! followed by arguments (numbers, words or double-quoted strings) and an optional block of
+//! nested statements in braces. `#` starts a comment. Angles are in degrees.
+//!
+//! ```text
+//! camera {
+//!   size 320 240
+//!   fov 60
+//!   from 0 1.5 -5; to 0 1 0; up 0 1 0
+//! }
+//! light { position -10 10 -10; color 1 1 1 }
+//!
+//! transform tilted { rotate_z 20; translate 0 1 0 }
+//! material marble {
+//!   marble 7 { color 0.9 0.9 0.85; color 0.2 0.2 0.25; transform { scale 0.5 } }
+//!   specular 0.3
+//! }
+//!
+//! sphere ball { material marble; transform tilted }
+//! plane {
+//!   material { checkers { color 1 1 1; color 0 0 0 }; ambient 0.2 }
+//! }
+//! ```
+//!
+//! Top-level statements:
+//! - `camera { ... }` with `size WIDTH HEIGHT`, `fov DEGREES`, either `from`/`to`/`up`
+//!   points or a `transform`, and `aovs NAME...` naming the [render
+//!   passes](crate::render::RenderPass) to output besides the image: `depth`, `normal`,
+//!   `albedo`, `object_id`, `direct` and `indirect`.
+//! - `light { ... }` with `position X Y Z` and `color R G B`.
+//! - `sphere [NAME] { ... }` and `plane [NAME] { ... }` with a `material` and a `transform`.
+//!   Named objects can be looked up with [`World::find`](crate::render::World::find).
+//! - `material NAME { ... }` and `transform NAME { ... }` define reusable materials and
+//!   transforms, which `material NAME` and `transform NAME` refer to later on.
+//!
+//! A material block holds one texture statement and optionally `ambient`, `diffuse`,
+//! `specular` and `shininess` values, `bump STRENGTH { TEXTURE }` or `normal_map { TEXTURE }`,
+//! and `material NAME` to start from a named material.
+//!
+//! Textures are `color R G B`; the patterns `stripes [WIDTH]`, `gradient [STOPS...]`, `ring`,
+//! `checkers` and `blend`, whose blocks hold the nested textures; the noise textures
+//! `marble SEED`, `wood SEED` and `clouds SEED`, blending two nested textures;
+//! `cellular SEED [f1|f2|f2_minus_f1|cell_id] [euclidean|manhattan|chebyshev]`;
+//! `image "PATH" [planar|spherical|cylindrical]` with optional `filter` and `wrap` statements;
+//! and `perturb SEED STRENGTH [FREQUENCY]` around one nested texture. Every texture block
+//! except `perturb` accepts a `transform`.
+//!
+//! A transform block applies `translate X Y Z`, `scale X Y Z` or `scale S`, `rotate_x`,
+//! `rotate_y` and `rotate_z DEGREES`, `rotate X Y Z DEGREES` around an axis, `shear XY XZ YX YZ
+//! ZX ZY` and `transform NAME` in order.
+
+use crate::render::{Camera, RenderPass, ValidationError, World};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+mod builder;
+mod syntax;
+
+/// Error returned when a scene description cannot be read or is invalid.
+#[derive(Debug)]
+pub enum SceneError {
+  Io(io::Error),
+  /// The description is malformed, refers to undefined names or images that cannot be loaded.
+  Invalid {
+    line: usize,
+    column: usize,
+    message: String,
+  },
+  /// The scene is well formed, but the world it describes cannot be rendered.
+  Validation(ValidationError),
+}
+impl Display for SceneError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return match self {
+      SceneError::Io(error) => write!(f, "I/O error: {}", error),
+      SceneError::Invalid {
+        line,
+        column,
+        message,
+      } => write!(f, "line {}, column {}: {}", line, column, message),
+      SceneError::Validation(error) => write!(f, "invalid scene: {}", error),
+    };
+  }
+}
+impl Error for SceneError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    return match self {
+      SceneError::Io(error) => Some(error),
+      SceneError::Validation(error) => Some(error),
+      _ => None,
+    };
+  }
+}
+impl From<io::Error> for SceneError {
+  fn from(error: io::Error) -> Self {
+    return SceneError::Io(error);
+  }
+}
+
+/// A camera and the world it looks at, built from a scene description.
+pub struct Scene {
+  camera: Camera,
+  world: World,
+  passes: Vec<RenderPass>,
+}
+impl Scene {
+  pub fn new(camera: Camera, world: World) -> Self {
+    return Self {
+      camera,
+      world,
+      passes: Vec::new(),
+    };
+  }
+  pub fn with_passes(mut self, passes: Vec<RenderPass>) -> Self {
+    self.passes = passes;
+    return self;
+  }
+
+  /// Builds a scene from a description. Relative image paths are resolved from the current
+  /// directory.
+  pub fn parse(source: &str) -> Result<Self, SceneError> {
+    return builder::build(&syntax::parse(source)?, Path::new(""));
+  }
+
+  /// Reads and builds a scene file. Relative image paths are resolved from the directory of the
+  /// file.
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    return builder::build(&syntax::parse(&source)?, directory);
+  }
+
+  pub fn camera(&self) -> &Camera {
+    return &self.camera;
+  }
+  pub fn world(&self) -> &World {
+    return &self.world;
+  }
+  /// Returns the passes the camera asks for besides the shaded image.
+  pub fn passes(&self) -> &[RenderPass] {
+    return &self.passes;
+  }
+  pub fn into_parts(self) -> (Camera, World) {
+    return (self.camera, self.world);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn error_display() {
+    let error = SceneError::Invalid {
+      line: 3,
+      column: 14,
+      message: "unknown material `stone`".to_string(),
+    };
+    assert_eq!(
+      error.to_string(),
+      "line 3, column 14: unknown material `stone`"
+    );
+  }
+
+  #[test]
+  fn parse_example_scene() {
+    let scene = match Scene::parse(include_str!("../scenes/corner.scene")) {
+      Ok(scene) => scene,
+      Err(error) => panic!("Expected example scene to parse, got {}", error),
+    };
+    assert_eq!(scene.camera().canvas_width(), 640);
+    assert_eq!(scene.world().objects().len(), 4);
+    assert_eq!(scene.world().lights().len(), 1);
+  }
+
+  #[test]
+  fn open_missing_file() {
+    let result = Scene::open("does/not/exist.scene");
+    assert!(matches!(result, Err(SceneError::Io(_))));
+  }
+}
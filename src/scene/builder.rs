@@ -0,0 +1,1121 @@
+use super::syntax::{Argument, Statement, Value};
+use super::{Scene, SceneError};
+use crate::geometry::{Material, Matrix, NormalMap, Point, Quaternion, Vector};
+use crate::render::{Camera, PointLight, RenderPass, World};
+use crate::shapes::{Plane, Shape, Sphere};
+use crate::textures::{
+  blend, checkers, gradient, gradient_stops, ring, stripes, stripes_with_width, Cellular,
+  CellularOutput, Filter, ImageTexture, Metric, NoiseKind, NoiseTexture, Pattern, Perturb, Texture,
+  UvMapping, Worley, Wrap,
+};
+use crate::utils::{Float, EPSILON};
+use crate::Color;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::Path;
+
+/// Builds the scene described by the top-level statements of a scene file.
+pub fn build(statements: &[Statement], directory: &Path) -> Result<Scene, SceneError> {
+  let mut builder = Builder {
+    directory,
+    materials: HashMap::new(),
+    transforms: HashMap::new(),
+  };
+  let mut camera = None;
+  let mut world = World::default();
+
+  for statement in statements {
+    match statement.name.as_str() {
+      "camera" => {
+        if camera.is_some() {
+          return Err(
+            statement
+              .position
+              .error("camera is already defined".to_string()),
+          );
+        }
+        camera = Some(builder.camera(statement)?);
+      }
+      "light" => {
+        world.add_light(builder.light(statement)?);
+      }
+      "sphere" | "plane" => {
+        let (name, material, transform) = builder.shape(statement)?;
+        let object: Box<dyn Shape> = match statement.name.as_str() {
+          "sphere" => Box::new(Sphere::new(material, transform)),
+          _ => Box::new(Plane::new(material, transform)),
+        };
+        match name {
+          None => world.add_object(object),
+          Some(name) if world.find(name).is_some() => {
+            return Err(
+              statement.arguments[0]
+                .position
+                .error(format!("object `{}` is already defined", name)),
+            )
+          }
+          Some(name) => world.add_named_object(name, object),
+        };
+      }
+      "material" => builder.define_material(statement)?,
+      "transform" => builder.define_transform(statement)?,
+      _ => return Err(unknown(statement, "the scene")),
+    }
+  }
+
+  world.validate().map_err(SceneError::Validation)?;
+  let (camera, passes) = camera.unwrap_or_default();
+  return Ok(Scene::new(camera, world).with_passes(passes));
+}
+
+/// Rejects transforms that cannot be inverted, which would make rendering panic.
+fn invertible(statement: &Statement, matrix: Matrix) -> Result<Matrix, SceneError> {
+  return match matrix.try_inverse() {
+    Ok(_) => Ok(matrix),
+    Err(_) => Err(
+      statement
+        .position
+        .error("transform cannot be inverted, for example because it scales by zero".to_string()),
+    ),
+  };
+}
+
+struct Builder<'a> {
+  directory: &'a Path,
+  /// Named materials are kept as statements, since every use needs its own textures.
+  materials: HashMap<String, &'a [Statement]>,
+  transforms: HashMap<String, Matrix>,
+}
+impl<'a> Builder<'a> {
+  /// Returns the camera of a `camera` statement and the passes it asks for.
+  fn camera(&self, statement: &Statement) -> Result<(Camera, Vec<RenderPass>), SceneError> {
+    no_arguments(statement)?;
+    let default = Camera::default();
+    let (mut width, mut height, mut fov) = (
+      default.canvas_width(),
+      default.canvas_height(),
+      default.fov(),
+    );
+    let (mut from, mut to, mut up) = (
+      Point::origin(),
+      Point::new(0., 0., -1.),
+      Vector::new(0., 1., 0.),
+    );
+    let mut look_at = None;
+    let mut transform = None;
+    let mut passes = Vec::new();
+
+    for statement in block(statement)? {
+      match statement.name.as_str() {
+        "size" => {
+          no_block(statement)?;
+          let [w, h] = arguments_array(statement)?;
+          width = positive_integer(w)? as usize;
+          height = positive_integer(h)? as usize;
+        }
+        "fov" => {
+          let [value] = numbers(statement)?;
+          if !(value > 0. && value < 180.) {
+            return Err(
+              statement.arguments[0]
+                .position
+                .error("field of view must be between 0 and 180 degrees".to_string()),
+            );
+          }
+          fov = value;
+        }
+        "from" | "to" | "up" => {
+          let [x, y, z] = numbers(statement)?;
+          match statement.name.as_str() {
+            "from" => from = Point::new(x, y, z),
+            "to" => to = Point::new(x, y, z),
+            _ => up = Vector::new(x, y, z),
+          }
+          look_at = Some(statement.position);
+        }
+        "transform" => transform = Some((self.transform(statement)?, statement.position)),
+        "aovs" => {
+          no_block(statement)?;
+          if statement.arguments.is_empty() {
+            return Err(
+              statement
+                .position
+                .error("`aovs` expects at least one pass".to_string()),
+            );
+          }
+          for argument in &statement.arguments {
+            let name = word(argument)?;
+            passes.push(RenderPass::from_name(name).ok_or_else(|| {
+              return argument.position.error(format!("unknown pass `{}`", name));
+            })?);
+          }
+        }
+        _ => return Err(unknown(statement, "camera")),
+      }
+    }
+
+    let camera_to_world = match (look_at, transform) {
+      (Some(_), Some((_, position))) => {
+        return Err(
+          position
+            .error("camera cannot have both a transform and `from`, `to` or `up`".to_string()),
+        )
+      }
+      (Some(position), None) => {
+        // Either would leave the camera without a direction to look in or a way to roll.
+        let forward = to - from;
+        if forward.magnitude() < EPSILON {
+          return Err(
+            position.error("camera `from` and `to` must be different points".to_string()),
+          );
+        }
+        if up.magnitude() < EPSILON
+          || forward.normalize().cross(&up.normalize()).magnitude() < EPSILON
+        {
+          return Err(
+            position.error("camera `up` cannot be parallel to the view direction".to_string()),
+          );
+        }
+        Matrix::look_at(&from, &to, &up)
+      }
+      (None, Some((transform, _))) => transform,
+      (None, None) => *default.camera_to_world(),
+    };
+    return Ok((Camera::new(width, height, fov, camera_to_world), passes));
+  }
+
+  fn light(&self, statement: &Statement) -> Result<PointLight, SceneError> {
+    no_arguments(statement)?;
+    let default = PointLight::default();
+    let (mut position, mut color) = (*default.position(), *default.color());
+    for statement in block(statement)? {
+      match statement.name.as_str() {
+        "position" => {
+          let [x, y, z] = numbers(statement)?;
+          position = Point::new(x, y, z);
+        }
+        "color" => {
+          let [r, g, b] = numbers(statement)?;
+          color = Color::new(r, g, b);
+        }
+        _ => return Err(unknown(statement, "light")),
+      }
+    }
+    return Ok(PointLight::new(position, color));
+  }
+
+  fn shape<'s>(
+    &self,
+    statement: &'s Statement,
+  ) -> Result<(Option<&'s str>, Material, Matrix), SceneError> {
+    let name = match &statement.arguments[..] {
+      [] => None,
+      [name] => Some(word(name)?),
+      [_, extra, ..] => {
+        return Err(
+          extra
+            .position
+            .error(format!("`{}` takes at most a name", statement.name)),
+        )
+      }
+    };
+    let mut material = None;
+    let mut transform = Matrix::identity();
+    if let Some(statements) = &statement.block {
+      for statement in statements {
+        match statement.name.as_str() {
+          "material" => material = Some(self.material(statement)?),
+          "transform" => transform = self.transform(statement)? * transform,
+          _ => return Err(unknown(statement, "shape")),
+        }
+      }
+    }
+    return Ok((name, material.unwrap_or_default(), transform));
+  }
+
+  fn define_material(&mut self, statement: &'a Statement) -> Result<(), SceneError> {
+    let name = definition_name(statement)?;
+    if self.materials.contains_key(name) {
+      return Err(
+        statement
+          .position
+          .error(format!("material `{}` is already defined", name)),
+      );
+    }
+    let statements = block(statement)?;
+    // Build the material once so mistakes are reported even if it is never used.
+    self.material_body(statements, &mut MaterialSpec::default())?;
+    self.materials.insert(name.to_string(), statements);
+    return Ok(());
+  }
+
+  fn define_transform(&mut self, statement: &Statement) -> Result<(), SceneError> {
+    let name = definition_name(statement)?;
+    if self.transforms.contains_key(name) {
+      return Err(
+        statement
+          .position
+          .error(format!("transform `{}` is already defined", name)),
+      );
+    }
+    let transform = invertible(statement, self.transform_body(block(statement)?)?)?;
+    self.transforms.insert(name.to_string(), transform);
+    return Ok(());
+  }
+
+  /// Returns the material of a `material NAME` or `material { ... }` statement.
+  fn material(&self, statement: &Statement) -> Result<Material, SceneError> {
+    let mut spec = MaterialSpec::default();
+    match (&statement.arguments[..], &statement.block) {
+      ([argument], None) => self.material_body(self.named_material(argument)?, &mut spec)?,
+      ([], Some(statements)) => self.material_body(statements, &mut spec)?,
+      _ => {
+        return Err(
+          statement
+            .position
+            .error("expected `material NAME` or `material { ... }`".to_string()),
+        )
+      }
+    }
+    return Ok(spec.build());
+  }
+
+  fn named_material(&self, argument: &Argument) -> Result<&'a [Statement], SceneError> {
+    let name = word(argument)?;
+    return self.materials.get(name).copied().ok_or_else(|| {
+      argument
+        .position
+        .error(format!("unknown material `{}`", name))
+    });
+  }
+
+  fn material_body(
+    &self,
+    statements: &[Statement],
+    spec: &mut MaterialSpec,
+  ) -> Result<(), SceneError> {
+    for statement in statements {
+      match statement.name.as_str() {
+        "ambient" => spec.ambience = numbers::<1>(statement)?[0],
+        "diffuse" => spec.diffuse = numbers::<1>(statement)?[0],
+        "specular" => spec.specular = numbers::<1>(statement)?[0],
+        "shininess" => spec.shininess = numbers::<1>(statement)?[0],
+        "bump" => {
+          let [strength] = arguments_array(statement)?;
+          let height = self.single_texture(statement)?;
+          spec.normal_map = Some(NormalMap::Bump {
+            height,
+            strength: number(strength)?,
+          });
+        }
+        "normal_map" => {
+          no_arguments(statement)?;
+          spec.normal_map = Some(NormalMap::Tangent(self.single_texture(statement)?));
+        }
+        "material" => {
+          no_block(statement)?;
+          let [name] = arguments_array(statement)?;
+          self.material_body(self.named_material(name)?, spec)?;
+        }
+        _ => match self.texture(statement)? {
+          Some(texture) => spec.texture = Some(texture),
+          None => return Err(unknown(statement, "material")),
+        },
+      }
+    }
+    return Ok(());
+  }
+
+  /// Returns the texture of a texture statement, or `None` if the statement is not one.
+  fn texture(&self, statement: &Statement) -> Result<Option<Box<dyn Texture>>, SceneError> {
+    let texture: Box<dyn Texture> = match statement.name.as_str() {
+      "color" => {
+        let [r, g, b] = numbers(statement)?;
+        Box::new(Color::new(r, g, b))
+      }
+      "stripes" | "gradient" | "ring" | "checkers" | "blend" => self.pattern(statement)?,
+      "marble" | "wood" | "clouds" => self.noise(statement)?,
+      "cellular" => self.cellular(statement)?,
+      "image" => self.image(statement)?,
+      "perturb" => self.perturb(statement)?,
+      _ => return Ok(None),
+    };
+    return Ok(Some(texture));
+  }
+
+  /// Returns the textures and the transform in the block of a texture statement.
+  fn nested_textures(
+    &self,
+    statement: &Statement,
+  ) -> Result<(Vec<Box<dyn Texture>>, Matrix), SceneError> {
+    let mut textures = Vec::new();
+    let mut transform = Matrix::identity();
+    if let Some(statements) = &statement.block {
+      for nested in statements {
+        if nested.name == "transform" {
+          transform = self.transform(nested)? * transform;
+        } else {
+          match self.texture(nested)? {
+            Some(texture) => textures.push(texture),
+            None => return Err(unknown(nested, &format!("`{}`", statement.name))),
+          }
+        }
+      }
+    }
+    return Ok((textures, transform));
+  }
+
+  fn single_texture(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let (mut textures, transform) = self.nested_textures(statement)?;
+    if textures.len() != 1 || transform != Matrix::identity() {
+      return Err(statement.position.error(format!(
+        "`{}` needs a block with exactly one texture",
+        statement.name
+      )));
+    }
+    return Ok(textures.remove(0));
+  }
+
+  fn pattern(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let (mut textures, transform) = self.nested_textures(statement)?;
+    if textures.is_empty() {
+      textures = vec![Box::new(Color::white()), Box::new(Color::black())];
+    }
+    let arguments = statement
+      .arguments
+      .iter()
+      .map(number)
+      .collect::<Result<Vec<Float>, SceneError>>()?;
+    let pattern = match (statement.name.as_str(), &arguments[..]) {
+      ("stripes", []) => Pattern::with_transformation(stripes, textures, transform),
+      ("stripes", [width]) if *width > 0. => {
+        Pattern::with_transformation(stripes_with_width(*width), textures, transform)
+      }
+      ("gradient", []) => Pattern::with_transformation(gradient, textures, transform),
+      ("gradient", stops) if stops.len() == textures.len() => {
+        Pattern::with_transformation(gradient_stops(stops.to_vec()), textures, transform)
+      }
+      ("ring", []) => Pattern::with_transformation(ring, textures, transform),
+      ("checkers", []) => Pattern::with_transformation(checkers, textures, transform),
+      ("blend", []) => Pattern::with_transformation(blend, textures, transform),
+      (name, _) => {
+        let expected = match name {
+          "stripes" => "`stripes` takes an optional positive width",
+          "gradient" => "`gradient` takes no stops or one stop per texture",
+          _ => "pattern takes no arguments",
+        };
+        return Err(statement.position.error(expected.to_string()));
+      }
+    };
+    return Ok(Box::new(pattern));
+  }
+
+  fn noise(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let (mut textures, transform) = self.nested_textures(statement)?;
+    let (a, b): (Box<dyn Texture>, Box<dyn Texture>) = match textures.len() {
+      0 => (Box::new(Color::white()), Box::new(Color::black())),
+      2 => {
+        let b = textures.remove(1);
+        (textures.remove(0), b)
+      }
+      _ => {
+        return Err(
+          statement
+            .position
+            .error(format!("`{}` blends exactly two textures", statement.name)),
+        )
+      }
+    };
+    let (seed, parameters) = match statement.arguments.split_first() {
+      Some((seed, parameters)) => (integer(seed)?, parameters),
+      None => return Err(statement.position.error("expected a seed".to_string())),
+    };
+    let parameters = parameters
+      .iter()
+      .map(number)
+      .collect::<Result<Vec<Float>, SceneError>>()?;
+    let texture = match (statement.name.as_str(), &parameters[..]) {
+      ("marble", []) => NoiseTexture::marble(seed, a, b),
+      ("marble", [frequency, turbulence]) => NoiseTexture::new(
+        NoiseKind::Marble {
+          frequency: *frequency,
+          turbulence: *turbulence,
+        },
+        seed,
+        a,
+        b,
+      ),
+      ("wood", []) => NoiseTexture::wood(seed, a, b),
+      ("wood", [rings, turbulence]) => NoiseTexture::new(
+        NoiseKind::Wood {
+          rings: *rings,
+          turbulence: *turbulence,
+        },
+        seed,
+        a,
+        b,
+      ),
+      ("clouds", []) => NoiseTexture::clouds(seed, a, b),
+      ("clouds", [_]) => {
+        let octaves = integer(&statement.arguments[1])? as u32;
+        NoiseTexture::new(NoiseKind::Clouds { octaves }, seed, a, b)
+      }
+      (name, _) => {
+        let expected = match name {
+          "marble" => "`marble` takes a seed, optionally followed by frequency and turbulence",
+          "wood" => "`wood` takes a seed, optionally followed by rings and turbulence",
+          _ => "`clouds` takes a seed, optionally followed by octaves",
+        };
+        return Err(statement.position.error(expected.to_string()));
+      }
+    };
+    return Ok(Box::new(texture.with_transformation(transform)));
+  }
+
+  fn cellular(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let (textures, transform) = self.nested_textures(statement)?;
+    if !textures.is_empty() {
+      return Err(
+        statement
+          .position
+          .error("`cellular` has no nested textures".to_string()),
+      );
+    }
+    let (seed, options) = match statement.arguments.split_first() {
+      Some((seed, options)) => (integer(seed)?, options),
+      None => return Err(statement.position.error("expected a seed".to_string())),
+    };
+    let (mut output, mut metric) = (CellularOutput::default(), Metric::default());
+    for option in options {
+      match word(option)? {
+        "f1" => output = CellularOutput::F1,
+        "f2" => output = CellularOutput::F2,
+        "f2_minus_f1" => output = CellularOutput::F2MinusF1,
+        "cell_id" => output = CellularOutput::CellId,
+        "euclidean" => metric = Metric::Euclidean,
+        "manhattan" => metric = Metric::Manhattan,
+        "chebyshev" => metric = Metric::Chebyshev,
+        other => {
+          return Err(
+            option
+              .position
+              .error(format!("unknown cellular option `{}`", other)),
+          )
+        }
+      }
+    }
+    let cellular = Cellular::new(Worley::new(seed, metric), output).with_transformation(transform);
+    return Ok(Box::new(cellular));
+  }
+
+  fn image(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let (path, mapping) = match &statement.arguments[..] {
+      [path] => (path, UvMapping::default()),
+      [path, mapping] => {
+        let mapping = match word(mapping)? {
+          "planar" => UvMapping::Planar,
+          "spherical" => UvMapping::Spherical,
+          "cylindrical" => UvMapping::Cylindrical,
+          other => {
+            return Err(
+              mapping
+                .position
+                .error(format!("unknown mapping `{}`", other)),
+            )
+          }
+        };
+        (path, mapping)
+      }
+      _ => {
+        return Err(
+          statement
+            .position
+            .error("expected an image path, optionally followed by a mapping".to_string()),
+        )
+      }
+    };
+    let file = match &path.value {
+      Value::Text(file) => file,
+      _ => {
+        return Err(
+          path
+            .position
+            .error("expected a quoted image path".to_string()),
+        )
+      }
+    };
+
+    let mut texture = ImageTexture::open(self.directory.join(file), mapping).map_err(|error| {
+      path
+        .position
+        .error(format!("cannot load image \"{}\": {}", file, error))
+    })?;
+    let mut transform = Matrix::identity();
+    if let Some(statements) = &statement.block {
+      for nested in statements {
+        match nested.name.as_str() {
+          "filter" => {
+            no_block(nested)?;
+            let [filter] = arguments_array(nested)?;
+            texture = texture.with_filter(match word(filter)? {
+              "nearest" => Filter::Nearest,
+              "bilinear" => Filter::Bilinear,
+              other => return Err(filter.position.error(format!("unknown filter `{}`", other))),
+            });
+          }
+          "wrap" => {
+            no_block(nested)?;
+            let [wrap] = arguments_array(nested)?;
+            texture = texture.with_wrap(match word(wrap)? {
+              "repeat" => Wrap::Repeat,
+              "clamp" => Wrap::Clamp,
+              "mirror" => Wrap::Mirror,
+              other => {
+                return Err(
+                  wrap
+                    .position
+                    .error(format!("unknown wrap mode `{}`", other)),
+                )
+              }
+            });
+          }
+          "transform" => transform = self.transform(nested)? * transform,
+          _ => return Err(unknown(nested, "`image`")),
+        }
+      }
+    }
+    return Ok(Box::new(texture.with_transformation(transform)));
+  }
+
+  fn perturb(&self, statement: &Statement) -> Result<Box<dyn Texture>, SceneError> {
+    let texture = self.single_texture(statement)?;
+    let (seed, strength, frequency) = match &statement.arguments[..] {
+      [seed, strength] => (integer(seed)?, number(strength)?, 1.),
+      [seed, strength, frequency] => (integer(seed)?, number(strength)?, number(frequency)?),
+      _ => {
+        return Err(statement.position.error(
+          "`perturb` takes a seed and a strength, optionally followed by a frequency".to_string(),
+        ))
+      }
+    };
+    return Ok(Box::new(
+      Perturb::new(texture, seed, strength).with_frequency(frequency),
+    ));
+  }
+
+  /// Returns the matrix of a `transform NAME` or `transform { ... }` statement.
+  fn transform(&self, statement: &Statement) -> Result<Matrix, SceneError> {
+    return match (&statement.arguments[..], &statement.block) {
+      ([argument], None) => {
+        let name = word(argument)?;
+        self.transforms.get(name).copied().ok_or_else(|| {
+          argument
+            .position
+            .error(format!("unknown transform `{}`", name))
+        })
+      }
+      ([], Some(statements)) => invertible(statement, self.transform_body(statements)?),
+      _ => Err(
+        statement
+          .position
+          .error("expected `transform NAME` or `transform { ... }`".to_string()),
+      ),
+    };
+  }
+
+  fn transform_body(&self, statements: &[Statement]) -> Result<Matrix, SceneError> {
+    let mut matrix = Matrix::identity();
+    for statement in statements {
+      matrix = match statement.name.as_str() {
+        "translate" => {
+          let [x, y, z] = numbers(statement)?;
+          matrix.translate(x, y, z)
+        }
+        "scale" if statement.arguments.len() == 1 => {
+          let [factor] = numbers(statement)?;
+          matrix.scale(factor, factor, factor)
+        }
+        "scale" => {
+          let [x, y, z] = numbers(statement)?;
+          matrix.scale(x, y, z)
+        }
+        "rotate_x" => matrix.rotate_x(numbers::<1>(statement)?[0].to_radians()),
+        "rotate_y" => matrix.rotate_y(numbers::<1>(statement)?[0].to_radians()),
+        "rotate_z" => matrix.rotate_z(numbers::<1>(statement)?[0].to_radians()),
+        "rotate" => {
+          let [x, y, z, angle] = numbers(statement)?;
+          let axis = Vector::new(x, y, z);
+          if axis.magnitude() == 0. {
+            return Err(
+              statement
+                .position
+                .error("the axis of `rotate` must not be zero".to_string()),
+            );
+          }
+          matrix.rotate(&Quaternion::from_axis_angle(&axis, angle.to_radians()))
+        }
+        "shear" => {
+          let [xy, xz, yx, yz, zx, zy] = numbers(statement)?;
+          matrix.shear(xy, xz, yx, yz, zx, zy)
+        }
+        "transform" => self.transform(statement)? * matrix,
+        _ => return Err(unknown(statement, "transform")),
+      };
+    }
+    return Ok(matrix);
+  }
+}
+
+/// Material properties collected while reading a material block.
+struct MaterialSpec {
+  texture: Option<Box<dyn Texture>>,
+  ambience: Float,
+  diffuse: Float,
+  specular: Float,
+  shininess: Float,
+  normal_map: Option<NormalMap>,
+}
+impl MaterialSpec {
+  fn build(self) -> Material {
+    let texture = self.texture.unwrap_or_else(|| Box::new(Color::white()));
+    let material = Material::new(
+      texture,
+      self.ambience,
+      self.diffuse,
+      self.specular,
+      self.shininess,
+    );
+    return match self.normal_map {
+      None => material,
+      Some(normal_map) => material.with_normal_map(normal_map),
+    };
+  }
+}
+impl Default for MaterialSpec {
+  fn default() -> Self {
+    let (ambience, diffuse, specular, shininess) = Material::default().shading_properties();
+    return Self {
+      texture: None,
+      ambience,
+      diffuse,
+      specular,
+      shininess,
+      normal_map: None,
+    };
+  }
+}
+
+fn unknown(statement: &Statement, context: &str) -> SceneError {
+  return statement.position.error(format!(
+    "unknown statement `{}` in {}",
+    statement.name, context
+  ));
+}
+
+fn block(statement: &Statement) -> Result<&[Statement], SceneError> {
+  return statement.block.as_deref().ok_or_else(|| {
+    statement
+      .position
+      .error(format!("`{}` needs a block", statement.name))
+  });
+}
+
+fn no_block(statement: &Statement) -> Result<(), SceneError> {
+  return match statement.block {
+    None => Ok(()),
+    Some(_) => Err(
+      statement
+        .position
+        .error(format!("`{}` does not take a block", statement.name)),
+    ),
+  };
+}
+
+fn no_arguments(statement: &Statement) -> Result<(), SceneError> {
+  return match statement.arguments.first() {
+    None => Ok(()),
+    Some(argument) => Err(
+      argument
+        .position
+        .error(format!("`{}` does not take arguments", statement.name)),
+    ),
+  };
+}
+
+fn definition_name(statement: &Statement) -> Result<&str, SceneError> {
+  return match &statement.arguments[..] {
+    [argument] => word(argument),
+    _ => Err(
+      statement
+        .position
+        .error(format!("expected `{} NAME {{ ... }}`", statement.name)),
+    ),
+  };
+}
+
+fn arguments_array<const N: usize>(statement: &Statement) -> Result<&[Argument; N], SceneError> {
+  return <&[Argument; N]>::try_from(&statement.arguments[..]).map_err(|_| {
+    statement.position.error(format!(
+      "`{}` expects {} argument{}, found {}",
+      statement.name,
+      N,
+      if N == 1 { "" } else { "s" },
+      statement.arguments.len()
+    ))
+  });
+}
+
+/// Returns the `N` numeric arguments of a statement without a block.
+fn numbers<const N: usize>(statement: &Statement) -> Result<[Float; N], SceneError> {
+  no_block(statement)?;
+  let arguments = arguments_array::<N>(statement)?;
+  let mut values = [0.; N];
+  for (value, argument) in values.iter_mut().zip(arguments) {
+    *value = number(argument)?;
+  }
+  return Ok(values);
+}
+
+fn number(argument: &Argument) -> Result<Float, SceneError> {
+  return match argument.value {
+    Value::Number(number) if number.is_finite() => Ok(number),
+    _ => Err(argument.position.error("expected a number".to_string())),
+  };
+}
+
+fn integer(argument: &Argument) -> Result<u64, SceneError> {
+  let value = number(argument)?;
+  if value < 0. || value.fract() != 0. {
+    return Err(
+      argument
+        .position
+        .error("expected a non-negative integer".to_string()),
+    );
+  }
+  return Ok(value as u64);
+}
+
+fn positive_integer(argument: &Argument) -> Result<u64, SceneError> {
+  return match integer(argument)? {
+    0 => Err(
+      argument
+        .position
+        .error("expected a positive integer".to_string()),
+    ),
+    value => Ok(value),
+  };
+}
+
+fn word(argument: &Argument) -> Result<&str, SceneError> {
+  return match &argument.value {
+    Value::Word(word) => Ok(word),
+    _ => Err(argument.position.error("expected a name".to_string())),
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::consts::PI;
+  use std::fs;
+
+  fn parse(source: &str) -> Scene {
+    return match Scene::parse(source) {
+      Ok(scene) => scene,
+      Err(error) => panic!("Expected scene to parse, got {}", error),
+    };
+  }
+
+  fn parse_error(source: &str) -> String {
+    return match Scene::parse(source) {
+      Ok(_) => panic!("Expected scene to be invalid"),
+      Err(error) => error.to_string(),
+    };
+  }
+
+  fn color_at(scene: &Scene, object: usize, point: Point) -> Color {
+    return scene.world().objects()[object].material().color_at(&point);
+  }
+
+  #[test]
+  fn empty_scene() {
+    let scene = parse("");
+    assert_eq!(scene.camera().canvas_width(), 640);
+    assert!(scene.world().objects().is_empty());
+    assert!(scene.world().lights().is_empty());
+  }
+
+  #[test]
+  fn camera_look_at() {
+    let scene = parse("camera {\n  size 320 240\n  fov 60\n  from 0 2 -5; to 5 -6 1; up 1 1 0\n}");
+    let camera = scene.camera();
+    assert_eq!(camera.canvas_width(), 320);
+    assert_eq!(camera.canvas_height(), 240);
+    assert_eq!(camera.fov(), 60.);
+    assert_eq!(
+      camera.camera_to_world(),
+      &Matrix::look_at(
+        &Point::new(0., 2., -5.),
+        &Point::new(5., -6., 1.),
+        &Vector::new(1., 1., 0.)
+      )
+    );
+  }
+
+  #[test]
+  fn camera_aovs() {
+    assert!(parse("camera { size 4 4 }").passes().is_empty());
+    let scene = parse("camera { aovs depth object_id normal }");
+    assert_eq!(
+      scene.passes(),
+      &[RenderPass::Depth, RenderPass::ObjectId, RenderPass::Normal]
+    );
+    assert_eq!(
+      parse_error("camera { aovs depth shadow }"),
+      "line 1, column 21: unknown pass `shadow`"
+    );
+    assert_eq!(
+      parse_error("camera { aovs }"),
+      "line 1, column 10: `aovs` expects at least one pass"
+    );
+  }
+
+  #[test]
+  fn camera_transform() {
+    let scene = parse("camera { transform { rotate_y 90; translate 1 2 3 } }");
+    assert_eq!(
+      scene.camera().camera_to_world(),
+      &Matrix::identity().rotate_y(PI / 2.).translate(1., 2., 3.)
+    );
+  }
+
+  #[test]
+  fn lights() {
+    let scene = parse("light { position -10 10 -10; color 1 0.5 0 }\nlight {}");
+    let lights = scene.world().lights();
+    assert_eq!(lights.len(), 2);
+    assert_eq!(lights[0].position(), &Point::new(-10., 10., -10.));
+    assert_eq!(lights[0].color(), &Color::new(1., 0.5, 0.));
+    assert_eq!(lights[1], PointLight::default());
+  }
+
+  #[test]
+  fn shapes_with_transform_chains() {
+    let source = "
+      transform lifted { translate 0 1 0 }
+      sphere {
+        transform { scale 2; rotate_x 90; rotate_z -45; shear 1 0 0 0 0 0; transform lifted }
+      }
+      plane { transform { rotate 0 0 -2 45 } }
+    ";
+    let scene = parse(source);
+    let objects = scene.world().objects();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(
+      objects[0].object_to_world(),
+      &Matrix::identity()
+        .scale(2., 2., 2.)
+        .rotate_x(PI / 2.)
+        .rotate_z(-PI / 4.)
+        .shear(1., 0., 0., 0., 0., 0.)
+        .translate(0., 1., 0.)
+    );
+    assert_eq!(
+      objects[1].object_to_world(),
+      &Matrix::identity().rotate_z(-PI / 4.)
+    );
+    assert!(parse_error("plane { transform { rotate 0 0 0 45 } }").contains("must not be zero"));
+  }
+
+  #[test]
+  fn singular_transforms() {
+    assert_eq!(
+      parse_error("sphere {\n  transform { scale 1 0 1 }\n}"),
+      "line 2, column 3: transform cannot be inverted, for example because it scales by zero"
+    );
+    assert!(parse_error("transform flat { scale 0 }").starts_with("line 1, column 1: transform"));
+    assert!(
+      parse_error("plane { material { stripes { transform { scale 0 } } } }")
+        .contains("cannot be inverted")
+    );
+  }
+
+  #[test]
+  fn invalid_worlds() {
+    assert_eq!(
+      parse_error("sphere ball { material { diffuse -1 } }\nlight { color 1 -1 1 }"),
+      "invalid scene: object `ball`: invalid material diffuse -1; light 1: invalid color (1, -1, 1)"
+    );
+  }
+
+  #[test]
+  fn materials() {
+    let source = "
+      material shiny { color 1 0 0; specular 1; shininess 50 }
+      sphere { material shiny }
+      sphere { material { material shiny; ambient 0.5; color 0 0 1 } }
+      sphere { material { ambient 0.2; diffuse 0.3 } }
+    ";
+    let scene = parse(source);
+    let objects = scene.world().objects();
+    assert_eq!(
+      objects[0].material().shading_properties(),
+      (0.1, 0.9, 1., 50.)
+    );
+    assert_eq!(color_at(&scene, 0, Point::origin()), Color::red());
+    assert_eq!(
+      objects[1].material().shading_properties(),
+      (0.5, 0.9, 1., 50.)
+    );
+    assert_eq!(color_at(&scene, 1, Point::origin()), Color::blue());
+    assert_eq!(
+      objects[2].material().shading_properties(),
+      (0.2, 0.3, 0.9, 200.)
+    );
+    assert_eq!(color_at(&scene, 2, Point::origin()), Color::white());
+  }
+
+  #[test]
+  fn nested_patterns() {
+    let source = "
+      sphere {
+        material {
+          stripes 0.5 {
+            color 1 0 0
+            checkers { color 0 1 0; color 0 0 1; transform { scale 0.1 } }
+          }
+        }
+      }
+    ";
+    let scene = parse(source);
+    assert_eq!(color_at(&scene, 0, Point::new(0.25, 0., 0.)), Color::red());
+    assert_eq!(
+      color_at(&scene, 0, Point::new(0.85, 0.05, 0.05)),
+      Color::green()
+    );
+    assert_eq!(
+      color_at(&scene, 0, Point::new(0.85, 0.15, 0.05)),
+      Color::blue()
+    );
+  }
+
+  #[test]
+  fn pattern_defaults_to_white_and_black() {
+    let scene = parse("plane { material { ring } }");
+    assert_eq!(color_at(&scene, 0, Point::new(0.5, 0., 0.)), Color::white());
+    assert_eq!(color_at(&scene, 0, Point::new(1.5, 0., 0.)), Color::black());
+  }
+
+  #[test]
+  fn procedural_textures() {
+    let source = "
+      material a { marble 3 { color 1 0 0; color 0 0 1; transform { scale 2 } } }
+      material b { wood 4 2 0.5 }
+      material c { clouds 5 3 }
+      material d { cellular 6 cell_id manhattan { transform { scale 0.5 } } }
+      material e { perturb 7 0.3 2 { gradient 0 1 { color 0 0 0; color 1 1 1 } } }
+      material f { color 1 1 1; bump 0.5 { cellular 1 f1 } }
+      material g { normal_map { color 0.5 0.5 1 } }
+      sphere { material a }
+    ";
+    let scene = parse(source);
+    let color = color_at(&scene, 0, Point::new(0.3, 0.7, -0.2));
+    assert_eq!(color.g, 0.);
+  }
+
+  #[test]
+  fn image_texture() {
+    let directory = std::env::temp_dir().join("sorb-rust-scene-image-texture");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("red.ppm"), "P3\n1 1\n255\n255 0 0\n").unwrap();
+    let scene_path = directory.join("test.scene");
+    fs::write(
+      &scene_path,
+      "sphere { material { image \"red.ppm\" spherical { filter nearest; wrap clamp } } }",
+    )
+    .unwrap();
+
+    let scene = match Scene::open(&scene_path) {
+      Ok(scene) => scene,
+      Err(error) => panic!("Expected scene to open, got {}", error),
+    };
+    assert_eq!(color_at(&scene, 0, Point::new(0., 1., 0.)), Color::red());
+    assert!(parse_error("sphere { material { image \"missing.ppm\" } }")
+      .starts_with("line 1, column 27: cannot load image \"missing.ppm\""));
+  }
+
+  #[test]
+  fn named_objects() {
+    let scene =
+      Scene::parse("sphere\nsphere ball { transform { translate 0 1 0 } }\nplane floor").unwrap();
+    let world = scene.world();
+    let ball = world.find("ball").unwrap();
+    assert_eq!(world.index_of(ball), Some(1));
+    assert_eq!(
+      world.object(ball).unwrap().object_to_world(),
+      &Matrix::identity().translate(0., 1., 0.)
+    );
+    assert_eq!(world.index_of(world.find("floor").unwrap()), Some(2));
+    assert_eq!(world.name(world.handles()[0]), None);
+  }
+
+  #[test]
+  fn errors_report_line_and_column() {
+    assert_eq!(
+      parse_error("sphere {\n  material stone\n}"),
+      "line 2, column 12: unknown material `stone`"
+    );
+    assert_eq!(
+      parse_error("sphere { transform { spin 3 } }"),
+      "line 1, column 22: unknown statement `spin` in transform"
+    );
+    assert_eq!(
+      parse_error("light {\n  position 1 2\n}"),
+      "line 2, column 3: `position` expects 3 arguments, found 2"
+    );
+    assert_eq!(
+      parse_error("light { color 1 red 0 }"),
+      "line 1, column 17: expected a number"
+    );
+    assert_eq!(
+      parse_error("camera { size 0 10 }"),
+      "line 1, column 15: expected a positive integer"
+    );
+    assert_eq!(
+      parse_error("camera { from 1 2 3; to 1 2 3 }"),
+      "line 1, column 22: camera `from` and `to` must be different points"
+    );
+    assert_eq!(
+      parse_error("camera { from 0 0 0; to 0 5 0\n up 0 1 0 }"),
+      "line 2, column 2: camera `up` cannot be parallel to the view direction"
+    );
+    assert_eq!(
+      parse_error("camera { up 0 0 0 }"),
+      "line 1, column 10: camera `up` cannot be parallel to the view direction"
+    );
+    assert_eq!(
+      parse_error("camera { from 0 0 0; transform { scale 1 } }"),
+      "line 1, column 22: camera cannot have both a transform and `from`, `to` or `up`"
+    );
+    assert_eq!(
+      parse_error("material m { color 1 1 1 }\nmaterial m { color 0 0 0 }"),
+      "line 2, column 1: material `m` is already defined"
+    );
+    assert_eq!(
+      parse_error("sphere ball\nplane ball"),
+      "line 2, column 7: object `ball` is already defined"
+    );
+    assert_eq!(
+      parse_error("sphere ball floor"),
+      "line 1, column 13: `sphere` takes at most a name"
+    );
+    assert_eq!(
+      parse_error("material m {\n  marble 1 { color 1 1 1 }\n}"),
+      "line 2, column 3: `marble` blends exactly two textures"
+    );
+    assert_eq!(
+      parse_error("cube {}"),
+      "line 1, column 1: unknown statement `cube` in the scene"
+    );
+  }
+}
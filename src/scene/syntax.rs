@@ -0,0 +1,341 @@
+use super::SceneError;
+use crate::utils::Float;
+
+/// Location in a scene description, both counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+  pub line: usize,
+  pub column: usize,
+}
+impl Position {
+  pub fn error(&self, message: String) -> SceneError {
+    return SceneError::Invalid {
+      line: self.line,
+      column: self.column,
+      message,
+    };
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Number(Float),
+  /// A bare identifier, such as a name or a keyword.
+  Word(String),
+  /// A double-quoted string.
+  Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Argument {
+  pub value: Value,
+  pub position: Position,
+}
+
+/// A statement: a name, any number of arguments and an optional block of nested statements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+  pub name: String,
+  pub position: Position,
+  pub arguments: Vec<Argument>,
+  pub block: Option<Vec<Statement>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Value(Value),
+  OpenBrace,
+  CloseBrace,
+  /// A newline or a semicolon.
+  Separator,
+  End,
+}
+impl Token {
+  fn describe(&self) -> String {
+    return match self {
+      Token::Value(Value::Number(number)) => format!("number `{}`", number),
+      Token::Value(Value::Word(word)) => format!("`{}`", word),
+      Token::Value(Value::Text(text)) => format!("string \"{}\"", text),
+      Token::OpenBrace => "`{`".to_string(),
+      Token::CloseBrace => "`}`".to_string(),
+      Token::Separator => "end of statement".to_string(),
+      Token::End => "end of file".to_string(),
+    };
+  }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Position)>, SceneError> {
+  let mut tokens = Vec::new();
+  let mut chars = source.chars().peekable();
+  let mut position = Position { line: 1, column: 1 };
+  let advance = |position: &mut Position, c: char| {
+    if c == '\n' {
+      position.line += 1;
+      position.column = 1;
+    } else {
+      position.column += 1;
+    }
+  };
+
+  while let Some(&c) = chars.peek() {
+    let start = position;
+    if c == '#' {
+      while let Some(&c) = chars.peek() {
+        if c == '\n' {
+          break;
+        }
+        advance(&mut position, c);
+        chars.next();
+      }
+    } else if c == '\n' || c == ';' {
+      advance(&mut position, c);
+      chars.next();
+      tokens.push((Token::Separator, start));
+    } else if c.is_whitespace() {
+      advance(&mut position, c);
+      chars.next();
+    } else if c == '{' || c == '}' {
+      advance(&mut position, c);
+      chars.next();
+      let token = if c == '{' {
+        Token::OpenBrace
+      } else {
+        Token::CloseBrace
+      };
+      tokens.push((token, start));
+    } else if c == '"' {
+      advance(&mut position, c);
+      chars.next();
+      let mut text = String::new();
+      loop {
+        let c = match chars.next() {
+          None | Some('\n') => return Err(start.error("unterminated string".to_string())),
+          Some(c) => c,
+        };
+        advance(&mut position, c);
+        match c {
+          '"' => break,
+          '\\' => match chars.next() {
+            Some(escaped @ ('"' | '\\')) => {
+              advance(&mut position, escaped);
+              text.push(escaped);
+            }
+            _ => return Err(position.error("invalid escape sequence in string".to_string())),
+          },
+          _ => text.push(c),
+        }
+      }
+      tokens.push((Token::Value(Value::Text(text)), start));
+    } else if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+      let mut number = String::new();
+      while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.') {
+          break;
+        }
+        advance(&mut position, c);
+        number.push(c);
+        chars.next();
+      }
+      let value = number
+        .parse()
+        .map_err(|_| start.error(format!("invalid number `{}`", number)))?;
+      tokens.push((Token::Value(Value::Number(value)), start));
+    } else if c.is_alphabetic() || c == '_' {
+      let mut word = String::new();
+      while let Some(&c) = chars.peek() {
+        if !(c.is_alphanumeric() || c == '_') {
+          break;
+        }
+        advance(&mut position, c);
+        word.push(c);
+        chars.next();
+      }
+      tokens.push((Token::Value(Value::Word(word)), start));
+    } else {
+      return Err(start.error(format!("unexpected character `{}`", c)));
+    }
+  }
+  tokens.push((Token::End, position));
+  return Ok(tokens);
+}
+
+/// Parses a scene description into its top-level statements.
+///
+/// Statements are separated by newlines or semicolons, and blocks are delimited by braces, which
+/// may span several lines.
+pub fn parse(source: &str) -> Result<Vec<Statement>, SceneError> {
+  let tokens = tokenize(source)?;
+  let mut parser = Parser {
+    tokens: &tokens,
+    index: 0,
+  };
+  let statements = parser.statements()?;
+  let (token, position) = parser.peek();
+  return match token {
+    Token::End => Ok(statements),
+    _ => Err(position.error(format!("unexpected {}", token.describe()))),
+  };
+}
+
+struct Parser<'a> {
+  tokens: &'a [(Token, Position)],
+  index: usize,
+}
+impl<'a> Parser<'a> {
+  fn peek(&self) -> &'a (Token, Position) {
+    return &self.tokens[self.index];
+  }
+
+  fn next(&mut self) -> &'a (Token, Position) {
+    let token = &self.tokens[self.index];
+    if self.index < self.tokens.len() - 1 {
+      self.index += 1;
+    }
+    return token;
+  }
+
+  /// Parses statements up to a closing brace or the end of the file, without consuming it.
+  fn statements(&mut self) -> Result<Vec<Statement>, SceneError> {
+    let mut statements = Vec::new();
+    loop {
+      let (token, position) = self.peek();
+      match token {
+        Token::Separator => {
+          self.next();
+        }
+        Token::CloseBrace | Token::End => return Ok(statements),
+        Token::Value(Value::Word(name)) => {
+          self.next();
+          statements.push(self.statement(name.clone(), *position)?);
+        }
+        _ => {
+          return Err(position.error(format!("expected a statement, found {}", token.describe())))
+        }
+      }
+    }
+  }
+
+  fn statement(&mut self, name: String, position: Position) -> Result<Statement, SceneError> {
+    let mut arguments = Vec::new();
+    while let (Token::Value(value), position) = self.peek() {
+      arguments.push(Argument {
+        value: value.clone(),
+        position: *position,
+      });
+      self.next();
+    }
+
+    let mut block = None;
+    if let (Token::OpenBrace, open_position) = self.peek() {
+      self.next();
+      block = Some(self.statements()?);
+      match self.next() {
+        (Token::CloseBrace, _) => {}
+        _ => return Err(open_position.error("unclosed `{`".to_string())),
+      }
+    }
+
+    let (token, position_after) = self.peek();
+    return match token {
+      Token::Separator | Token::CloseBrace | Token::End => Ok(Statement {
+        name,
+        position,
+        arguments,
+        block,
+      }),
+      _ => Err(position_after.error(format!(
+        "expected end of statement, found {}",
+        token.describe()
+      ))),
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn at(line: usize, column: usize) -> Position {
+    return Position { line, column };
+  }
+
+  fn error_position(source: &str) -> (usize, usize, String) {
+    return match parse(source) {
+      Err(SceneError::Invalid {
+        line,
+        column,
+        message,
+      }) => (line, column, message),
+      other => panic!("Expected a syntax error, got {:?}", other),
+    };
+  }
+
+  #[test]
+  fn parse_statements() {
+    let statements = parse("light { position -1 2.5 3e1; color 1 1 1 }\nsphere").unwrap();
+    assert_eq!(statements.len(), 2);
+    assert_eq!(statements[0].name, "light");
+    assert_eq!(statements[0].position, at(1, 1));
+    assert!(statements[0].arguments.is_empty());
+
+    let block = statements[0].block.as_ref().unwrap();
+    assert_eq!(block.len(), 2);
+    assert_eq!(block[0].name, "position");
+    let values: Vec<&Value> = block[0].arguments.iter().map(|a| &a.value).collect();
+    assert_eq!(
+      values,
+      [
+        &Value::Number(-1.),
+        &Value::Number(2.5),
+        &Value::Number(30.)
+      ]
+    );
+    assert_eq!(block[0].arguments[1].position, at(1, 21));
+    assert_eq!(block[1].position, at(1, 30));
+
+    assert_eq!(statements[1].name, "sphere");
+    assert_eq!(statements[1].position, at(2, 1));
+    assert!(statements[1].block.is_none());
+  }
+
+  #[test]
+  fn parse_words_strings_and_comments() {
+    let source = "# A comment\nimage \"floor \\\"tiles\\\".ppm\" spherical # trailing\n";
+    let statements = parse(source).unwrap();
+    assert_eq!(statements.len(), 1);
+    assert_eq!(statements[0].position, at(2, 1));
+    assert_eq!(
+      statements[0].arguments[0].value,
+      Value::Text("floor \"tiles\".ppm".to_string())
+    );
+    assert_eq!(
+      statements[0].arguments[1].value,
+      Value::Word("spherical".to_string())
+    );
+  }
+
+  #[test]
+  fn parse_multiline_blocks() {
+    let source = "material red {\n  color 1 0 0\n\n  stripes {\n  }\n}\n";
+    let statements = parse(source).unwrap();
+    let block = statements[0].block.as_ref().unwrap();
+    assert_eq!(block.len(), 2);
+    assert_eq!(block[1].block, Some(vec![]));
+  }
+
+  #[test]
+  fn parse_errors_report_position() {
+    assert_eq!(error_position("sphere {\n  scale 1 @ 2\n}").0, 2);
+    assert_eq!(error_position("sphere {\n  scale 1 @ 2\n}").1, 11);
+    assert_eq!(error_position("camera {\n  fov 90\n").0, 1);
+    assert_eq!(error_position("camera {\n  fov 90\n").1, 8);
+    assert_eq!(error_position("}").1, 1);
+    assert_eq!(error_position("scale 1x").2, "invalid number `1x`");
+    assert_eq!(
+      error_position("3 sphere").2,
+      "expected a statement, found number `3`"
+    );
+    assert_eq!(error_position("sphere { } 3").1, 12);
+    assert_eq!(error_position("image \"open").2, "unterminated string");
+  }
+}
@@ -1,5 +1,6 @@
-use crate::geometry::{Material, Matrix, Point, Ray, Vector};
-use crate::utils::EPSILON;
+use crate::geometry::{Aabb, Material, Matrix, Point, Ray, TangentFrame, Vector};
+use crate::render::ObjectHandle;
+use crate::utils::{Float, EPSILON};
 use crate::Color;
 use std::cmp::Ordering::Equal;
 
@@ -9,55 +10,116 @@ pub use sphere::*;
 mod plane;
 pub use plane::*;
 
-pub trait Shape {
+pub trait Shape: Send + Sync {
   fn material(&self) -> &Material;
+  fn material_mut(&mut self) -> &mut Material;
   fn set_material(&mut self, material: Material);
 
   fn object_to_world(&self) -> &Matrix;
+  /// Returns the inverse of the object-to-world transformation, computed when the transformation
+  /// is set, or `None` if it is singular. Rays never hit a shape with a singular transformation.
+  fn world_to_object(&self) -> Option<&Matrix>;
   fn set_object_to_world(&mut self, object_to_world: Matrix);
 
-  fn intersect_object_space(&self, ray: &Ray) -> Vec<f64>;
-  fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
-    let ray_object = self.object_to_world().inverse() * *ray;
+  /// Returns a box in object space that contains the whole shape.
+  fn bounds(&self) -> Aabb;
+  /// Returns a box in world space that contains the whole shape.
+  fn world_bounds(&self) -> Aabb {
+    return self.bounds().transform(self.object_to_world());
+  }
+
+  /// Returns the times at which a ray in object space intersects the shape, including those
+  /// outside the interval of the ray.
+  fn intersect_object_space(&self, ray: &Ray) -> Vec<Float>;
+  /// Returns whether a ray in object space intersects the shape within its interval. Shapes stop
+  /// at the first intersection inside the interval and allocate nothing, which keeps shadow rays
+  /// cheap.
+  fn intersects_object_space(&self, ray: &Ray) -> bool;
+  /// Returns the intersections within the interval of the ray.
+  fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+    let world_to_object = match self.world_to_object() {
+      Some(world_to_object) => world_to_object,
+      None => return Vec::new(),
+    };
+    let ray_object = *world_to_object * *ray;
     let intersection_times = self.intersect_object_space(&ray_object);
     return intersection_times
       .into_iter()
+      .filter(|&time| ray.contains(time))
       .map(|time| {
         let point = ray.position(time);
         let point_object = ray_object.position(time);
+        let mut normal = self.normal_at(&point);
+        if self.material().normal_map().is_some() {
+          let (frame_object, frame_world) = self.tangent_frames(&point_object, &normal);
+          normal = self
+            .material()
+            .perturb_normal(&point_object, &frame_object, &frame_world);
+        }
         return Intersection::new(
           time,
           point,
           point_object,
           -ray.direction.normalize(),
-          self.normal_at(&point),
+          normal,
           self.material(),
         );
       })
       .collect();
   }
+  /// Returns whether the shape intersects the ray within its interval, without computing the
+  /// details of the intersections.
+  fn intersects(&self, ray: &Ray) -> bool {
+    let world_to_object = match self.world_to_object() {
+      Some(world_to_object) => world_to_object,
+      None => return false,
+    };
+    return self.intersects_object_space(&(*world_to_object * *ray));
+  }
 
   fn normal_at_object_space(&self, point: &Point) -> Vector;
+  /// Returns the normal at a point on the shape, or a zero vector if the shape's transformation is
+  /// singular.
   fn normal_at(&self, point: &Point) -> Vector {
-    let point_object = self.object_to_world().inverse() * *point;
+    let world_to_object = match self.world_to_object() {
+      Some(world_to_object) => world_to_object,
+      None => return Vector::new(0., 0., 0.),
+    };
+    let point_object = *world_to_object * *point;
     let normal_object = self.normal_at_object_space(&point_object);
-    let normal_world = self.object_to_world().inverse().transpose() * normal_object;
+    let normal_world = world_to_object.transpose() * normal_object;
     return normal_world.normalize();
   }
+
+  /// Returns the surface's texture coordinates at a point in object space.
+  fn uv_at_object_space(&self, point: &Point) -> (Float, Float);
+  /// Returns the direction in which `u` increases at a point in object space.
+  fn tangent_at_object_space(&self, point: &Point) -> Vector;
+  /// Returns the tangent frames at a point, in object space and in world space.
+  ///
+  /// `normal` is the world space normal at the point.
+  fn tangent_frames(&self, point_object: &Point, normal: &Vector) -> (TangentFrame, TangentFrame) {
+    let tangent_object = self.tangent_at_object_space(point_object);
+    let frame_object = TangentFrame::new(tangent_object, self.normal_at_object_space(point_object));
+    let frame_world = TangentFrame::new(*self.object_to_world() * frame_object.tangent, *normal);
+    return (frame_object, frame_world);
+  }
 }
 
 #[derive(Debug)]
 pub struct Intersection<'a> {
-  pub time: f64,
+  pub time: Float,
   pub point: Point,
   pub point_object: Point,
   pub outgoing: Vector,
   pub normal: Vector,
   pub material: &'a Material,
+  /// Handle of the intersected object, when it belongs to a [`World`](crate::render::World).
+  pub object: Option<ObjectHandle>,
 }
 impl<'a> Intersection<'a> {
   pub fn new(
-    time: f64,
+    time: Float,
     point: Point,
     point_object: Point,
     outgoing: Vector,
@@ -71,9 +133,16 @@ impl<'a> Intersection<'a> {
       outgoing,
       normal,
       material,
+      object: None,
     };
   }
 
+  /// Sets the handle of the intersected object.
+  pub fn with_object(mut self, object: ObjectHandle) -> Self {
+    self.object = Some(object);
+    return self;
+  }
+
   /// Returns the base color at the intersection point in object space, before shading is applied.
   pub fn base_color(&self) -> Color {
     return self.material.color_at(&self.point_object);
@@ -87,10 +156,11 @@ impl<'a> Intersection<'a> {
   }
 }
 
+/// Returns the closest intersection. Intersections are already limited to the interval of their
+/// ray by [`Shape::intersect`], so every one is a hit.
 pub fn find_hit<'a>(intersections: &'a [Intersection]) -> Option<&'a Intersection<'a>> {
   return intersections
     .iter()
-    .filter(|intersection| intersection.time >= 0.)
     .min_by(|x, y| x.time.partial_cmp(&y.time).unwrap_or(Equal));
 }
 
@@ -162,7 +232,7 @@ mod tests {
     assert_eq!(intersection.point_over(), Point::new(1., 1. + EPSILON, 1.));
   }
 
-  fn test_find_hit(times: &[f64], expected_hit_time: Option<f64>) {
+  fn test_find_hit(times: &[Float], expected_hit_time: Option<Float>) {
     let material = Material::default();
     let intersections: Vec<Intersection> = times
       .iter()
@@ -193,22 +263,13 @@ mod tests {
   }
 
   #[test]
-  fn hit_when_some_negative() {
-    let times = vec![-1., 1.];
-    let expected_hit_time = Some(1.);
-    test_find_hit(&times, expected_hit_time);
-  }
-
-  #[test]
-  fn hit_when_all_negative() {
-    let times = vec![-2., -1.];
-    let expected_hit_time = None;
-    test_find_hit(&times, expected_hit_time);
+  fn hit_when_no_intersections() {
+    test_find_hit(&[], None);
   }
 
   #[test]
   fn hit_intersection_order_does_not_matter() {
-    let times = vec![5., 7., -3., 2.];
+    let times = vec![5., 7., 3., 2.];
     let expected_hit_time = Some(2.);
     test_find_hit(&times, expected_hit_time);
   }
@@ -1,24 +1,43 @@
 use super::Shape;
-use crate::geometry::{Material, Matrix, Point, Ray, Vector};
-use crate::utils::EPSILON;
+use crate::geometry::{Aabb, Material, Matrix, Point, Ray, Vector};
+use crate::textures::UvMapping;
+use crate::utils::{Float, EPSILON};
 
-#[derive(Default)]
 pub struct Plane {
   material: Material,
   object_to_world: Matrix,
+  world_to_object: Option<Matrix>,
 }
 impl Plane {
   pub fn new(material: Material, object_to_world: Matrix) -> Self {
     return Self {
       material,
       object_to_world,
+      world_to_object: object_to_world.try_inverse().ok(),
     };
   }
 }
+impl Plane {
+  /// Returns the time at which a ray in object space crosses the plane, unless it is parallel.
+  fn time(ray: &Ray) -> Option<Float> {
+    if ray.direction.y.abs() < EPSILON {
+      return None;
+    }
+    return Some(-ray.origin.y / ray.direction.y);
+  }
+}
+impl Default for Plane {
+  fn default() -> Self {
+    return Self::new(Material::default(), Matrix::identity());
+  }
+}
 impl Shape for Plane {
   fn material(&self) -> &Material {
     return &self.material;
   }
+  fn material_mut(&mut self) -> &mut Material {
+    return &mut self.material;
+  }
   fn set_material(&mut self, material: Material) {
     self.material = material;
   }
@@ -26,28 +45,46 @@ impl Shape for Plane {
   fn object_to_world(&self) -> &Matrix {
     return &self.object_to_world;
   }
+  fn world_to_object(&self) -> Option<&Matrix> {
+    return self.world_to_object.as_ref();
+  }
   fn set_object_to_world(&mut self, object_to_world: Matrix) {
     self.object_to_world = object_to_world;
+    self.world_to_object = object_to_world.try_inverse().ok();
   }
 
-  fn intersect_object_space(&self, ray: &Ray) -> Vec<f64> {
-    if ray.direction.y.abs() < EPSILON {
-      return vec![];
-    }
+  fn bounds(&self) -> Aabb {
+    return Aabb::infinite();
+  }
 
-    return vec![-ray.origin.y / ray.direction.y];
+  fn intersect_object_space(&self, ray: &Ray) -> Vec<Float> {
+    return Self::time(ray).into_iter().collect();
+  }
+  fn intersects_object_space(&self, ray: &Ray) -> bool {
+    return Self::time(ray).is_some_and(|time| ray.contains(time));
   }
 
   fn normal_at_object_space(&self, _point: &Point) -> Vector {
     return Vector::new(0., 1., 0.);
   }
+
+  fn uv_at_object_space(&self, point: &Point) -> (Float, Float) {
+    return UvMapping::Planar.map(point);
+  }
+
+  fn tangent_at_object_space(&self, _point: &Point) -> Vector {
+    return Vector::new(1., 0., 0.);
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::assert_ae;
+  use crate::geometry::NormalMap;
+  use crate::textures::{gradient, Pattern};
+  use crate::utils::consts::{PI, SQRT_2};
   use crate::Color;
-  use std::f64::consts::PI;
 
   #[test]
   fn init_new() {
@@ -58,6 +95,16 @@ mod tests {
     assert_eq!(plane.object_to_world(), &scaling);
   }
 
+  #[test]
+  fn bounds_are_infinite() {
+    let plane = Plane::new(
+      Material::default(),
+      Matrix::identity().rotate_x(PI / 4.).translate(0., 1., 0.),
+    );
+    assert_eq!(plane.bounds(), Aabb::infinite());
+    assert_eq!(plane.world_bounds(), Aabb::infinite());
+  }
+
   #[test]
   fn init_default() {
     let plane = Plane::default();
@@ -118,6 +165,17 @@ mod tests {
     assert_eq!(intersection[0].time, 1.);
   }
 
+  #[test]
+  fn intersects_within_interval() {
+    let plane = Plane::default();
+    let ray = Ray::new(Point::new(0., 2., 0.), Vector::new(0., -1., 0.));
+    assert!(plane.intersects(&ray));
+    assert!(plane.intersects(&ray.with_interval(0., 2.)));
+    assert!(!plane.intersects(&ray.with_interval(0., 1.5)));
+    let parallel = Ray::new(Point::new(0., 2., 0.), Vector::new(1., 0., 0.));
+    assert!(!plane.intersects(&parallel));
+  }
+
   #[test]
   fn intersect_transformed_plane() {
     let plane = Plane::new(
@@ -127,13 +185,13 @@ mod tests {
     let ray = Ray::new(Point::origin(), Vector::new(0., 3., 1.));
     let intersection = plane.intersect(&ray);
     assert_eq!(intersection.len(), 1);
-    assert_eq!(intersection[0].time, 5.);
+    assert_ae!(intersection[0].time, 5.);
   }
 
   #[test]
   fn intersection_has_intersection_point() {
     let plane = Plane::default();
-    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., 1., 0.));
+    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., -1., 0.));
     let intersection = plane.intersect(&ray);
     assert_eq!(intersection.len(), 1);
     assert_eq!(intersection[0].point, Point::new(1., 0., 3.));
@@ -142,16 +200,16 @@ mod tests {
   #[test]
   fn intersection_has_outgoing_vector() {
     let plane = Plane::default();
-    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., 1., 0.));
+    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., -1., 0.));
     let intersection = plane.intersect(&ray);
     assert_eq!(intersection.len(), 1);
-    assert_eq!(intersection[0].outgoing, Vector::new(0., -1., 0.));
+    assert_eq!(intersection[0].outgoing, Vector::new(0., 1., 0.));
   }
 
   #[test]
   fn intersection_outgoing_vector_is_normalized() {
     let plane = Plane::default();
-    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., 1., 0.));
+    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., -1., 0.));
     let intersection = plane.intersect(&ray);
     assert_eq!(intersection.len(), 1);
     assert_eq!(
@@ -163,7 +221,7 @@ mod tests {
   #[test]
   fn intersection_has_plane_material() {
     let plane = Plane::default();
-    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., 1., 0.));
+    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(0., -1., 0.));
     let intersection = plane.intersect(&ray);
 
     assert_eq!(intersection.len(), 1);
@@ -176,12 +234,46 @@ mod tests {
   #[test]
   fn intersection_has_normal_at_intersection() {
     let plane = Plane::default();
-    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(1., 1., 0.));
+    let ray = Ray::new(Point::new(1., 5., 3.), Vector::new(1., -1., 0.));
     let intersection = plane.intersect(&ray);
     assert_eq!(intersection.len(), 1);
     assert_eq!(intersection[0].normal, Vector::new(0., 1., 0.));
   }
 
+  #[test]
+  fn uv() {
+    let plane = Plane::default();
+    assert_eq!(
+      plane.uv_at_object_space(&Point::new(0.25, 0., -3.)),
+      (0.25, -3.)
+    );
+  }
+
+  #[test]
+  fn tangent_frames() {
+    let plane = Plane::default();
+    let (frame_object, frame_world) =
+      plane.tangent_frames(&Point::origin(), &Vector::new(0., 1., 0.));
+    assert_eq!(frame_object, frame_world);
+    assert_eq!(frame_world.tangent, Vector::new(1., 0., 0.));
+    assert_eq!(frame_world.bitangent, Vector::new(0., 0., 1.));
+  }
+
+  #[test]
+  fn intersection_normal_uses_bump_map() {
+    let material = Material::default().with_normal_map(NormalMap::Bump {
+      height: Box::new(Pattern::with_fn(gradient)),
+      strength: 1.,
+    });
+    let plane = Plane::new(material, Matrix::identity());
+    let ray = Ray::new(Point::new(0.5, 1., 0.5), Vector::new(0., -1., 0.));
+    let intersection = plane.intersect(&ray);
+    assert_eq!(
+      intersection[0].normal,
+      Vector::new(SQRT_2 / 2., SQRT_2 / 2., 0.)
+    );
+  }
+
   #[test]
   fn normal() {
     let plane = Plane::default();
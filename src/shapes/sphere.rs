@@ -1,24 +1,47 @@
 use super::Shape;
-use crate::geometry::{Material, Matrix, Point, Ray, Vector};
-use crate::utils::quadratic;
+use crate::geometry::{Aabb, Material, Matrix, Point, Ray, Vector};
+use crate::textures::UvMapping;
+use crate::utils::{quadratic, Float};
 
-#[derive(Default)]
 pub struct Sphere {
   material: Material,
   object_to_world: Matrix,
+  world_to_object: Option<Matrix>,
 }
 impl Sphere {
   pub fn new(material: Material, object_to_world: Matrix) -> Self {
     return Self {
       material,
       object_to_world,
+      world_to_object: object_to_world.try_inverse().ok(),
     };
   }
 }
+impl Sphere {
+  /// Returns the times at which a ray in object space enters and leaves the sphere.
+  fn roots(ray: &Ray) -> Option<(Float, Float)> {
+    let sphere_to_ray = ray.origin - Point::origin();
+    let direction = ray.direction;
+
+    let a = direction.dot(&direction);
+    let b = 2. * direction.dot(&sphere_to_ray);
+    let c = sphere_to_ray.dot(&sphere_to_ray) - 1.;
+
+    return quadratic(a, b, c);
+  }
+}
+impl Default for Sphere {
+  fn default() -> Self {
+    return Self::new(Material::default(), Matrix::identity());
+  }
+}
 impl Shape for Sphere {
   fn material(&self) -> &Material {
     return &self.material;
   }
+  fn material_mut(&mut self) -> &mut Material {
+    return &mut self.material;
+  }
   fn set_material(&mut self, material: Material) {
     self.material = material;
   }
@@ -26,36 +49,51 @@ impl Shape for Sphere {
   fn object_to_world(&self) -> &Matrix {
     return &self.object_to_world;
   }
+  fn world_to_object(&self) -> Option<&Matrix> {
+    return self.world_to_object.as_ref();
+  }
   fn set_object_to_world(&mut self, object_to_world: Matrix) {
     self.object_to_world = object_to_world;
+    self.world_to_object = object_to_world.try_inverse().ok();
   }
 
-  fn intersect_object_space(&self, ray: &Ray) -> Vec<f64> {
-    let sphere_to_ray = ray.origin - Point::origin();
-    let direction = ray.direction;
-
-    let a = direction.dot(&direction);
-    let b = 2. * direction.dot(&sphere_to_ray);
-    let c = sphere_to_ray.dot(&sphere_to_ray) - 1.;
+  fn bounds(&self) -> Aabb {
+    return Aabb::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.));
+  }
 
-    let intersection_times = quadratic(a, b, c);
-    return match intersection_times {
+  fn intersect_object_space(&self, ray: &Ray) -> Vec<Float> {
+    return match Self::roots(ray) {
       Some((t1, t2)) => vec![t1, t2],
       None => vec![],
     };
   }
+  fn intersects_object_space(&self, ray: &Ray) -> bool {
+    return match Self::roots(ray) {
+      // The far root only matters when the near one is outside the interval.
+      Some((t1, t2)) => ray.contains(t1) || ray.contains(t2),
+      None => false,
+    };
+  }
 
   fn normal_at_object_space(&self, point: &Point) -> Vector {
     return *point - Point::origin();
   }
+
+  fn uv_at_object_space(&self, point: &Point) -> (Float, Float) {
+    return UvMapping::Spherical.map(point);
+  }
+
+  fn tangent_at_object_space(&self, point: &Point) -> Vector {
+    return Vector::new(-point.z, 0., point.x);
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::geometry::{Point, Vector};
+  use crate::geometry::{NormalMap, Point, Vector};
+  use crate::utils::consts::{PI, SQRT_2};
   use crate::Color;
-  use std::f64::consts::{PI, SQRT_2};
 
   #[test]
   fn init_new() {
@@ -79,6 +117,20 @@ mod tests {
     assert_eq!(sphere.object_to_world(), &Matrix::identity());
   }
 
+  #[test]
+  fn bounds() {
+    let sphere = Sphere::new(
+      Material::default(),
+      Matrix::identity().scale(2., 1., 1.).translate(0., 3., 0.),
+    );
+    let unit = Aabb::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.));
+    assert_eq!(sphere.bounds(), unit);
+    assert_eq!(
+      sphere.world_bounds(),
+      Aabb::new(Point::new(-2., 2., -1.), Point::new(2., 4., 1.))
+    );
+  }
+
   #[test]
   fn get_set_material() {
     let mut sphere = Sphere::default();
@@ -93,6 +145,20 @@ mod tests {
     let translation = Matrix::identity().translate(5., 4., 3.);
     sphere.set_object_to_world(translation);
     assert_eq!(sphere.object_to_world(), &translation);
+    assert_eq!(sphere.world_to_object(), Some(&translation.inverse()));
+  }
+
+  #[test]
+  fn singular_transform_is_never_hit() {
+    let sphere = Sphere::new(Material::default(), Matrix::identity().scale(1., 0., 1.));
+    assert_eq!(sphere.world_to_object(), None);
+    let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+    assert!(sphere.intersect(&ray).is_empty());
+    assert!(!sphere.intersects(&ray));
+    assert_eq!(
+      sphere.normal_at(&Point::new(1., 0., 0.)),
+      Vector::new(0., 0., 0.)
+    );
   }
 
   #[test]
@@ -199,9 +265,8 @@ mod tests {
     let ray = Ray::new(Point::origin(), Vector::new(0., 0., 1.));
     let intersections = sphere.intersect(&ray);
 
-    assert_eq!(intersections.len(), 2);
-    assert_eq!(intersections[0].time, -1.);
-    assert_eq!(intersections[1].time, 1.);
+    assert_eq!(intersections.len(), 1);
+    assert_eq!(intersections[0].time, 1.);
   }
 
   #[test]
@@ -210,9 +275,27 @@ mod tests {
     let ray = Ray::new(Point::new(0., 0., 5.), Vector::new(0., 0., 1.));
     let intersections = sphere.intersect(&ray);
 
-    assert_eq!(intersections.len(), 2);
-    assert_eq!(intersections[0].time, -6.);
-    assert_eq!(intersections[1].time, -4.);
+    assert_eq!(intersections.len(), 0);
+    assert!(!sphere.intersects(&ray));
+  }
+
+  #[test]
+  fn intersection_within_ray_interval() {
+    let sphere = Sphere::default();
+    let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+    let times = |ray: Ray| -> Vec<Float> {
+      return sphere.intersect(&ray).iter().map(|hit| hit.time).collect();
+    };
+    assert_eq!(times(ray.with_interval(5., 10.)), vec![6.]);
+    assert_eq!(times(ray.with_interval(0., 4.)), vec![4.]);
+    assert_eq!(times(ray.with_interval(4.5, 5.5)), vec![]);
+    assert_eq!(
+      times(ray.with_interval(Float::NEG_INFINITY, Float::INFINITY)),
+      vec![4., 6.]
+    );
+    assert!(sphere.intersects(&ray.with_interval(0., 4.)));
+    assert!(sphere.intersects(&ray.with_interval(5., 10.)));
+    assert!(!sphere.intersects(&ray.with_interval(4.5, 5.5)));
   }
 
   #[test]
@@ -237,6 +320,52 @@ mod tests {
     assert_eq!(intersections.len(), 0);
   }
 
+  #[test]
+  fn uv() {
+    let sphere = Sphere::default();
+    assert_eq!(
+      sphere.uv_at_object_space(&Point::new(0., 0., -1.)),
+      (0., 0.5)
+    );
+    assert_eq!(
+      sphere.uv_at_object_space(&Point::new(0., 1., 0.)),
+      (0.5, 1.)
+    );
+  }
+
+  #[test]
+  fn tangent_points_towards_increasing_u() {
+    let sphere = Sphere::default();
+    let point = Point::new(0., 0., -1.);
+    let (u, _) = sphere.uv_at_object_space(&point);
+    let tangent = sphere.tangent_at_object_space(&point);
+    assert_eq!(tangent, Vector::new(1., 0., 0.));
+    let (next_u, _) = sphere.uv_at_object_space(&(point + tangent * 0.01));
+    assert!(next_u > u);
+  }
+
+  #[test]
+  fn tangent_frames_follow_transformation() {
+    let sphere = Sphere::new(Material::default(), Matrix::identity().rotate_y(PI / 2.));
+    let point = Point::new(-1., 0., 0.);
+    let normal = sphere.normal_at(&point);
+    let (frame_object, frame_world) = sphere.tangent_frames(&Point::new(0., 0., -1.), &normal);
+    assert_eq!(frame_object.tangent, Vector::new(1., 0., 0.));
+    assert_eq!(frame_world.normal, Vector::new(-1., 0., 0.));
+    assert_eq!(frame_world.tangent, Vector::new(0., 0., -1.));
+    assert_eq!(frame_world.bitangent, Vector::new(0., 1., 0.));
+  }
+
+  #[test]
+  fn intersection_normal_uses_normal_map() {
+    let material =
+      Material::default().with_normal_map(NormalMap::Tangent(Box::new(Color::new(1., 0.5, 0.5))));
+    let sphere = Sphere::new(material, Matrix::identity());
+    let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+    let intersections = sphere.intersect(&ray);
+    assert_eq!(intersections[0].normal, Vector::new(1., 0., 0.));
+  }
+
   #[test]
   fn normal_at_x_axis() {
     let sphere = Sphere::default();
@@ -262,13 +391,17 @@ mod tests {
   fn normal_at_non_axial_point() {
     let sphere = Sphere::default();
     let normal = sphere.normal_at(&Point::new(
-      3_f64.sqrt() / 3.,
-      3_f64.sqrt() / 3.,
-      3_f64.sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
     ));
     assert_eq!(
       normal,
-      Vector::new(3_f64.sqrt() / 3., 3_f64.sqrt() / 3., 3_f64.sqrt() / 3.)
+      Vector::new(
+        (3. as Float).sqrt() / 3.,
+        (3. as Float).sqrt() / 3.,
+        (3. as Float).sqrt() / 3.
+      )
     );
   }
 
@@ -276,9 +409,9 @@ mod tests {
   fn normal_is_normalized() {
     let sphere = Sphere::default();
     let normal = sphere.normal_at(&Point::new(
-      3_f64.sqrt() / 3.,
-      3_f64.sqrt() / 3.,
-      3_f64.sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
+      (3. as Float).sqrt() / 3.,
     ));
     assert_eq!(normal.normalize(), normal);
   }
@@ -0,0 +1,122 @@
+//! Kernels for the hot paths of geometry: dot and cross products, 4×4 matrix multiplication and
+//! inversion, and ray intersection with four bounding boxes at once.
+//!
+//! The functions at the root of the module are the fastest implementation for the target. On
+//! x86_64 these are vectorized with `std::arch`: [`sse2`] in double precision and [`sse`] in
+//! single precision. Everywhere else they are the portable versions in [`scalar`]. All give
+//! bit-identical results, and the `no-simd` feature forces the portable versions, for example to
+//! compare their speed with `cargo bench`.
+
+use crate::utils::Float;
+
+pub mod scalar;
+#[cfg(all(target_arch = "x86_64", feature = "f32", not(feature = "no-simd")))]
+pub mod sse;
+#[cfg(all(target_arch = "x86_64", not(feature = "f32"), not(feature = "no-simd")))]
+pub mod sse2;
+
+#[cfg(not(all(target_arch = "x86_64", not(feature = "no-simd"))))]
+pub use scalar::{cross3, dot3, intersect_boxes4, mat4_inverse, mat4_mul};
+#[cfg(all(target_arch = "x86_64", feature = "f32", not(feature = "no-simd")))]
+pub use sse::{cross3, dot3, intersect_boxes4, mat4_inverse, mat4_mul};
+#[cfg(all(target_arch = "x86_64", not(feature = "f32"), not(feature = "no-simd")))]
+pub use sse2::{cross3, dot3, intersect_boxes4, mat4_inverse, mat4_mul};
+
+/// Returns whether the kernels at the root of the module are vectorized.
+pub const VECTORIZED: bool = cfg!(all(target_arch = "x86_64", not(feature = "no-simd")));
+
+const IDENTITY: [[Float; 4]; 4] = [
+  [1., 0., 0., 0.],
+  [0., 1., 0., 0.],
+  [0., 0., 1., 0.],
+  [0., 0., 0., 1.],
+];
+
+/// Returns the size below which a pivot of `matrix` is rounding error of a zero.
+fn pivot_threshold(matrix: &[[Float; 4]; 4]) -> Float {
+  let largest = matrix
+    .iter()
+    .flatten()
+    .fold(0., |largest: Float, element| largest.max(element.abs()));
+  return 4. * Float::EPSILON * largest;
+}
+
+/// Returns the row at or below `start` with the largest element of `column`. Dividing by it keeps
+/// rounding error small.
+fn pivot_row(column: [Float; 4], start: usize) -> usize {
+  return (start..4)
+    .max_by(|&a, &b| column[a].abs().total_cmp(&column[b].abs()))
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::geometry::{Aabb, Boxes4, Point};
+
+  const A: [Float; 3] = [1.5, -2.25, 3.];
+  const B: [Float; 3] = [-0.1, 7., 0.3];
+  const MATRIX: [[Float; 4]; 4] = [
+    [-5., 2., 6., -8.],
+    [1., -5., 1., 8.],
+    [7., 7., -6., -7.],
+    [1., -3., 7., 4.],
+  ];
+
+  #[test]
+  fn products_match_scalar() {
+    assert_eq!(dot3(A, B), scalar::dot3(A, B));
+    assert_eq!(cross3(A, B), scalar::cross3(A, B));
+  }
+
+  #[test]
+  fn matrices_match_scalar() {
+    let other = [
+      [0.1, 0.2, 0.3, 0.4],
+      [-1., 2., -3., 4.],
+      [5.5, 0., 0., 1.],
+      [0., 0., 0., 1.],
+    ];
+    assert_eq!(mat4_mul(&MATRIX, &other), scalar::mat4_mul(&MATRIX, &other));
+    assert_eq!(mat4_inverse(&MATRIX), scalar::mat4_inverse(&MATRIX));
+    assert_eq!(mat4_inverse(&other), scalar::mat4_inverse(&other));
+  }
+
+  #[test]
+  fn singular_matrix_has_no_inverse() {
+    let mut singular = MATRIX;
+    singular[3] = singular[0];
+    assert_eq!(mat4_inverse(&singular), None);
+    assert_eq!(scalar::mat4_inverse(&singular), None);
+  }
+
+  #[test]
+  fn boxes_match_scalar() {
+    let mut boxes = Boxes4::EMPTY;
+    boxes.set(
+      0,
+      &Aabb::new(Point::new(-1., -1., 4.), Point::new(1., 1., 6.)),
+    );
+    boxes.set(
+      1,
+      &Aabb::new(Point::new(2., 2., 4.), Point::new(3., 3., 6.)),
+    );
+    boxes.set(
+      2,
+      &Aabb::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.)),
+    );
+    let rays = [
+      ([0., 0., 0.], [Float::INFINITY, Float::INFINITY, 1.]),
+      ([0.5, -3., 2.], [2., 0.5, -1.]),
+      ([2.5, 2.5, -9.], [-Float::INFINITY, Float::INFINITY, 0.25]),
+    ];
+    for (origin, inverse_direction) in rays {
+      for (t_min, t_max) in [(0., Float::INFINITY), (0.5, 5.), (4.5, 4.75)] {
+        assert_eq!(
+          intersect_boxes4(&origin, &inverse_direction, &boxes, t_min, t_max),
+          scalar::intersect_boxes4(&origin, &inverse_direction, &boxes, t_min, t_max)
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,98 @@
+//! Portable implementations of the kernels, used where no vectorized version exists. They round
+//! exactly like the vectorized versions, so renders do not depend on the platform.
+
+use crate::geometry::Boxes4;
+use crate::utils::Float;
+
+#[inline]
+pub fn dot3(a: [Float; 3], b: [Float; 3]) -> Float {
+  return a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+}
+
+#[inline]
+pub fn cross3(a: [Float; 3], b: [Float; 3]) -> [Float; 3] {
+  return [
+    a[1] * b[2] - a[2] * b[1],
+    a[2] * b[0] - a[0] * b[2],
+    a[0] * b[1] - a[1] * b[0],
+  ];
+}
+
+#[inline]
+pub fn mat4_mul(a: &[[Float; 4]; 4], b: &[[Float; 4]; 4]) -> [[Float; 4]; 4] {
+  let mut product = [[0.; 4]; 4];
+  for (product_row, a_row) in product.iter_mut().zip(a) {
+    for (col, element) in product_row.iter_mut().enumerate() {
+      *element =
+        a_row[0] * b[0][col] + a_row[1] * b[1][col] + a_row[2] * b[2][col] + a_row[3] * b[3][col];
+    }
+  }
+  return product;
+}
+
+#[inline]
+pub fn mat4_inverse(matrix: &[[Float; 4]; 4]) -> Option<[[Float; 4]; 4]> {
+  let threshold = super::pivot_threshold(matrix);
+  let mut copy = *matrix;
+  let mut inverse = super::IDENTITY;
+  for i in 0..4 {
+    let pivot_row = super::pivot_row([copy[0][i], copy[1][i], copy[2][i], copy[3][i]], i);
+    if copy[pivot_row][i].abs() <= threshold {
+      return None;
+    }
+    copy.swap(i, pivot_row);
+    inverse.swap(i, pivot_row);
+
+    let pivot = copy[i][i];
+    for k in 0..4 {
+      copy[i][k] /= pivot;
+      inverse[i][k] /= pivot;
+    }
+
+    for j in 0..4 {
+      let factor = copy[j][i];
+      if j != i && factor != 0. {
+        for k in 0..4 {
+          copy[j][k] -= factor * copy[i][k];
+          inverse[j][k] -= factor * inverse[i][k];
+        }
+      }
+    }
+  }
+  return Some(inverse);
+}
+
+/// Minimum like `minpd` and `minps`, which return the second operand if either is NaN.
+#[inline]
+fn min(a: Float, b: Float) -> Float {
+  return if a < b { a } else { b };
+}
+/// Maximum like `maxpd` and `maxps`, which return the second operand if either is NaN.
+#[inline]
+fn max(a: Float, b: Float) -> Float {
+  return if a > b { a } else { b };
+}
+
+#[inline]
+pub fn intersect_boxes4(
+  origin: &[Float; 3],
+  inverse_direction: &[Float; 3],
+  boxes: &Boxes4,
+  t_min: Float,
+  t_max: Float,
+) -> [Float; 4] {
+  let mut entries = [Float::INFINITY; 4];
+  for (index, entry) in entries.iter_mut().enumerate() {
+    let (mut near, mut far) = (t_min, t_max);
+    for axis in 0..3 {
+      let t1 = (boxes.min[axis][index] - origin[axis]) * inverse_direction[axis];
+      let t2 = (boxes.max[axis][index] - origin[axis]) * inverse_direction[axis];
+      near = max(min(t1, t2), near);
+      far = min(max(t1, t2), far);
+    }
+    if near <= far {
+      *entry = near;
+    }
+  }
+  return entries;
+}
@@ -0,0 +1,161 @@
+//! Kernels vectorized with SSE, four `f32` lanes at a time. SSE is part of every x86_64
+//! processor, so no detection at runtime is needed.
+//!
+//! A row of a matrix, or one bound of four boxes, fills a whole register. Operations are done in
+//! the same order as in [`scalar`](super::scalar), without fused multiply-add, so results are
+//! identical.
+
+use crate::geometry::Boxes4;
+use std::arch::x86_64::*;
+
+/// Returns the lanes of a register.
+///
+/// SAFETY: SSE must be available.
+#[inline(always)]
+unsafe fn lanes(register: __m128) -> [f32; 4] {
+  let mut lanes = [0.; 4];
+  _mm_storeu_ps(lanes.as_mut_ptr(), register);
+  return lanes;
+}
+
+#[inline]
+pub fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+  // SAFETY: SSE is available on every x86_64 processor.
+  unsafe {
+    // `_mm_set_ps` takes the highest lane first.
+    let products = _mm_mul_ps(
+      _mm_set_ps(0., a[2], a[1], a[0]),
+      _mm_set_ps(0., b[2], b[1], b[0]),
+    );
+    let sum = _mm_add_ss(products, _mm_shuffle_ps::<0b01>(products, products));
+    let sum = _mm_add_ss(sum, _mm_movehl_ps(products, products));
+    return _mm_cvtss_f32(sum);
+  }
+}
+
+#[inline]
+pub fn cross3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+  // SAFETY: SSE is available on every x86_64 processor.
+  unsafe {
+    let left = _mm_mul_ps(
+      _mm_set_ps(0., a[0], a[2], a[1]),
+      _mm_set_ps(0., b[1], b[0], b[2]),
+    );
+    let right = _mm_mul_ps(
+      _mm_set_ps(0., a[1], a[0], a[2]),
+      _mm_set_ps(0., b[0], b[2], b[1]),
+    );
+    let [x, y, z, _] = lanes(_mm_sub_ps(left, right));
+    return [x, y, z];
+  }
+}
+
+#[inline]
+pub fn mat4_mul(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+  let mut product = [[0.; 4]; 4];
+  // SAFETY: SSE is available on every x86_64 processor, and every load and store accesses a
+  // four-element row.
+  unsafe {
+    let b_rows = b.map(|row| _mm_loadu_ps(row.as_ptr()));
+    for (product_row, a_row) in product.iter_mut().zip(a) {
+      let mut sum = _mm_mul_ps(_mm_set1_ps(a_row[0]), b_rows[0]);
+      for k in 1..4 {
+        sum = _mm_add_ps(sum, _mm_mul_ps(_mm_set1_ps(a_row[k]), b_rows[k]));
+      }
+      _mm_storeu_ps(product_row.as_mut_ptr(), sum);
+    }
+  }
+  return product;
+}
+
+/// Row of a matrix followed by the same row of its inverse being built.
+type Row = [__m128; 2];
+
+/// Divides the pivot row of column `i` by its pivot and subtracts it from the other rows. Returns
+/// false if the pivot is a zero.
+///
+/// SAFETY: SSE must be available.
+#[inline(always)]
+unsafe fn eliminate(rows: &mut [Row; 4], i: usize, threshold: f32) -> bool {
+  let column = [0, 1, 2, 3].map(|j| lanes(rows[j][0])[i]);
+  let pivot_row = super::pivot_row(column, i);
+  if column[pivot_row].abs() <= threshold {
+    return false;
+  }
+  rows.swap(i, pivot_row);
+
+  let pivot = _mm_set1_ps(column[pivot_row]);
+  for half in rows[i].iter_mut() {
+    *half = _mm_div_ps(*half, pivot);
+  }
+  let row = rows[i];
+
+  for (j, other) in rows.iter_mut().enumerate() {
+    let factor = lanes(other[0])[i];
+    if j != i && factor != 0. {
+      let factor = _mm_set1_ps(factor);
+      for (half, row_half) in other.iter_mut().zip(row) {
+        *half = _mm_sub_ps(*half, _mm_mul_ps(factor, row_half));
+      }
+    }
+  }
+  return true;
+}
+
+#[inline]
+pub fn mat4_inverse(matrix: &[[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+  let threshold = super::pivot_threshold(matrix);
+  let mut inverse = [[0.; 4]; 4];
+  // SAFETY: SSE is available on every x86_64 processor, and every load and store accesses a
+  // four-element row.
+  unsafe {
+    // The rows stay in registers during the elimination.
+    let mut rows: [Row; 4] = [0, 1, 2, 3].map(|j| {
+      return [
+        _mm_loadu_ps(matrix[j].as_ptr()),
+        _mm_loadu_ps(super::IDENTITY[j].as_ptr()),
+      ];
+    });
+    // Steps with constant columns let the compiler pick lanes without going through memory.
+    let invertible = eliminate(&mut rows, 0, threshold)
+      && eliminate(&mut rows, 1, threshold)
+      && eliminate(&mut rows, 2, threshold)
+      && eliminate(&mut rows, 3, threshold);
+    if !invertible {
+      return None;
+    }
+    for (inverse_row, row) in inverse.iter_mut().zip(&rows) {
+      _mm_storeu_ps(inverse_row.as_mut_ptr(), row[1]);
+    }
+  }
+  return Some(inverse);
+}
+
+#[inline]
+pub fn intersect_boxes4(
+  origin: &[f32; 3],
+  inverse_direction: &[f32; 3],
+  boxes: &Boxes4,
+  t_min: f32,
+  t_max: f32,
+) -> [f32; 4] {
+  // SAFETY: SSE is available on every x86_64 processor, and every load accesses a four-element
+  // array.
+  unsafe {
+    let mut near = _mm_set1_ps(t_min);
+    let mut far = _mm_set1_ps(t_max);
+    for axis in 0..3 {
+      let origin = _mm_set1_ps(origin[axis]);
+      let inverse_direction = _mm_set1_ps(inverse_direction[axis]);
+      let min = _mm_loadu_ps(boxes.min[axis].as_ptr());
+      let max = _mm_loadu_ps(boxes.max[axis].as_ptr());
+      let t1 = _mm_mul_ps(_mm_sub_ps(min, origin), inverse_direction);
+      let t2 = _mm_mul_ps(_mm_sub_ps(max, origin), inverse_direction);
+      near = _mm_max_ps(_mm_min_ps(t1, t2), near);
+      far = _mm_min_ps(_mm_max_ps(t1, t2), far);
+    }
+    let hit = _mm_cmple_ps(near, far);
+    let missed = _mm_andnot_ps(hit, _mm_set1_ps(f32::INFINITY));
+    return lanes(_mm_or_ps(_mm_and_ps(hit, near), missed));
+  }
+}
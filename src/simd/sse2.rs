@@ -0,0 +1,194 @@
+//! Kernels vectorized with SSE2, two `f64` lanes at a time. SSE2 is part of every x86_64
+//! processor, so no detection at runtime is needed.
+//!
+//! Operations are done in the same order as in [`scalar`](super::scalar), without fused
+//! multiply-add, so results are identical.
+
+use crate::geometry::Boxes4;
+use std::arch::x86_64::*;
+
+#[inline]
+pub fn dot3(a: [f64; 3], b: [f64; 3]) -> f64 {
+  // SAFETY: SSE2 is available on every x86_64 processor, and the loads read two elements of
+  // three-element arrays.
+  unsafe {
+    let products = _mm_mul_pd(_mm_loadu_pd(a.as_ptr()), _mm_loadu_pd(b.as_ptr()));
+    let sum = _mm_add_sd(products, _mm_unpackhi_pd(products, products));
+    return _mm_cvtsd_f64(sum) + a[2] * b[2];
+  }
+}
+
+#[inline]
+pub fn cross3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+  let mut cross = [0.; 3];
+  // SAFETY: SSE2 is available on every x86_64 processor, and the store writes the first two
+  // elements of a three-element array.
+  unsafe {
+    // `_mm_set_pd` takes the high lane first.
+    let left = _mm_mul_pd(_mm_set_pd(a[2], a[1]), _mm_set_pd(b[0], b[2]));
+    let right = _mm_mul_pd(_mm_set_pd(a[0], a[2]), _mm_set_pd(b[2], b[1]));
+    _mm_storeu_pd(cross.as_mut_ptr(), _mm_sub_pd(left, right));
+  }
+  cross[2] = a[0] * b[1] - a[1] * b[0];
+  return cross;
+}
+
+#[inline]
+pub fn mat4_mul(a: &[[f64; 4]; 4], b: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+  let mut product = [[0.; 4]; 4];
+  // SAFETY: SSE2 is available on every x86_64 processor, and every load and store accesses two
+  // elements of a four-element row.
+  unsafe {
+    let b_rows = [0, 1, 2, 3].map(|k| {
+      return (
+        _mm_loadu_pd(b[k].as_ptr()),
+        _mm_loadu_pd(b[k].as_ptr().add(2)),
+      );
+    });
+    for (product_row, a_row) in product.iter_mut().zip(a) {
+      let factor = _mm_set1_pd(a_row[0]);
+      let mut low = _mm_mul_pd(factor, b_rows[0].0);
+      let mut high = _mm_mul_pd(factor, b_rows[0].1);
+      for k in 1..4 {
+        let factor = _mm_set1_pd(a_row[k]);
+        low = _mm_add_pd(low, _mm_mul_pd(factor, b_rows[k].0));
+        high = _mm_add_pd(high, _mm_mul_pd(factor, b_rows[k].1));
+      }
+      _mm_storeu_pd(product_row.as_mut_ptr(), low);
+      _mm_storeu_pd(product_row.as_mut_ptr().add(2), high);
+    }
+  }
+  return product;
+}
+
+/// Row of a matrix followed by the same row of its inverse being built, as four pairs of lanes.
+type Row = [__m128d; 4];
+
+/// Returns element `index` of a row.
+///
+/// SAFETY: SSE2 must be available.
+#[inline]
+unsafe fn element(row: &Row, index: usize) -> f64 {
+  let pair = row[index / 2];
+  return _mm_cvtsd_f64(if index & 1 == 0 {
+    pair
+  } else {
+    _mm_unpackhi_pd(pair, pair)
+  });
+}
+
+/// Divides the pivot row of column `i` by its pivot and subtracts it from the other rows. Returns
+/// false if the pivot is a zero.
+///
+/// SAFETY: SSE2 must be available.
+#[inline(always)]
+unsafe fn eliminate(rows: &mut [Row; 4], i: usize, threshold: f64) -> bool {
+  let column = [0, 1, 2, 3].map(|j| element(&rows[j], i));
+  let pivot_row = super::pivot_row(column, i);
+  if column[pivot_row].abs() <= threshold {
+    return false;
+  }
+  rows.swap(i, pivot_row);
+
+  let pivot = _mm_set1_pd(column[pivot_row]);
+  for lanes in rows[i].iter_mut() {
+    *lanes = _mm_div_pd(*lanes, pivot);
+  }
+  let row = rows[i];
+
+  for (j, other) in rows.iter_mut().enumerate() {
+    let factor = element(other, i);
+    if j != i && factor != 0. {
+      let factor = _mm_set1_pd(factor);
+      for (lanes, row_lanes) in other.iter_mut().zip(row) {
+        *lanes = _mm_sub_pd(*lanes, _mm_mul_pd(factor, row_lanes));
+      }
+    }
+  }
+  return true;
+}
+
+#[inline]
+pub fn mat4_inverse(matrix: &[[f64; 4]; 4]) -> Option<[[f64; 4]; 4]> {
+  let threshold = super::pivot_threshold(matrix);
+  let mut inverse = [[0.; 4]; 4];
+  // SAFETY: SSE2 is available on every x86_64 processor, and every load and store accesses two
+  // elements of a four-element row.
+  unsafe {
+    // The rows stay in registers during the elimination.
+    let mut rows: [Row; 4] = [0, 1, 2, 3].map(|j| {
+      let identity = super::IDENTITY[j].as_ptr();
+      return [
+        _mm_loadu_pd(matrix[j].as_ptr()),
+        _mm_loadu_pd(matrix[j].as_ptr().add(2)),
+        _mm_loadu_pd(identity),
+        _mm_loadu_pd(identity.add(2)),
+      ];
+    });
+    // Steps with constant columns let the compiler pick lanes without going through memory.
+    let invertible = eliminate(&mut rows, 0, threshold)
+      && eliminate(&mut rows, 1, threshold)
+      && eliminate(&mut rows, 2, threshold)
+      && eliminate(&mut rows, 3, threshold);
+    if !invertible {
+      return None;
+    }
+    for (inverse_row, row) in inverse.iter_mut().zip(&rows) {
+      _mm_storeu_pd(inverse_row.as_mut_ptr(), row[2]);
+      _mm_storeu_pd(inverse_row.as_mut_ptr().add(2), row[3]);
+    }
+  }
+  return Some(inverse);
+}
+
+/// Returns the time at which a ray enters boxes `2 * half` and `2 * half + 1`, or infinity where
+/// it misses them.
+///
+/// SAFETY: SSE2 must be available, and `half` must be 0 or 1.
+#[inline(always)]
+unsafe fn intersect_boxes2(
+  origin: &[f64; 3],
+  inverse_direction: &[f64; 3],
+  boxes: &Boxes4,
+  half: usize,
+  t_min: f64,
+  t_max: f64,
+) -> __m128d {
+  let mut near = _mm_set1_pd(t_min);
+  let mut far = _mm_set1_pd(t_max);
+  for axis in 0..3 {
+    let origin = _mm_set1_pd(origin[axis]);
+    let inverse_direction = _mm_set1_pd(inverse_direction[axis]);
+    let min = _mm_loadu_pd(boxes.min[axis].as_ptr().add(2 * half));
+    let max = _mm_loadu_pd(boxes.max[axis].as_ptr().add(2 * half));
+    let t1 = _mm_mul_pd(_mm_sub_pd(min, origin), inverse_direction);
+    let t2 = _mm_mul_pd(_mm_sub_pd(max, origin), inverse_direction);
+    near = _mm_max_pd(_mm_min_pd(t1, t2), near);
+    far = _mm_min_pd(_mm_max_pd(t1, t2), far);
+  }
+  let hit = _mm_cmple_pd(near, far);
+  return _mm_or_pd(
+    _mm_and_pd(hit, near),
+    _mm_andnot_pd(hit, _mm_set1_pd(f64::INFINITY)),
+  );
+}
+
+#[inline]
+pub fn intersect_boxes4(
+  origin: &[f64; 3],
+  inverse_direction: &[f64; 3],
+  boxes: &Boxes4,
+  t_min: f64,
+  t_max: f64,
+) -> [f64; 4] {
+  // SAFETY: SSE2 is available on every x86_64 processor, and the stores write two elements of a
+  // four-element array each.
+  unsafe {
+    let low = intersect_boxes2(origin, inverse_direction, boxes, 0, t_min, t_max);
+    let high = intersect_boxes2(origin, inverse_direction, boxes, 1, t_min, t_max);
+    let mut entries = [0.; 4];
+    _mm_storeu_pd(entries.as_mut_ptr(), low);
+    _mm_storeu_pd(entries.as_mut_ptr().add(2), high);
+    return entries;
+  }
+}
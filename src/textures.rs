@@ -2,11 +2,45 @@ use crate::geometry::Point;
 use crate::Color;
 use std::fmt::Debug;
 
+mod cellular;
+pub use cellular::*;
+
+mod image;
+pub use image::*;
+
+mod mapping;
+pub use mapping::*;
+
+mod noise;
+pub use noise::*;
+
 mod pattern;
 pub use pattern::*;
 
-pub trait Texture: Debug {
+mod perturb;
+pub use perturb::*;
+
+/// Textures are shared between render threads, so they must be `Send` and `Sync`.
+pub trait Texture: Debug + Send + Sync {
   fn color_at(&self, point: &Point) -> Color;
+
+  /// Returns whether the transformations of the texture and of the textures nested in it can be
+  /// inverted. No point maps into a texture with a singular transformation, so it is black.
+  fn has_invertible_transforms(&self) -> bool {
+    return true;
+  }
+}
+
+impl<T> Texture for Box<T>
+where
+  T: Texture + ?Sized,
+{
+  fn color_at(&self, point: &Point) -> Color {
+    return (**self).color_at(point);
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return (**self).has_invertible_transforms();
+  }
 }
 
 impl Texture for Color {
@@ -27,4 +61,10 @@ mod tests {
     assert_eq!(color.color_at(&Point::new(1.36, -32., 12.)), color);
     assert_eq!(color.color_at(&Point::new(0., 33.33, -5.7)), color);
   }
+
+  #[test]
+  fn boxed_texture_color_at() {
+    let texture: Box<dyn Texture> = Box::new(Color::cyan());
+    assert_eq!(texture.color_at(&Point::origin()), Color::cyan());
+  }
 }
@@ -0,0 +1,331 @@
+use crate::geometry::{Matrix, Point, Vector};
+use crate::textures::Texture;
+use crate::utils::{Float, Rng};
+use crate::Color;
+
+/// Distance function used to find the nearest feature points.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Metric {
+  /// Straight-line distance, giving round cells.
+  #[default]
+  Euclidean,
+  /// Sum of the distances along each axis, giving diamond-shaped cells.
+  Manhattan,
+  /// Largest distance along any axis, giving square cells.
+  Chebyshev,
+}
+impl Metric {
+  pub fn length(&self, vector: &Vector) -> Float {
+    let (x, y, z) = (vector.x.abs(), vector.y.abs(), vector.z.abs());
+    return match self {
+      Metric::Euclidean => vector.magnitude(),
+      Metric::Manhattan => x + y + z,
+      Metric::Chebyshev => x.max(y).max(z),
+    };
+  }
+}
+
+/// Result of a [`Worley`](Worley) lookup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellularSample {
+  /// Distance to the nearest feature point.
+  pub f1: Float,
+  /// Distance to the second-nearest feature point.
+  pub f2: Float,
+  /// Identifier of the cell owning the nearest feature point.
+  pub cell_id: u64,
+}
+impl CellularSample {
+  /// Maps the cell identifier to a stable pseudo-random number in `0.0..1.0`.
+  ///
+  /// Useful with [`palette`](crate::textures::palette) to color each cell with one of several
+  /// textures.
+  pub fn cell_value(&self) -> Float {
+    return Rng::new(self.cell_id).next_float();
+  }
+}
+
+/// Seeded cellular (Worley) noise with one jittered feature point per unit cube.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Worley {
+  seed: u64,
+  metric: Metric,
+}
+impl Worley {
+  pub fn new(seed: u64, metric: Metric) -> Self {
+    return Self { seed, metric };
+  }
+
+  pub fn seed(&self) -> u64 {
+    return self.seed;
+  }
+  pub fn metric(&self) -> Metric {
+    return self.metric;
+  }
+
+  fn cell_id(&self, x: i64, y: i64, z: i64) -> u64 {
+    let mut rng = Rng::new(self.seed);
+    for coordinate in [x, y, z].iter() {
+      rng = Rng::new(rng.next_u64() ^ *coordinate as u64);
+    }
+    return rng.next_u64();
+  }
+
+  /// Returns the feature point of the cell with the given identifier and lattice corner.
+  fn feature_point(cell_id: u64, x: i64, y: i64, z: i64) -> Point {
+    let mut rng = Rng::new(cell_id);
+    return Point::new(
+      x as Float + rng.next_float(),
+      y as Float + rng.next_float(),
+      z as Float + rng.next_float(),
+    );
+  }
+
+  pub fn sample(&self, point: &Point) -> CellularSample {
+    let (x, y, z) = (
+      point.x.floor() as i64,
+      point.y.floor() as i64,
+      point.z.floor() as i64,
+    );
+
+    let mut nearest = CellularSample {
+      f1: Float::INFINITY,
+      f2: Float::INFINITY,
+      cell_id: 0,
+    };
+    for dx in -1..=1 {
+      for dy in -1..=1 {
+        for dz in -1..=1 {
+          let (cx, cy, cz) = (x + dx, y + dy, z + dz);
+          let cell_id = self.cell_id(cx, cy, cz);
+          let feature = Self::feature_point(cell_id, cx, cy, cz);
+          let distance = self.metric.length(&(feature - *point));
+          if distance < nearest.f1 {
+            nearest.f2 = nearest.f1;
+            nearest.f1 = distance;
+            nearest.cell_id = cell_id;
+          } else if distance < nearest.f2 {
+            nearest.f2 = distance;
+          }
+        }
+      }
+    }
+    return nearest;
+  }
+}
+
+/// What a [`Cellular`](Cellular) texture shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CellularOutput {
+  /// Grayscale distance to the nearest feature point: dark spots at the cell centers.
+  #[default]
+  F1,
+  /// Grayscale distance to the second-nearest feature point.
+  F2,
+  /// Grayscale difference between F2 and F1: dark cracks along the cell borders.
+  F2MinusF1,
+  /// A flat pseudo-random color per cell, like Voronoi tiles.
+  CellId,
+}
+
+/// Texture showing cellular noise, for stone tiles, scales or cracked mud.
+#[derive(Clone, Debug)]
+pub struct Cellular {
+  worley: Worley,
+  output: CellularOutput,
+  texture_to_world: Matrix,
+  world_to_texture: Option<Matrix>,
+}
+impl Cellular {
+  pub fn new(worley: Worley, output: CellularOutput) -> Self {
+    return Self {
+      worley,
+      output,
+      texture_to_world: Matrix::identity(),
+      world_to_texture: Some(Matrix::identity()),
+    };
+  }
+
+  pub fn with_transformation(mut self, texture_to_world: Matrix) -> Self {
+    self.texture_to_world = texture_to_world;
+    self.world_to_texture = texture_to_world.try_inverse().ok();
+    return self;
+  }
+
+  pub fn worley(&self) -> &Worley {
+    return &self.worley;
+  }
+  pub fn output(&self) -> CellularOutput {
+    return self.output;
+  }
+  pub fn texture_to_world(&self) -> &Matrix {
+    return &self.texture_to_world;
+  }
+}
+impl Texture for Cellular {
+  fn color_at(&self, point: &Point) -> Color {
+    let world_to_texture = match &self.world_to_texture {
+      Some(world_to_texture) => world_to_texture,
+      None => return Color::black(),
+    };
+    let point_texture = *world_to_texture * *point;
+    let sample = self.worley.sample(&point_texture);
+    let value = match self.output {
+      CellularOutput::F1 => sample.f1,
+      CellularOutput::F2 => sample.f2,
+      CellularOutput::F2MinusF1 => sample.f2 - sample.f1,
+      CellularOutput::CellId => {
+        let mut rng = Rng::new(sample.cell_id);
+        return Color::new(rng.next_float(), rng.next_float(), rng.next_float());
+      }
+    };
+    return Color::new(value, value, value);
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return self.world_to_texture.is_some();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::textures::{palette, Pattern};
+
+  fn sample_points() -> Vec<Point> {
+    return (0..200)
+      .map(|i| {
+        let i = i as Float;
+        Point::new(i * 0.173 - 17., i * 0.061 + 3., -i * 0.119)
+      })
+      .collect();
+  }
+
+  #[test]
+  fn metric_length() {
+    let vector = Vector::new(3., -4., 0.);
+    assert_eq!(Metric::Euclidean.length(&vector), 5.);
+    assert_eq!(Metric::Manhattan.length(&vector), 7.);
+    assert_eq!(Metric::Chebyshev.length(&vector), 4.);
+  }
+
+  #[test]
+  fn init_new() {
+    let worley = Worley::new(3, Metric::Manhattan);
+    assert_eq!(worley.seed(), 3);
+    assert_eq!(worley.metric(), Metric::Manhattan);
+
+    let cellular = Cellular::new(worley, CellularOutput::CellId);
+    assert_eq!(cellular.worley(), &worley);
+    assert_eq!(cellular.output(), CellularOutput::CellId);
+    assert_eq!(cellular.texture_to_world(), &Matrix::identity());
+  }
+
+  #[test]
+  fn sample_is_deterministic() {
+    let point = Point::new(1.7, -2.2, 0.4);
+    assert_eq!(
+      Worley::new(9, Metric::Euclidean).sample(&point),
+      Worley::new(9, Metric::Euclidean).sample(&point)
+    );
+  }
+
+  #[test]
+  fn sample_depends_on_seed() {
+    let point = Point::new(1.7, -2.2, 0.4);
+    assert_ne!(
+      Worley::new(1, Metric::Euclidean).sample(&point),
+      Worley::new(2, Metric::Euclidean).sample(&point)
+    );
+  }
+
+  #[test]
+  fn sample_distances_are_ordered() {
+    for metric in [Metric::Euclidean, Metric::Manhattan, Metric::Chebyshev].iter() {
+      let worley = Worley::new(5, *metric);
+      for point in sample_points() {
+        let sample = worley.sample(&point);
+        assert!(sample.f1 >= 0.);
+        assert!(sample.f1 <= sample.f2);
+        assert!(sample.f2.is_finite());
+      }
+    }
+  }
+
+  #[test]
+  fn sample_is_zero_at_feature_point() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    let cell_id = worley.cell_id(2, -1, 4);
+    let feature = Worley::feature_point(cell_id, 2, -1, 4);
+    let sample = worley.sample(&feature);
+    assert_eq!(sample.f1, 0.);
+    assert_eq!(sample.cell_id, cell_id);
+  }
+
+  #[test]
+  fn sample_cell_id_constant_near_feature_point() {
+    let worley = Worley::new(5, Metric::Chebyshev);
+    let cell_id = worley.cell_id(0, 0, 0);
+    let feature = Worley::feature_point(cell_id, 0, 0, 0);
+    let nearby = feature + Vector::new(0.001, -0.001, 0.001);
+    assert_eq!(worley.sample(&nearby).cell_id, cell_id);
+  }
+
+  #[test]
+  fn cell_value_in_unit_range() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    for point in sample_points() {
+      let value = worley.sample(&point).cell_value();
+      assert!((0. ..1.).contains(&value));
+    }
+  }
+
+  #[test]
+  fn color_at_distance_outputs_are_grayscale() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    let point = Point::new(0.3, 0.7, -1.2);
+    let sample = worley.sample(&point);
+
+    let f1 = Cellular::new(worley, CellularOutput::F1).color_at(&point);
+    assert_eq!(f1, Color::new(sample.f1, sample.f1, sample.f1));
+    let f2 = Cellular::new(worley, CellularOutput::F2).color_at(&point);
+    assert_eq!(f2, Color::new(sample.f2, sample.f2, sample.f2));
+    let cracks = Cellular::new(worley, CellularOutput::F2MinusF1).color_at(&point);
+    let difference = sample.f2 - sample.f1;
+    assert_eq!(cracks, Color::new(difference, difference, difference));
+  }
+
+  #[test]
+  fn color_at_cell_id_is_flat_per_cell() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    let cellular = Cellular::new(worley, CellularOutput::CellId);
+    let cell_id = worley.cell_id(1, 1, 1);
+    let feature = Worley::feature_point(cell_id, 1, 1, 1);
+    assert_eq!(
+      cellular.color_at(&feature),
+      cellular.color_at(&(feature + Vector::new(0.001, 0.001, 0.001)))
+    );
+  }
+
+  #[test]
+  fn color_at_with_transformation() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    let cellular = Cellular::new(worley, CellularOutput::F1)
+      .with_transformation(Matrix::identity().scale(2., 2., 2.));
+    let point = Point::new(0.6, 1.4, -0.2);
+    let f1 = worley.sample(&(point / 2.)).f1;
+    assert_eq!(cellular.color_at(&point), Color::new(f1, f1, f1));
+  }
+
+  #[test]
+  fn composes_with_palette() {
+    let worley = Worley::new(5, Metric::Euclidean);
+    let pattern = Pattern::new(
+      palette(move |point: &Point| worley.sample(point).cell_value()),
+      vec![Box::new(Color::red()), Box::new(Color::blue())],
+    );
+    for point in sample_points() {
+      let color = pattern.color_at(&point);
+      assert!(color == Color::red() || color == Color::blue());
+    }
+  }
+}
@@ -0,0 +1,284 @@
+use crate::formats::FormatError;
+use crate::geometry::{Matrix, Point};
+use crate::render::Canvas;
+use crate::textures::{Texture, UvMapping};
+use crate::utils::{Float, Lerp};
+use crate::Color;
+use std::path::Path;
+
+/// How texels are combined when sampling between pixel centers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+  Nearest,
+  #[default]
+  Bilinear,
+}
+
+/// How texture coordinates outside `0.0..=1.0` are brought back onto the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Wrap {
+  #[default]
+  Repeat,
+  Clamp,
+  Mirror,
+}
+impl Wrap {
+  /// Maps a texel index onto `0..size`.
+  fn apply(&self, index: i64, size: usize) -> usize {
+    let size = size as i64;
+    let wrapped = match self {
+      Wrap::Repeat => index.rem_euclid(size),
+      Wrap::Clamp => index.clamp(0, size - 1),
+      Wrap::Mirror => {
+        let period = index.rem_euclid(2 * size);
+        if period < size {
+          period
+        } else {
+          2 * size - 1 - period
+        }
+      }
+    };
+    return wrapped as usize;
+  }
+}
+
+/// Texture that looks up colors in an image through a [`UvMapping`](UvMapping).
+///
+/// `u` runs left to right and `v` runs bottom to top, so `(0, 0)` is the bottom-left corner of
+/// the image.
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+  canvas: Canvas,
+  mapping: UvMapping,
+  filter: Filter,
+  wrap: Wrap,
+  texture_to_world: Matrix,
+  world_to_texture: Option<Matrix>,
+}
+impl ImageTexture {
+  pub fn new(canvas: Canvas, mapping: UvMapping) -> Self {
+    return Self {
+      canvas,
+      mapping,
+      filter: Filter::default(),
+      wrap: Wrap::default(),
+      texture_to_world: Matrix::identity(),
+      world_to_texture: Some(Matrix::identity()),
+    };
+  }
+
+  /// Loads the image from a PPM or PFM file.
+  pub fn open<P: AsRef<Path>>(path: P, mapping: UvMapping) -> Result<Self, FormatError> {
+    return Ok(Self::new(Canvas::open(path)?, mapping));
+  }
+
+  pub fn with_filter(mut self, filter: Filter) -> Self {
+    self.filter = filter;
+    return self;
+  }
+  pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+    self.wrap = wrap;
+    return self;
+  }
+  pub fn with_transformation(mut self, texture_to_world: Matrix) -> Self {
+    self.texture_to_world = texture_to_world;
+    self.world_to_texture = texture_to_world.try_inverse().ok();
+    return self;
+  }
+
+  pub fn canvas(&self) -> &Canvas {
+    return &self.canvas;
+  }
+  pub fn mapping(&self) -> UvMapping {
+    return self.mapping;
+  }
+  pub fn filter(&self) -> Filter {
+    return self.filter;
+  }
+  pub fn wrap(&self) -> Wrap {
+    return self.wrap;
+  }
+  pub fn texture_to_world(&self) -> &Matrix {
+    return &self.texture_to_world;
+  }
+
+  fn texel(&self, x: i64, y: i64) -> Color {
+    return *self.canvas.pixel(
+      self.wrap.apply(x, self.canvas.width()),
+      self.wrap.apply(y, self.canvas.height()),
+    );
+  }
+
+  /// Returns the filtered color at the texture coordinates `(u, v)`.
+  pub fn sample(&self, u: Float, v: Float) -> Color {
+    if self.canvas.width() == 0 || self.canvas.height() == 0 {
+      return Color::black();
+    }
+
+    let x = u * self.canvas.width() as Float - 0.5;
+    let y = (1. - v) * self.canvas.height() as Float - 0.5;
+    return match self.filter {
+      Filter::Nearest => self.texel(x.round() as i64, y.round() as i64),
+      Filter::Bilinear => {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        // The casts saturate for huge coordinates, so the neighbours must not overflow.
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let (x1, y1) = (x0.saturating_add(1), y0.saturating_add(1));
+
+        let top = self.texel(x0, y0).lerp(&self.texel(x1, y0), &tx);
+        let bottom = self.texel(x0, y1).lerp(&self.texel(x1, y1), &tx);
+        top.lerp(&bottom, &ty)
+      }
+    };
+  }
+}
+impl Texture for ImageTexture {
+  fn color_at(&self, point: &Point) -> Color {
+    let world_to_texture = match &self.world_to_texture {
+      Some(world_to_texture) => world_to_texture,
+      None => return Color::black(),
+    };
+    let point_texture = *world_to_texture * *point;
+    let (u, v) = self.mapping.map(&point_texture);
+    return self.sample(u, v);
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return self.world_to_texture.is_some();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// 2x2 image with red and green on the top row, blue and white on the bottom row.
+  fn test_canvas() -> Canvas {
+    let mut canvas = Canvas::new(2, 2);
+    canvas.set_pixel(0, 0, &Color::red());
+    canvas.set_pixel(1, 0, &Color::green());
+    canvas.set_pixel(0, 1, &Color::blue());
+    canvas.set_pixel(1, 1, &Color::white());
+    return canvas;
+  }
+
+  fn test_texture(filter: Filter, wrap: Wrap) -> ImageTexture {
+    return ImageTexture::new(test_canvas(), UvMapping::Planar)
+      .with_filter(filter)
+      .with_wrap(wrap);
+  }
+
+  #[test]
+  fn init_new() {
+    let texture = ImageTexture::new(test_canvas(), UvMapping::Spherical);
+    assert_eq!(texture.canvas().width(), 2);
+    assert_eq!(texture.mapping(), UvMapping::Spherical);
+    assert_eq!(texture.filter(), Filter::Bilinear);
+    assert_eq!(texture.wrap(), Wrap::Repeat);
+    assert_eq!(texture.texture_to_world(), &Matrix::identity());
+  }
+
+  #[test]
+  fn init_with_options() {
+    let texture = ImageTexture::new(test_canvas(), UvMapping::Planar)
+      .with_filter(Filter::Nearest)
+      .with_wrap(Wrap::Mirror)
+      .with_transformation(Matrix::identity().scale(2., 2., 2.));
+    assert_eq!(texture.filter(), Filter::Nearest);
+    assert_eq!(texture.wrap(), Wrap::Mirror);
+    assert_eq!(
+      texture.texture_to_world(),
+      &Matrix::identity().scale(2., 2., 2.)
+    );
+  }
+
+  #[test]
+  fn sample_nearest() {
+    let texture = test_texture(Filter::Nearest, Wrap::Clamp);
+    assert_eq!(texture.sample(0.25, 0.75), Color::red());
+    assert_eq!(texture.sample(0.75, 0.75), Color::green());
+    assert_eq!(texture.sample(0.25, 0.25), Color::blue());
+    assert_eq!(texture.sample(0.75, 0.25), Color::white());
+    assert_eq!(texture.sample(0.6, 0.9), Color::green());
+  }
+
+  #[test]
+  fn sample_bilinear_at_texel_centers() {
+    let texture = test_texture(Filter::Bilinear, Wrap::Clamp);
+    assert_eq!(texture.sample(0.25, 0.75), Color::red());
+    assert_eq!(texture.sample(0.75, 0.25), Color::white());
+  }
+
+  #[test]
+  fn sample_bilinear_between_texels() {
+    let texture = test_texture(Filter::Bilinear, Wrap::Clamp);
+    assert_eq!(texture.sample(0.5, 0.75), Color::new(0.5, 0.5, 0.));
+    assert_eq!(texture.sample(0.25, 0.5), Color::new(0.5, 0., 0.5));
+    assert_eq!(texture.sample(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+  }
+
+  #[test]
+  fn sample_repeat() {
+    let texture = test_texture(Filter::Nearest, Wrap::Repeat);
+    assert_eq!(texture.sample(1.25, 0.75), Color::red());
+    assert_eq!(texture.sample(-0.25, 0.75), Color::green());
+    assert_eq!(texture.sample(0.25, 1.25), Color::blue());
+  }
+
+  #[test]
+  fn sample_clamp() {
+    let texture = test_texture(Filter::Nearest, Wrap::Clamp);
+    assert_eq!(texture.sample(5., 0.75), Color::green());
+    assert_eq!(texture.sample(-5., 0.75), Color::red());
+    assert_eq!(texture.sample(0.25, -3.), Color::blue());
+  }
+
+  #[test]
+  fn sample_mirror() {
+    let texture = test_texture(Filter::Nearest, Wrap::Mirror);
+    assert_eq!(texture.sample(1.25, 0.75), Color::green());
+    assert_eq!(texture.sample(1.75, 0.75), Color::red());
+    assert_eq!(texture.sample(-0.25, 0.75), Color::red());
+    assert_eq!(texture.sample(2.25, 0.75), Color::red());
+  }
+
+  #[test]
+  fn sample_bilinear_repeat_blends_across_edge() {
+    let texture = test_texture(Filter::Bilinear, Wrap::Repeat);
+    assert_eq!(texture.sample(0., 0.75), Color::new(0.5, 0.5, 0.));
+  }
+
+  #[test]
+  fn sample_huge_coordinates() {
+    for wrap in [Wrap::Repeat, Wrap::Clamp, Wrap::Mirror] {
+      let texture = test_texture(Filter::Bilinear, wrap);
+      texture.sample(Float::MAX, -Float::MAX);
+      texture.sample(-Float::MAX, Float::MAX);
+      texture.sample(Float::INFINITY, Float::NAN);
+    }
+  }
+
+  #[test]
+  fn sample_empty_canvas() {
+    let texture = ImageTexture::new(Canvas::new(0, 0), UvMapping::Planar);
+    assert_eq!(texture.sample(0.5, 0.5), Color::black());
+  }
+
+  #[test]
+  fn color_at_uses_mapping() {
+    let texture = test_texture(Filter::Nearest, Wrap::Repeat);
+    assert_eq!(texture.color_at(&Point::new(0.25, 10., 0.75)), Color::red());
+    assert_eq!(
+      texture.color_at(&Point::new(0.75, -3., 0.25)),
+      Color::white()
+    );
+  }
+
+  #[test]
+  fn color_at_with_transformation() {
+    let texture = test_texture(Filter::Nearest, Wrap::Clamp)
+      .with_transformation(Matrix::identity().scale(2., 1., 2.));
+    assert_eq!(texture.color_at(&Point::new(0.5, 0., 1.5)), Color::red());
+    assert_eq!(texture.color_at(&Point::new(1.5, 0., 0.5)), Color::white());
+  }
+}
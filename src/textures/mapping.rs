@@ -0,0 +1,92 @@
+use crate::geometry::Point;
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+/// Projects a point in texture space onto 2D texture coordinates `(u, v)`.
+///
+/// Coordinates in `0.0..=1.0` cover the texture exactly once; anything outside is left to the
+/// sampler's wrap mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UvMapping {
+  /// Projects along the y axis, using `x` as `u` and `z` as `v`.
+  #[default]
+  Planar,
+  /// Wraps around the unit sphere using longitude as `u` and latitude as `v`.
+  Spherical,
+  /// Wraps around the y axis using the angle as `u` and height as `v`.
+  Cylindrical,
+}
+impl UvMapping {
+  pub fn map(&self, point: &Point) -> (Float, Float) {
+    return match self {
+      UvMapping::Planar => (point.x, point.z),
+      UvMapping::Spherical => {
+        let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+        let polar = if radius == 0. {
+          0.
+        } else {
+          (point.y / radius).acos()
+        };
+        (azimuth_u(point), 1. - polar / PI)
+      }
+      UvMapping::Cylindrical => (azimuth_u(point), point.y),
+    };
+  }
+}
+
+/// Maps the angle around the y axis to `0.0..1.0`, increasing counter-clockwise when seen from
+/// above and starting behind the origin.
+fn azimuth_u(point: &Point) -> Float {
+  let azimuth = point.x.atan2(point.z);
+  return 1. - (azimuth / (2. * PI) + 0.5);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::assert_ae;
+
+  fn assert_uv(actual: (Float, Float), expected: (Float, Float)) {
+    assert_ae!(actual.0, expected.0);
+    assert_ae!(actual.1, expected.1);
+  }
+
+  #[test]
+  fn planar() {
+    assert_uv(
+      UvMapping::Planar.map(&Point::new(0.25, 5., 0.5)),
+      (0.25, 0.5),
+    );
+    assert_uv(UvMapping::Planar.map(&Point::new(-1.5, 0., 2.)), (-1.5, 2.));
+  }
+
+  #[test]
+  fn spherical() {
+    let mapping = UvMapping::Spherical;
+    assert_uv(mapping.map(&Point::new(0., 0., -1.)), (0., 0.5));
+    assert_uv(mapping.map(&Point::new(1., 0., 0.)), (0.25, 0.5));
+    assert_uv(mapping.map(&Point::new(0., 0., 1.)), (0.5, 0.5));
+    assert_uv(mapping.map(&Point::new(-1., 0., 0.)), (0.75, 0.5));
+    assert_uv(mapping.map(&Point::new(0., 1., 0.)), (0.5, 1.));
+    assert_uv(mapping.map(&Point::new(0., -1., 0.)), (0.5, 0.));
+  }
+
+  #[test]
+  fn spherical_at_origin() {
+    assert_uv(UvMapping::Spherical.map(&Point::origin()), (0.5, 1.));
+  }
+
+  #[test]
+  fn cylindrical() {
+    let mapping = UvMapping::Cylindrical;
+    assert_uv(mapping.map(&Point::new(0., 0., -1.)), (0., 0.));
+    assert_uv(mapping.map(&Point::new(0., 0.5, -1.)), (0., 0.5));
+    assert_uv(mapping.map(&Point::new(1., 0.25, 0.)), (0.25, 0.25));
+    assert_uv(mapping.map(&Point::new(-1., 2., 0.)), (0.75, 2.));
+  }
+
+  #[test]
+  fn default() {
+    assert_eq!(UvMapping::default(), UvMapping::Planar);
+  }
+}
@@ -0,0 +1,377 @@
+use crate::geometry::{Matrix, Point};
+use crate::textures::Texture;
+use crate::utils::{Float, Lerp, Rng};
+use crate::Color;
+use std::fmt::{self, Debug};
+
+/// Seeded 3D gradient noise, based on Ken Perlin's improved noise.
+///
+/// The same seed always produces the same noise. Values lie roughly in `-1.0..=1.0` and are zero
+/// at every integer lattice point.
+#[derive(Clone, PartialEq)]
+pub struct Perlin {
+  seed: u64,
+  permutation: [u8; 512],
+}
+impl Perlin {
+  pub fn new(seed: u64) -> Self {
+    let mut table: Vec<u8> = (0..=255).collect();
+    let mut rng = Rng::new(seed);
+    for i in (1..table.len()).rev() {
+      table.swap(i, rng.below(i + 1));
+    }
+
+    let mut permutation = [0; 512];
+    for (i, value) in permutation.iter_mut().enumerate() {
+      *value = table[i % 256];
+    }
+    return Self { seed, permutation };
+  }
+
+  pub fn seed(&self) -> u64 {
+    return self.seed;
+  }
+
+  fn hash(&self, x: usize, y: usize, z: usize) -> u8 {
+    let p = &self.permutation;
+    return p[p[p[x] as usize + y] as usize + z];
+  }
+
+  pub fn noise(&self, point: &Point) -> Float {
+    let (x_floor, y_floor, z_floor) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (x, y, z) = (point.x - x_floor, point.y - y_floor, point.z - z_floor);
+    let xi = (x_floor as i64).rem_euclid(256) as usize;
+    let yi = (y_floor as i64).rem_euclid(256) as usize;
+    let zi = (z_floor as i64).rem_euclid(256) as usize;
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |dx: usize, dy: usize, dz: usize| {
+      gradient(
+        self.hash(xi + dx, yi + dy, zi + dz),
+        x - dx as Float,
+        y - dy as Float,
+        z - dz as Float,
+      )
+    };
+    let bottom = corner(0, 0, 0)
+      .lerp(&corner(1, 0, 0), &u)
+      .lerp(&corner(0, 1, 0).lerp(&corner(1, 1, 0), &u), &v);
+    let top = corner(0, 0, 1)
+      .lerp(&corner(1, 0, 1), &u)
+      .lerp(&corner(0, 1, 1).lerp(&corner(1, 1, 1), &u), &v);
+    return bottom.lerp(&top, &w);
+  }
+
+  /// Fractal Brownian motion: sums `octaves` layers of noise, each at twice the frequency and
+  /// half the amplitude of the previous one.
+  ///
+  /// The sum is normalized, so the result stays roughly within `-1.0..=1.0`.
+  pub fn fbm(&self, point: &Point, octaves: u32) -> Float {
+    return self.octaves(point, octaves, |value| value);
+  }
+
+  /// Like [`fbm`](Perlin::fbm), but sums the absolute value of each octave, producing sharp
+  /// creases where the noise crosses zero.
+  ///
+  /// The result lies within `0.0..=1.0`.
+  pub fn turbulence(&self, point: &Point, octaves: u32) -> Float {
+    return self.octaves(point, octaves, Float::abs);
+  }
+
+  fn octaves(&self, point: &Point, octaves: u32, shape: impl Fn(Float) -> Float) -> Float {
+    let mut sum = 0.;
+    let mut total_amplitude = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = 1.;
+    for _ in 0..octaves {
+      sum += amplitude * shape(self.noise(&(*point * frequency)));
+      total_amplitude += amplitude;
+      amplitude *= 0.5;
+      frequency *= 2.;
+    }
+    return if total_amplitude == 0. {
+      0.
+    } else {
+      sum / total_amplitude
+    };
+  }
+}
+impl Debug for Perlin {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return f.debug_struct("Perlin").field("seed", &self.seed).finish();
+  }
+}
+
+fn fade(t: Float) -> Float {
+  return t * t * t * (t * (t * 6. - 15.) + 10.);
+}
+
+fn gradient(hash: u8, x: Float, y: Float, z: Float) -> Float {
+  let h = hash & 15;
+  let u = if h < 8 { x } else { y };
+  let v = if h < 4 {
+    y
+  } else if h == 12 || h == 14 {
+    x
+  } else {
+    z
+  };
+  return (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v });
+}
+
+/// The procedural look of a [`NoiseTexture`](NoiseTexture).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseKind {
+  /// Veins running along the x axis, distorted by turbulence.
+  Marble { frequency: Float, turbulence: Float },
+  /// Concentric growth rings around the y axis, distorted by turbulence.
+  Wood { rings: Float, turbulence: Float },
+  /// Soft billowing fBm noise.
+  Clouds { octaves: u32 },
+}
+
+/// Texture that blends between two textures using a noise-based procedural function.
+#[derive(Clone, Debug)]
+pub struct NoiseTexture<A, B>
+where
+  A: Texture,
+  B: Texture,
+{
+  kind: NoiseKind,
+  perlin: Perlin,
+  a: A,
+  b: B,
+  texture_to_world: Matrix,
+  world_to_texture: Option<Matrix>,
+}
+impl<A, B> NoiseTexture<A, B>
+where
+  A: Texture,
+  B: Texture,
+{
+  pub fn new(kind: NoiseKind, seed: u64, a: A, b: B) -> Self {
+    return Self {
+      kind,
+      perlin: Perlin::new(seed),
+      a,
+      b,
+      texture_to_world: Matrix::identity(),
+      world_to_texture: Some(Matrix::identity()),
+    };
+  }
+  pub fn marble(seed: u64, a: A, b: B) -> Self {
+    let kind = NoiseKind::Marble {
+      frequency: 1.,
+      turbulence: 5.,
+    };
+    return Self::new(kind, seed, a, b);
+  }
+  pub fn wood(seed: u64, a: A, b: B) -> Self {
+    let kind = NoiseKind::Wood {
+      rings: 4.,
+      turbulence: 0.5,
+    };
+    return Self::new(kind, seed, a, b);
+  }
+  pub fn clouds(seed: u64, a: A, b: B) -> Self {
+    return Self::new(NoiseKind::Clouds { octaves: 6 }, seed, a, b);
+  }
+
+  pub fn with_transformation(mut self, texture_to_world: Matrix) -> Self {
+    self.texture_to_world = texture_to_world;
+    self.world_to_texture = texture_to_world.try_inverse().ok();
+    return self;
+  }
+
+  pub fn kind(&self) -> NoiseKind {
+    return self.kind;
+  }
+  pub fn perlin(&self) -> &Perlin {
+    return &self.perlin;
+  }
+  pub fn textures(&self) -> (&A, &B) {
+    return (&self.a, &self.b);
+  }
+  pub fn texture_to_world(&self) -> &Matrix {
+    return &self.texture_to_world;
+  }
+
+  /// Returns the blend factor between the two textures at a point in texture space.
+  pub fn value_at(&self, point: &Point) -> Float {
+    return match self.kind {
+      NoiseKind::Marble {
+        frequency,
+        turbulence,
+      } => {
+        let phase = point.x * frequency + turbulence * self.perlin.turbulence(point, 6);
+        0.5 + 0.5 * phase.sin()
+      }
+      NoiseKind::Wood { rings, turbulence } => {
+        let radius = (point.x.powi(2) + point.z.powi(2)).sqrt();
+        (radius * rings + turbulence * self.perlin.turbulence(point, 4)).fract()
+      }
+      NoiseKind::Clouds { octaves } => 0.5 + 0.5 * self.perlin.fbm(point, octaves),
+    };
+  }
+}
+impl<A, B> Texture for NoiseTexture<A, B>
+where
+  A: Texture,
+  B: Texture,
+{
+  fn color_at(&self, point: &Point) -> Color {
+    let world_to_texture = match &self.world_to_texture {
+      Some(world_to_texture) => world_to_texture,
+      None => return Color::black(),
+    };
+    let point_texture = *world_to_texture * *point;
+    let t = self.value_at(&point_texture).clamp(0., 1.);
+    return self
+      .a
+      .color_at(&point_texture)
+      .lerp(&self.b.color_at(&point_texture), &t);
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return self.world_to_texture.is_some()
+      && self.a.has_invertible_transforms()
+      && self.b.has_invertible_transforms();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn perlin_is_deterministic() {
+    let a = Perlin::new(7);
+    let b = Perlin::new(7);
+    let point = Point::new(1.3, -4.7, 2.2);
+    assert_eq!(a.noise(&point), b.noise(&point));
+    assert_eq!(a, b);
+    assert_eq!(a.seed(), 7);
+  }
+
+  #[test]
+  fn perlin_seeds_differ() {
+    let point = Point::new(1.3, -4.7, 2.2);
+    assert_ne!(Perlin::new(1).noise(&point), Perlin::new(2).noise(&point));
+  }
+
+  #[test]
+  fn perlin_zero_at_lattice_points() {
+    let perlin = Perlin::new(3);
+    assert_eq!(perlin.noise(&Point::origin()), 0.);
+    assert_eq!(perlin.noise(&Point::new(5., -2., 17.)), 0.);
+  }
+
+  #[test]
+  fn perlin_is_bounded_and_varies() {
+    let perlin = Perlin::new(11);
+    let mut rng = Rng::new(0);
+    let values: Vec<Float> = (0..1000)
+      .map(|_| {
+        let point = Point::new(
+          rng.next_float() * 20. - 10.,
+          rng.next_float() * 20. - 10.,
+          rng.next_float() * 20. - 10.,
+        );
+        perlin.noise(&point)
+      })
+      .collect();
+    assert!(values.iter().all(|value| value.abs() <= 1.5));
+    assert!(values.iter().any(|value| *value > 0.2));
+    assert!(values.iter().any(|value| *value < -0.2));
+  }
+
+  #[test]
+  fn perlin_is_continuous() {
+    let perlin = Perlin::new(5);
+    let point = Point::new(0.3, 0.6, 0.9);
+    let nearby = Point::new(0.3001, 0.6, 0.9);
+    assert!((perlin.noise(&point) - perlin.noise(&nearby)).abs() < 0.001);
+  }
+
+  #[test]
+  fn fbm_single_octave_is_noise() {
+    let perlin = Perlin::new(5);
+    let point = Point::new(0.3, 0.6, 0.9);
+    assert_eq!(perlin.fbm(&point, 1), perlin.noise(&point));
+    assert_eq!(perlin.fbm(&point, 0), 0.);
+  }
+
+  #[test]
+  fn turbulence_is_non_negative() {
+    let perlin = Perlin::new(5);
+    for i in 0..100 {
+      let point = Point::new(i as Float * 0.37, i as Float * 0.11, -(i as Float) * 0.23);
+      let value = perlin.turbulence(&point, 4);
+      assert!((0. ..=1.).contains(&value));
+    }
+  }
+
+  #[test]
+  fn init_named_kinds() {
+    let marble = NoiseTexture::marble(1, Color::white(), Color::black());
+    assert!(matches!(marble.kind(), NoiseKind::Marble { .. }));
+    assert_eq!(marble.perlin().seed(), 1);
+    assert_eq!(marble.textures(), (&Color::white(), &Color::black()));
+    assert_eq!(marble.texture_to_world(), &Matrix::identity());
+
+    let wood = NoiseTexture::wood(2, Color::white(), Color::black());
+    assert!(matches!(wood.kind(), NoiseKind::Wood { .. }));
+
+    let clouds = NoiseTexture::clouds(3, Color::white(), Color::black());
+    assert_eq!(clouds.kind(), NoiseKind::Clouds { octaves: 6 });
+  }
+
+  #[test]
+  fn marble_without_turbulence_is_sine_of_x() {
+    let kind = NoiseKind::Marble {
+      frequency: 1.,
+      turbulence: 0.,
+    };
+    let marble = NoiseTexture::new(kind, 0, Color::black(), Color::white());
+    assert_eq!(marble.color_at(&Point::origin()), Color::new(0.5, 0.5, 0.5));
+    let peak = Point::new(crate::utils::consts::FRAC_PI_2, 3., -2.);
+    assert_eq!(marble.color_at(&peak), Color::white());
+  }
+
+  #[test]
+  fn wood_without_turbulence_is_rings() {
+    let kind = NoiseKind::Wood {
+      rings: 1.,
+      turbulence: 0.,
+    };
+    let wood = NoiseTexture::new(kind, 0, Color::black(), Color::white());
+    assert_eq!(wood.color_at(&Point::origin()), Color::black());
+    assert_eq!(
+      wood.color_at(&Point::new(0., 7., 1.25)),
+      Color::new(0.25, 0.25, 0.25)
+    );
+  }
+
+  #[test]
+  fn clouds_stay_between_textures() {
+    let clouds = NoiseTexture::clouds(9, Color::black(), Color::white());
+    for i in 0..100 {
+      let color = clouds.color_at(&Point::new(i as Float * 0.13, 0.5, i as Float * 0.07));
+      assert!((0. ..=1.).contains(&color.r));
+    }
+  }
+
+  #[test]
+  fn color_at_with_transformation() {
+    let kind = NoiseKind::Marble {
+      frequency: 1.,
+      turbulence: 0.,
+    };
+    let marble = NoiseTexture::new(kind, 0, Color::black(), Color::white())
+      .with_transformation(Matrix::identity().translate(crate::utils::consts::FRAC_PI_2, 0., 0.));
+    assert_eq!(marble.color_at(&Point::origin()), Color::black());
+    assert_eq!(
+      marble.color_at(&Point::new(crate::utils::consts::PI, 0., 0.)),
+      Color::white()
+    );
+  }
+}
@@ -1,129 +1,252 @@
 use crate::geometry::{Matrix, Point};
 use crate::textures::Texture;
-use crate::utils::Lerp;
+use crate::utils::{Float, Lerp};
 use crate::Color;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+
+/// Which of a pattern's textures to sample at a point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+  /// Sample a single texture.
+  Single(usize),
+  /// Sample two textures and interpolate from the first to the second by the given factor.
+  Mix(usize, usize, Float),
+  /// Sample every texture and average them.
+  Average,
+}
 
-type FnPattern = fn(Point, Color, Color) -> Color;
+/// Chooses textures for a point in pattern space, given the number of textures in the pattern.
+pub type FnPattern = Box<dyn Fn(&Point, usize) -> Selection + Send + Sync>;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct Pattern<A, B>
-where
-  A: Texture,
-  B: Texture,
-{
+/// Texture that combines any number of nested textures according to a pattern function.
+///
+/// The pattern function only decides which textures to use, so textures that are not selected at
+/// a point are never evaluated there.
+pub struct Pattern {
   fn_pattern: FnPattern,
-  a: A,
-  b: B,
+  textures: Vec<Box<dyn Texture>>,
   pattern_to_world: Matrix,
+  world_to_pattern: Option<Matrix>,
 }
-impl<A, B> Pattern<A, B>
-where
-  A: Texture,
-  B: Texture,
-{
-  pub fn with_transformation(fn_pattern: FnPattern, a: A, b: B, pattern_to_world: Matrix) -> Self {
+impl Pattern {
+  pub fn with_transformation<F>(
+    fn_pattern: F,
+    textures: Vec<Box<dyn Texture>>,
+    pattern_to_world: Matrix,
+  ) -> Self
+  where
+    F: Fn(&Point, usize) -> Selection + Send + Sync + 'static,
+  {
     return Self {
-      fn_pattern,
-      a,
-      b,
+      fn_pattern: Box::new(fn_pattern),
+      textures,
       pattern_to_world,
+      world_to_pattern: pattern_to_world.try_inverse().ok(),
     };
   }
-  pub fn new(fn_pattern: FnPattern, a: A, b: B) -> Self {
-    return Self::with_transformation(fn_pattern, a, b, Matrix::identity());
+  pub fn new<F>(fn_pattern: F, textures: Vec<Box<dyn Texture>>) -> Self
+  where
+    F: Fn(&Point, usize) -> Selection + Send + Sync + 'static,
+  {
+    return Self::with_transformation(fn_pattern, textures, Matrix::identity());
+  }
+  /// Creates a pattern alternating between white and black.
+  pub fn with_fn<F>(fn_pattern: F) -> Self
+  where
+    F: Fn(&Point, usize) -> Selection + Send + Sync + 'static,
+  {
+    return Self::new(
+      fn_pattern,
+      vec![Box::new(Color::white()), Box::new(Color::black())],
+    );
   }
 
-  pub fn textures(&self) -> (&A, &B) {
-    return (&self.a, &self.b);
+  pub fn textures(&self) -> &[Box<dyn Texture>] {
+    return &self.textures;
   }
   pub fn pattern_to_world(&self) -> &Matrix {
     return &self.pattern_to_world;
   }
+
+  /// Returns the selection the pattern function makes at a point in pattern space.
+  pub fn selection_at(&self, point_pattern: &Point) -> Selection {
+    return (self.fn_pattern)(point_pattern, self.textures.len());
+  }
+
+  fn texture_color(&self, index: usize, point_pattern: &Point) -> Color {
+    let index = index.min(self.textures.len() - 1);
+    return self.textures[index].color_at(point_pattern);
+  }
 }
-impl Pattern<Color, Color> {
-  pub fn with_fn(fn_pattern: FnPattern) -> Self {
-    return Self::with_transformation(
-      fn_pattern,
-      Color::white(),
-      Color::black(),
-      Matrix::identity(),
-    );
+impl Debug for Pattern {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    return f
+      .debug_struct("Pattern")
+      .field("textures", &self.textures)
+      .field("pattern_to_world", &self.pattern_to_world)
+      .finish_non_exhaustive();
   }
 }
-impl<A, B> Texture for Pattern<A, B>
-where
-  A: Texture,
-  B: Texture,
-{
+impl Texture for Pattern {
   fn color_at(&self, point: &Point) -> Color {
-    let point_pattern = self.pattern_to_world().inverse() * *point;
-    return (self.fn_pattern)(
-      point_pattern,
-      self.a.color_at(&point_pattern),
-      self.b.color_at(&point_pattern),
-    );
+    let world_to_pattern = match &self.world_to_pattern {
+      Some(world_to_pattern) if !self.textures.is_empty() => world_to_pattern,
+      _ => return Color::black(),
+    };
+
+    let point_pattern = *world_to_pattern * *point;
+    return match self.selection_at(&point_pattern) {
+      Selection::Single(index) => self.texture_color(index, &point_pattern),
+      Selection::Mix(from, to, t) => self
+        .texture_color(from, &point_pattern)
+        .lerp(&self.texture_color(to, &point_pattern), &t),
+      Selection::Average => {
+        let sum: Color = self
+          .textures
+          .iter()
+          .map(|texture| texture.color_at(&point_pattern))
+          .sum();
+        sum * (1. / self.textures.len() as Float)
+      }
+    };
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return self.world_to_pattern.is_some()
+      && self
+        .textures
+        .iter()
+        .all(|texture| texture.has_invertible_transforms());
   }
 }
 
-pub fn stripes(point: Point, a: Color, b: Color) -> Color {
-  return if point.x.rem_euclid(2.).floor() == 0. {
-    a
+/// Alternates between the textures in unit-wide bands along the x axis.
+pub fn stripes(point: &Point, count: usize) -> Selection {
+  return Selection::Single(band(point.x, count));
+}
+
+/// Like [`stripes`](stripes), but with bands of the given width.
+pub fn stripes_with_width(width: Float) -> impl Fn(&Point, usize) -> Selection + Send + Sync {
+  return move |point, count| Selection::Single(band(point.x / width, count));
+}
+
+/// Interpolates through the textures along the x axis, one unit per texture, then back again.
+pub fn gradient(point: &Point, count: usize) -> Selection {
+  if count < 2 {
+    return Selection::Single(0);
+  }
+
+  let last = count - 1;
+  let position = point.x.abs().rem_euclid(2. * last as Float);
+  let segment = position.floor() as usize;
+  let t = position.fract();
+  return if segment < last {
+    Selection::Mix(segment, segment + 1, t)
   } else {
-    b
+    let segment = segment - last;
+    Selection::Mix(last - segment, last - segment - 1, t)
   };
 }
 
-pub fn gradient(point: Point, a: Color, b: Color) -> Color {
-  if point.x.abs().rem_euclid(2.).floor() == 0. {
-    return a.lerp(&b, &point.x.abs().fract());
-  } else {
-    return b.lerp(&a, &point.x.abs().fract());
-  }
+/// Interpolates through the textures along the x axis, with texture `i` placed at `stops[i]`.
+///
+/// Points before the first stop or after the last stop use the first or last texture.
+pub fn gradient_stops(stops: Vec<Float>) -> impl Fn(&Point, usize) -> Selection + Send + Sync {
+  return move |point, count| {
+    let stops = &stops[..stops.len().min(count)];
+    let after = stops.iter().position(|&stop| point.x < stop);
+    return match after {
+      Some(0) => Selection::Single(0),
+      None => Selection::Single(stops.len().saturating_sub(1)),
+      Some(i) => {
+        let (from, to) = (stops[i - 1], stops[i]);
+        Selection::Mix(i - 1, i, (point.x - from) / (to - from))
+      }
+    };
+  };
 }
 
-pub fn ring(point: Point, a: Color, b: Color) -> Color {
+/// Alternates between the textures in unit-wide rings around the y axis.
+pub fn ring(point: &Point, count: usize) -> Selection {
   let radius = (point.x.powi(2) + point.z.powi(2)).sqrt();
-  if radius.rem_euclid(2.).floor() == 0. {
-    return a;
-  } else {
-    return b;
-  }
+  return Selection::Single(band(radius, count));
 }
 
-pub fn checkers(point: Point, a: Color, b: Color) -> Color {
-  if (point.x.floor() + point.y.floor() + point.z.floor()).rem_euclid(2.) == 0. {
-    return a;
-  } else {
-    return b;
-  }
+/// Alternates between the textures in unit cubes.
+pub fn checkers(point: &Point, count: usize) -> Selection {
+  return Selection::Single(band(
+    point.x.floor() + point.y.floor() + point.z.floor(),
+    count,
+  ));
 }
 
-pub fn blend(_point: Point, a: Color, b: Color) -> Color {
-  return a.blend(&b);
+/// Averages all textures, regardless of the point.
+pub fn blend(_point: &Point, _count: usize) -> Selection {
+  return Selection::Average;
+}
+
+/// Picks a texture by quantizing a scalar field in `0.0..=1.0` into one bucket per texture.
+pub fn palette<F>(field: F) -> impl Fn(&Point, usize) -> Selection + Send + Sync
+where
+  F: Fn(&Point) -> Float + Send + Sync,
+{
+  return move |point, count| {
+    let value = field(point).clamp(0., 1.);
+    let index = (value * count as Float) as usize;
+    return Selection::Single(index.min(count.saturating_sub(1)));
+  };
+}
+
+/// Returns the index of the unit-wide band `position` falls in, cycling through `count` bands.
+fn band(position: Float, count: usize) -> usize {
+  return (position.floor() as i64).rem_euclid(count.max(1) as i64) as usize;
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::utils::consts::PI;
 
-  fn test_fn_pattern(point: Point, a: Color, b: Color) -> Color {
+  fn test_fn_pattern(point: &Point, _count: usize) -> Selection {
     if point.x >= 0. {
-      return a;
+      return Selection::Single(0);
     } else {
-      return b;
+      return Selection::Single(1);
     }
   }
 
+  /// Texture that fails the test if it is ever evaluated.
+  #[derive(Debug)]
+  struct UnreachableTexture;
+  impl Texture for UnreachableTexture {
+    fn color_at(&self, _point: &Point) -> Color {
+      panic!("Texture should not have been evaluated");
+    }
+  }
+
+  fn white_black() -> Vec<Box<dyn Texture>> {
+    return vec![Box::new(Color::white()), Box::new(Color::black())];
+  }
+
+  fn pattern_color(fn_pattern: fn(&Point, usize) -> Selection, point: Point) -> Color {
+    return Pattern::with_fn(fn_pattern).color_at(&point);
+  }
+
   #[test]
   fn init_with_transformation() {
     let pattern = Pattern::with_transformation(
       test_fn_pattern,
-      Color::white(),
-      Color::black(),
+      white_black(),
       Matrix::identity().scale(5., -5., 5.),
     );
-    assert_eq!(pattern.textures(), (&Color::white(), &Color::black()));
+    assert_eq!(pattern.textures().len(), 2);
+    assert_eq!(
+      pattern.textures()[0].color_at(&Point::origin()),
+      Color::white()
+    );
+    assert_eq!(
+      pattern.textures()[1].color_at(&Point::origin()),
+      Color::black()
+    );
     assert_eq!(
       pattern.pattern_to_world(),
       &Matrix::identity().scale(5., -5., 5.)
@@ -133,37 +256,35 @@ mod tests {
   #[test]
   fn init_with_func() {
     let pattern = Pattern::with_fn(test_fn_pattern);
-    assert_eq!(pattern.textures(), (&Color::white(), &Color::black()));
+    assert_eq!(pattern.textures().len(), 2);
+    assert_eq!(
+      pattern.textures()[0].color_at(&Point::origin()),
+      Color::white()
+    );
+    assert_eq!(
+      pattern.textures()[1].color_at(&Point::origin()),
+      Color::black()
+    );
     assert_eq!(pattern.pattern_to_world(), &Matrix::identity());
   }
 
   #[test]
   fn init_new() {
-    let pattern = Pattern::new(test_fn_pattern, Color::cyan(), Color::red());
-    assert_eq!(pattern.textures(), (&Color::cyan(), &Color::red()));
+    let pattern = Pattern::new(
+      test_fn_pattern,
+      vec![Box::new(Color::cyan()), Box::new(Color::red())],
+    );
+    assert_eq!(
+      pattern.textures()[0].color_at(&Point::origin()),
+      Color::cyan()
+    );
+    assert_eq!(
+      pattern.textures()[1].color_at(&Point::origin()),
+      Color::red()
+    );
     assert_eq!(pattern.pattern_to_world(), &Matrix::identity());
   }
 
-  #[test]
-  fn equality_identical() {
-    let color_a = Color::new(0.3, 0.3, 0.6);
-    let color_b = Color::new(0.6, 0.2, 0.1);
-    let a = Pattern::new(test_fn_pattern, color_a, color_b);
-    let b = Pattern::new(test_fn_pattern, color_a, color_b);
-
-    assert_eq!(a, b);
-
-    let b = Pattern::new(test_fn_pattern, color_a, Color::new(0.6000001, 0.2, 0.1));
-    assert_eq!(a, b);
-  }
-
-  #[test]
-  fn equality_different() {
-    let a = Pattern::new(test_fn_pattern, Color::yellow(), Color::cyan());
-    let b = Pattern::new(test_fn_pattern, Color::red(), Color::green());
-    assert_ne!(a, b);
-  }
-
   #[test]
   fn color_at() {
     let pattern = Pattern::with_fn(test_fn_pattern);
@@ -183,8 +304,7 @@ mod tests {
   fn color_at_with_transformation() {
     let pattern = Pattern::with_transformation(
       test_fn_pattern,
-      Color::white(),
-      Color::black(),
+      white_black(),
       Matrix::identity().translate(5., 0., 0.),
     );
 
@@ -199,8 +319,66 @@ mod tests {
     assert_eq!(pattern.color_at(&Point::new(5., 0., 1.)), Color::white());
   }
 
+  #[test]
+  fn singular_transformation_is_black() {
+    let pattern = Pattern::with_transformation(
+      test_fn_pattern,
+      white_black(),
+      Matrix::identity().scale(1., 0., 1.),
+    );
+    assert!(!pattern.has_invertible_transforms());
+    assert_eq!(pattern.color_at(&Point::origin()), Color::black());
+    let nested = Pattern::new(test_fn_pattern, vec![Box::new(pattern)]);
+    assert!(!nested.has_invertible_transforms());
+    assert!(Pattern::new(test_fn_pattern, white_black()).has_invertible_transforms());
+  }
+
+  #[test]
+  fn color_at_only_evaluates_selected_texture() {
+    let pattern = Pattern::new(
+      test_fn_pattern,
+      vec![Box::new(Color::cyan()), Box::new(UnreachableTexture)],
+    );
+    assert_eq!(pattern.color_at(&Point::new(1., 0., 0.)), Color::cyan());
+  }
+
+  #[test]
+  fn color_at_nested_pattern() {
+    let inner = Pattern::with_transformation(
+      stripes,
+      vec![Box::new(Color::red()), Box::new(Color::blue())],
+      Matrix::identity().rotate_y(PI / 2.),
+    );
+    let pattern = Pattern::new(stripes, vec![Box::new(inner), Box::new(Color::green())]);
+    assert_eq!(pattern.color_at(&Point::new(0.5, 0., -0.5)), Color::red());
+    assert_eq!(pattern.color_at(&Point::new(0.5, 0., 0.5)), Color::blue());
+    assert_eq!(pattern.color_at(&Point::new(1.5, 0., 0.5)), Color::green());
+  }
+
+  #[test]
+  fn color_at_closure_captures_parameters() {
+    let threshold = 2.5;
+    let pattern = Pattern::with_fn(move |point: &Point, _count| {
+      Selection::Single(if point.y > threshold { 1 } else { 0 })
+    });
+    assert_eq!(pattern.color_at(&Point::new(0., 2., 0.)), Color::white());
+    assert_eq!(pattern.color_at(&Point::new(0., 3., 0.)), Color::black());
+  }
+
+  #[test]
+  fn color_at_clamps_selected_index() {
+    let pattern = Pattern::with_fn(|_point: &Point, _count| Selection::Single(7));
+    assert_eq!(pattern.color_at(&Point::origin()), Color::black());
+  }
+
+  #[test]
+  fn color_at_without_textures() {
+    let pattern = Pattern::new(stripes, vec![]);
+    assert_eq!(pattern.color_at(&Point::origin()), Color::black());
+  }
+
   fn stripes_default(point: Point) -> Color {
-    return stripes(point, Color::white(), Color::black());
+    return pattern_color(stripes, point);
   }
   #[test]
   fn stripes_alternates_in_x() {
@@ -231,8 +409,25 @@ mod tests {
     assert_eq!(stripes_default(Point::new(0., 0., -1.1)), Color::white());
   }
 
+  #[test]
+  fn stripes_cycles_through_all_textures() {
+    assert_eq!(stripes(&Point::new(0.5, 0., 0.), 3), Selection::Single(0));
+    assert_eq!(stripes(&Point::new(1.5, 0., 0.), 3), Selection::Single(1));
+    assert_eq!(stripes(&Point::new(2.5, 0., 0.), 3), Selection::Single(2));
+    assert_eq!(stripes(&Point::new(3.5, 0., 0.), 3), Selection::Single(0));
+    assert_eq!(stripes(&Point::new(-0.5, 0., 0.), 3), Selection::Single(2));
+  }
+
+  #[test]
+  fn stripes_with_width_scales_bands() {
+    let wide = stripes_with_width(0.5);
+    assert_eq!(wide(&Point::new(0.25, 0., 0.), 2), Selection::Single(0));
+    assert_eq!(wide(&Point::new(0.75, 0., 0.), 2), Selection::Single(1));
+    assert_eq!(wide(&Point::new(1.25, 0., 0.), 2), Selection::Single(0));
+  }
+
   fn gradient_default(point: Point) -> Color {
-    return gradient(point, Color::white(), Color::black());
+    return pattern_color(gradient, point);
   }
   #[test]
   fn gradient_linearly_interpolates_in_x() {
@@ -288,9 +483,68 @@ mod tests {
     assert_eq!(gradient_default(Point::new(0., 0., -1.)), Color::white());
     assert_eq!(gradient_default(Point::new(0., 0., -1.1)), Color::white());
   }
+  #[test]
+  fn gradient_through_many_textures() {
+    assert_eq!(
+      gradient(&Point::new(0.5, 0., 0.), 3),
+      Selection::Mix(0, 1, 0.5)
+    );
+    assert_eq!(
+      gradient(&Point::new(1.25, 0., 0.), 3),
+      Selection::Mix(1, 2, 0.25)
+    );
+    assert_eq!(
+      gradient(&Point::new(2.25, 0., 0.), 3),
+      Selection::Mix(2, 1, 0.25)
+    );
+    assert_eq!(
+      gradient(&Point::new(3.5, 0., 0.), 3),
+      Selection::Mix(1, 0, 0.5)
+    );
+    assert_eq!(gradient(&Point::new(3.5, 0., 0.), 1), Selection::Single(0));
+  }
+
+  #[test]
+  fn gradient_stops_interpolates_between_stops() {
+    let pattern = Pattern::new(
+      gradient_stops(vec![0., 1., 3.]),
+      vec![
+        Box::new(Color::red()),
+        Box::new(Color::green()),
+        Box::new(Color::blue()),
+      ],
+    );
+    assert_eq!(pattern.color_at(&Point::new(-1., 0., 0.)), Color::red());
+    assert_eq!(
+      pattern.color_at(&Point::new(0.5, 0., 0.)),
+      Color::new(0.5, 0.5, 0.)
+    );
+    assert_eq!(pattern.color_at(&Point::new(1., 0., 0.)), Color::green());
+    assert_eq!(
+      pattern.color_at(&Point::new(2.5, 0., 0.)),
+      Color::new(0., 0.25, 0.75)
+    );
+    assert_eq!(pattern.color_at(&Point::new(10., 0., 0.)), Color::blue());
+  }
+
+  #[test]
+  fn gradient_stops_only_evaluates_neighbouring_textures() {
+    let pattern = Pattern::new(
+      gradient_stops(vec![0., 1., 2.]),
+      vec![
+        Box::new(UnreachableTexture),
+        Box::new(Color::green()),
+        Box::new(Color::blue()),
+      ],
+    );
+    assert_eq!(
+      pattern.color_at(&Point::new(1.5, 0., 0.)),
+      Color::new(0., 0.5, 0.5)
+    );
+  }
 
   fn ring_default(point: Point) -> Color {
-    return ring(point, Color::white(), Color::black());
+    return pattern_color(ring, point);
   }
   #[test]
   fn ring_alternates_in_x_and_z() {
@@ -320,7 +574,7 @@ mod tests {
   }
 
   fn checkers_default(point: Point) -> Color {
-    return checkers(point, Color::white(), Color::black());
+    return pattern_color(checkers, point);
   }
   #[test]
   fn checkers_alternates_in_x() {
@@ -342,7 +596,7 @@ mod tests {
   }
 
   fn blend_origin(a: Color, b: Color) -> Color {
-    return blend(Point::origin(), a, b);
+    return Pattern::new(blend, vec![Box::new(a), Box::new(b)]).color_at(&Point::origin());
   }
   #[test]
   fn blend_averages_colors() {
@@ -361,7 +615,7 @@ mod tests {
   }
 
   fn blend_white_black(point: Point) -> Color {
-    return blend(point, Color::white(), Color::black());
+    return pattern_color(blend, point);
   }
   #[test]
   fn blend_independent_of_point() {
@@ -378,4 +632,30 @@ mod tests {
       Color::new(0.5, 0.5, 0.5)
     );
   }
+
+  #[test]
+  fn blend_averages_many_textures() {
+    let pattern = Pattern::new(
+      blend,
+      vec![
+        Box::new(Color::red()),
+        Box::new(Color::green()),
+        Box::new(Color::blue()),
+      ],
+    );
+    assert_eq!(
+      pattern.color_at(&Point::origin()),
+      Color::new(1. / 3., 1. / 3., 1. / 3.)
+    );
+  }
+
+  #[test]
+  fn palette_quantizes_field() {
+    let select = palette(|point: &Point| point.y);
+    assert_eq!(select(&Point::new(0., 0.1, 0.), 4), Selection::Single(0));
+    assert_eq!(select(&Point::new(0., 0.3, 0.), 4), Selection::Single(1));
+    assert_eq!(select(&Point::new(0., 0.99, 0.), 4), Selection::Single(3));
+    assert_eq!(select(&Point::new(0., 1., 0.), 4), Selection::Single(3));
+    assert_eq!(select(&Point::new(0., -5., 0.), 4), Selection::Single(0));
+  }
 }
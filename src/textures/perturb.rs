@@ -0,0 +1,129 @@
+use crate::geometry::{Point, Vector};
+use crate::textures::{Perlin, Texture};
+use crate::utils::Float;
+use crate::Color;
+
+/// Texture that displaces the lookup point of another texture by 3D noise before sampling it.
+///
+/// Wrapping a [`Pattern`](crate::textures::Pattern) turns its straight edges into organic,
+/// wavy ones. The displacement is applied in the space the wrapper receives points in, so a
+/// `Perturb` nested in a pattern is distorted in that pattern's space.
+#[derive(Clone, Debug)]
+pub struct Perturb<T>
+where
+  T: Texture,
+{
+  texture: T,
+  perlin: Perlin,
+  strength: Float,
+  frequency: Float,
+}
+impl<T> Perturb<T>
+where
+  T: Texture,
+{
+  pub fn new(texture: T, seed: u64, strength: Float) -> Self {
+    return Self {
+      texture,
+      perlin: Perlin::new(seed),
+      strength,
+      frequency: 1.,
+    };
+  }
+
+  pub fn with_frequency(mut self, frequency: Float) -> Self {
+    self.frequency = frequency;
+    return self;
+  }
+
+  pub fn texture(&self) -> &T {
+    return &self.texture;
+  }
+  pub fn strength(&self) -> Float {
+    return self.strength;
+  }
+  pub fn frequency(&self) -> Float {
+    return self.frequency;
+  }
+
+  /// Returns the displacement applied to the lookup at `point`.
+  pub fn offset_at(&self, point: &Point) -> Vector {
+    let point = *point * self.frequency;
+    // Sample the noise at distant offsets so the three axes are uncorrelated.
+    let offset = Vector::new(
+      self.perlin.noise(&point),
+      self.perlin.noise(&(point + Vector::new(31.4, 12.7, 5.3))),
+      self.perlin.noise(&(point + Vector::new(-7.9, 23.1, 47.6))),
+    );
+    return offset * self.strength;
+  }
+}
+impl<T> Texture for Perturb<T>
+where
+  T: Texture,
+{
+  fn color_at(&self, point: &Point) -> Color {
+    return self.texture.color_at(&(*point + self.offset_at(point)));
+  }
+  fn has_invertible_transforms(&self) -> bool {
+    return self.texture.has_invertible_transforms();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::geometry::Matrix;
+  use crate::textures::{stripes, Pattern};
+
+  #[test]
+  fn init_new() {
+    let perturb = Perturb::new(Color::red(), 4, 0.25).with_frequency(3.);
+    assert_eq!(perturb.texture(), &Color::red());
+    assert_eq!(perturb.strength(), 0.25);
+    assert_eq!(perturb.frequency(), 3.);
+  }
+
+  #[test]
+  fn zero_strength_leaves_texture_unchanged() {
+    let pattern = Pattern::with_fn(stripes);
+    let perturb = Perturb::new(Pattern::with_fn(stripes), 4, 0.);
+    for i in 0..20 {
+      let point = Point::new(i as Float * 0.3 - 3., 0.5, 0.1);
+      assert_eq!(perturb.color_at(&point), pattern.color_at(&point));
+    }
+  }
+
+  #[test]
+  fn offset_is_bounded_by_strength() {
+    let perturb = Perturb::new(Color::white(), 4, 0.1);
+    for i in 0..100 {
+      let point = Point::new(i as Float * 0.17, i as Float * -0.31, i as Float * 0.07);
+      let offset = perturb.offset_at(&point);
+      assert!(offset.x.abs() <= 0.15 && offset.y.abs() <= 0.15 && offset.z.abs() <= 0.15);
+    }
+  }
+
+  #[test]
+  fn perturbs_pattern_edges() {
+    let perturb = Perturb::new(Pattern::with_fn(stripes), 4, 0.5);
+    let pattern = Pattern::with_fn(stripes);
+    let differs = (0..200).any(|i| {
+      let point = Point::new(i as Float * 0.05, 0.3, i as Float * 0.11);
+      perturb.color_at(&point) != pattern.color_at(&point)
+    });
+    assert!(differs);
+  }
+
+  #[test]
+  fn composes_with_pattern_transformation() {
+    let inner = Perturb::new(Color::white(), 4, 0.);
+    let pattern = Pattern::with_transformation(
+      stripes,
+      vec![Box::new(inner), Box::new(Color::black())],
+      Matrix::identity().translate(1., 0., 0.),
+    );
+    assert_eq!(pattern.color_at(&Point::new(1.5, 0., 0.)), Color::white());
+    assert_eq!(pattern.color_at(&Point::new(0.5, 0., 0.)), Color::black());
+  }
+}
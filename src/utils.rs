@@ -1,12 +1,36 @@
+/// Floating-point type of coordinates, colors and every other computed quantity. `f64` by
+/// default, or `f32` with the `f32` feature.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+// Documented on the `f64` definition.
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// Mathematical constants of [`Float`] precision.
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+/// Mathematical constants of [`Float`] precision.
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
 /// Constant used to accommodate for floating-point rounding error.
-pub const EPSILON: f64 = 0.00001;
-pub fn approx_equals(a: f64, b: f64) -> bool {
-  return (a - b).abs() < EPSILON;
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 0.00001;
+/// Constant used to accommodate for floating-point rounding error. Single precision has about
+/// seven significant digits, so its rounding error is larger.
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 0.0001;
+
+/// Returns whether two numbers differ by less than [`EPSILON`], or, for numbers larger than one,
+/// by less than `EPSILON` relative to their size, since the rounding error grows with them.
+pub fn approx_equals(a: Float, b: Float) -> bool {
+  let difference = (a - b).abs();
+  return a == b || difference < EPSILON || difference < EPSILON * a.abs().max(b.abs());
 }
 #[macro_export]
 macro_rules! assert_ae {
   ($left:expr, $right:expr) => {
-    if !approx_equals($left, $right) {
+    if !$crate::utils::approx_equals($left, $right) {
       panic!(
         "assertion failed: `(left == right)`
 left:  `{}`
@@ -17,7 +41,7 @@ right: `{}`",
   };
 }
 
-pub fn clamp_number(number: f64, min: f64, max: f64) -> f64 {
+pub fn clamp_number(number: Float, min: Float, max: Float) -> Float {
   return if number < min {
     min
   } else if number > max {
@@ -27,7 +51,7 @@ pub fn clamp_number(number: f64, min: f64, max: f64) -> f64 {
   };
 }
 
-pub fn quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+pub fn quadratic(a: Float, b: Float, c: Float) -> Option<(Float, Float)> {
   let discriminant = b.powi(2) - 4. * a * c;
   if discriminant < 0. {
     return None;
@@ -48,18 +72,68 @@ pub trait Lerp {
 
   fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self;
 }
-impl Lerp for f64 {
-  type Scalar = f64;
+impl Lerp for Float {
+  type Scalar = Float;
 
   fn lerp(&self, other: &Self, t: &Self::Scalar) -> Self {
     return self * (1. - t) + other * t;
   }
 }
 
+/// Returns the point at `index` of the R2 low-discrepancy sequence in the unit square.
+///
+/// Consecutive points fill the square evenly for any count, unlike a grid, which needs a square
+/// number of points.
+pub fn r2_sequence(index: usize) -> (Float, Float) {
+  // The plastic number, the unique real solution of x^3 = x + 1.
+  #[cfg_attr(feature = "f32", allow(clippy::excessive_precision))]
+  const G: Float = 1.324_717_957_244_746;
+  let n = index as Float;
+  return ((0.5 + n / G).fract(), (0.5 + n / (G * G)).fract());
+}
+
+/// Small deterministic pseudo-random number generator (SplitMix64).
+///
+/// Not suitable for cryptography, but fast and reproducible across platforms for a given seed.
+#[derive(Clone, Debug)]
+pub struct Rng {
+  state: u64,
+}
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    return Self { state: seed };
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    return z ^ (z >> 31);
+  }
+
+  /// Returns a number in `0.0..1.0`.
+  pub fn next_f64(&mut self) -> f64 {
+    return (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+  }
+
+  /// Returns a number in `0.0..1.0` of [`Float`] precision.
+  pub fn next_float(&mut self) -> Float {
+    // Using as many bits as the mantissa holds makes every result exactly representable.
+    let bits = Float::MANTISSA_DIGITS;
+    return (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float;
+  }
+
+  /// Returns an integer in `0..bound`.
+  pub fn below(&mut self, bound: usize) -> usize {
+    return (self.next_u64() % bound as u64) as usize;
+  }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
   use super::*;
-  use crate::assert_ae;
 
   #[test]
   fn approx_equals_difference_less_than_epsilon() {
@@ -68,7 +142,15 @@ mod tests {
 
   #[test]
   fn approx_equals_difference_larger_than_epsilon() {
-    assert_eq!(approx_equals(0.00001, 0.00002), false);
+    assert_eq!(approx_equals(EPSILON, 2. * EPSILON), false);
+  }
+
+  #[test]
+  fn approx_equals_is_relative_for_large_numbers() {
+    assert!(approx_equals(1e6, 1e6 * (1. + EPSILON / 2.)));
+    assert!(!approx_equals(1e6, 1e6 * (1. + 2. * EPSILON)));
+    assert!(approx_equals(Float::INFINITY, Float::INFINITY));
+    assert!(!approx_equals(Float::NAN, Float::NAN));
   }
 
   #[test]
@@ -119,6 +201,7 @@ mod tests {
   }
 
   #[test]
+  #[cfg_attr(feature = "f32", allow(clippy::excessive_precision))]
   fn quadratic_large_floating_point_numbers() {
     let roots = quadratic(-299.45, 392348.998, 889.000987);
     let (x1, x2) = roots.expect("Expected roots");
@@ -127,13 +210,54 @@ mod tests {
   }
 
   #[test]
-  fn lerp_f64() {
-    assert_eq!(20_f64.lerp(&80., &0.), 20.);
-    assert_eq!(20_f64.lerp(&80., &1.), 80.);
-    assert_eq!(20_f64.lerp(&80., &0.5), 50.);
-    assert_eq!(80_f64.lerp(&20., &0.75), 35.);
-    assert_eq!((-1_f64).lerp(&1., &0.25), -0.5);
-    assert_eq!((-1_f64).lerp(&1., &0.5), 0.);
-    assert_eq!((-1_f64).lerp(&1., &0.75), 0.5);
+  fn lerp_float() {
+    assert_eq!((20. as Float).lerp(&80., &0.), 20.);
+    assert_eq!((20. as Float).lerp(&80., &1.), 80.);
+    assert_eq!((20. as Float).lerp(&80., &0.5), 50.);
+    assert_eq!((80. as Float).lerp(&20., &0.75), 35.);
+    assert_eq!((-1. as Float).lerp(&1., &0.25), -0.5);
+    assert_eq!((-1. as Float).lerp(&1., &0.5), 0.);
+    assert_eq!((-1. as Float).lerp(&1., &0.75), 0.5);
+  }
+
+  #[test]
+  fn rng_is_deterministic() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    for _ in 0..10 {
+      assert_eq!(a.next_u64(), b.next_u64());
+    }
+  }
+
+  #[test]
+  fn rng_seeds_differ() {
+    assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+  }
+
+  #[test]
+  fn rng_ranges() {
+    let mut rng = Rng::new(7);
+    for _ in 0..1000 {
+      let float = rng.next_float();
+      assert!((0. ..1.).contains(&float));
+      assert!((0. ..1.).contains(&rng.next_f64()));
+      assert!(rng.below(5) < 5);
+    }
+  }
+
+  #[test]
+  fn r2_sequence_fills_unit_square() {
+    let points: Vec<(Float, Float)> = (0..64).map(r2_sequence).collect();
+    assert!(points
+      .iter()
+      .all(|&(x, y)| (0. ..1.).contains(&x) && (0. ..1.).contains(&y)));
+    // Every cell of a 4x4 grid gets close to its share of 4 points.
+    for cell in 0..16 {
+      let count = points
+        .iter()
+        .filter(|(x, y)| (x * 4.) as usize + 4 * (y * 4.) as usize == cell)
+        .count();
+      assert!((2..=6).contains(&count));
+    }
   }
 }
@@ -0,0 +1,136 @@
+//! Golden-image tests: renders the reference scenes in `tests/golden` and compares them with the
+//! images checked in next to them, so changes to shading or cameras that alter whole frames are
+//! caught.
+//!
+//! References are stored as 16-bit PNG files with linear colors. When a render changes on
+//! purpose, run `SORB_BLESS=1 cargo test --test golden` to overwrite them, and look at the new
+//! images before committing. When a comparison fails, the render and an image highlighting the
+//! differing pixels are written to the test directory under `target`.
+
+#![allow(clippy::needless_return)]
+
+use sorb_rust::formats::read_image;
+use sorb_rust::prelude::*;
+use sorb_rust::render::Comparison;
+use sorb_rust::utils::Float;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Largest difference of a channel of a single pixel, one step of an 8-bit image.
+const PIXEL_TOLERANCE: Float = 1. / 255.;
+/// Largest root-mean-square difference over the whole image.
+const RMSE_TOLERANCE: Float = 1e-3;
+/// Environment variable that overwrites the references with the current renders when set.
+const BLESS_VARIABLE: &str = "SORB_BLESS";
+
+fn golden_directory() -> PathBuf {
+  return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+}
+
+fn encode(canvas: &Canvas) -> Vec<u8> {
+  let mut data = Vec::new();
+  canvas
+    .write_png(&mut data, PngBitDepth::Sixteen, &DisplayTransform::linear())
+    .expect("Writing to memory cannot fail");
+  return data;
+}
+
+fn write_png(canvas: &Canvas, path: &Path, display: &DisplayTransform) {
+  let mut data = Vec::new();
+  canvas
+    .write_png(&mut data, PngBitDepth::Eight, display)
+    .expect("Writing to memory cannot fail");
+  fs::write(path, data).unwrap();
+}
+
+/// Compares a render with the reference image `name.png`, or overwrites the reference when
+/// blessing.
+fn assert_matches_reference(name: &str, canvas: &Canvas) {
+  let reference_path = golden_directory().join(format!("{}.png", name));
+  let encoded = encode(canvas);
+  if env::var_os(BLESS_VARIABLE).is_some() {
+    fs::write(&reference_path, &encoded).unwrap();
+    return;
+  }
+
+  let reference = match fs::read(&reference_path) {
+    Ok(data) => read_image(&data).unwrap(),
+    Err(error) => panic!(
+      "Cannot read reference {}: {}. Run with {}=1 to create it.",
+      reference_path.display(),
+      error,
+      BLESS_VARIABLE
+    ),
+  };
+  // Comparing the stored form of the render quantizes and clamps it like the reference.
+  let actual = read_image(&encoded).unwrap();
+  assert_eq!(
+    (actual.width(), actual.height()),
+    (reference.width(), reference.height()),
+    "Render of `{}` has a different size than its reference",
+    name
+  );
+
+  let comparison = Comparison::new(&actual, &reference, PIXEL_TOLERANCE);
+  if comparison.is_match() && comparison.rmse() <= RMSE_TOLERANCE {
+    return;
+  }
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+  fs::create_dir_all(&output).unwrap();
+  let actual_path = output.join(format!("{}.actual.png", name));
+  let diff_path = output.join(format!("{}.diff.png", name));
+  write_png(&actual, &actual_path, &DisplayTransform::default());
+  write_png(comparison.diff(), &diff_path, &DisplayTransform::linear());
+  panic!(
+    "Render of `{}` differs from its reference: {} pixels differ by more than {:.4}, \
+     max delta {:.4}, RMSE {:.5} (tolerance {:.5}).\nRender: {}\nDiff: {}\n\
+     Run with {}=1 if the change is intended.",
+    name,
+    comparison.differing_pixels(),
+    PIXEL_TOLERANCE,
+    comparison.max_delta(),
+    comparison.rmse(),
+    RMSE_TOLERANCE,
+    actual_path.display(),
+    diff_path.display(),
+    BLESS_VARIABLE
+  );
+}
+
+fn render_scene(path: &Path, width: Option<usize>, samples: usize) -> Canvas {
+  let (camera, world) = Scene::open(path).unwrap().into_parts();
+  let camera = match width {
+    Some(width) => {
+      let height = (width as Float / camera.aspect_ratio()).round() as usize;
+      Camera::new(width, height, camera.fov(), *camera.camera_to_world())
+    }
+    None => camera,
+  };
+  return camera.with_samples(samples).render(&world);
+}
+
+#[test]
+fn golden_shading() {
+  let canvas = render_scene(&golden_directory().join("shading.scene"), None, 1);
+  assert_matches_reference("shading", &canvas);
+}
+
+#[test]
+fn golden_patterns() {
+  let canvas = render_scene(&golden_directory().join("patterns.scene"), None, 1);
+  assert_matches_reference("patterns", &canvas);
+}
+
+#[test]
+fn golden_antialiasing() {
+  let canvas = render_scene(&golden_directory().join("shading.scene"), None, 4);
+  assert_matches_reference("antialiasing", &canvas);
+}
+
+#[test]
+fn golden_corner() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/corner.scene");
+  let canvas = render_scene(&path, Some(80), 1);
+  assert_matches_reference("corner", &canvas);
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::needless_return)]
+
+use sorb_rust::formats::read_image;
+use sorb_rust::prelude::*;
+use sorb_rust::render::TransferFunction;
+
+fn sphere_world() -> World {
+  return World::new(
+    vec![Box::new(Sphere::new(
+      Material::new(Box::new(Color::new(0.8, 1., 0.6)), 0.1, 0.7, 0.2, 200.),
+      Matrix::identity().translate(0., 0., -3.),
+    ))],
+    vec![PointLight::new(Point::new(-10., 10., 10.), Color::white())],
+  );
+}
+
+#[test]
+fn render_sphere() {
+  let world = sphere_world();
+  let camera = Camera::new(11, 11, 90., Matrix::identity());
+  let canvas = camera.render(&world);
+  assert_eq!((canvas.width(), canvas.height()), (11, 11));
+  assert_eq!(canvas.pixel(0, 0), &Color::black());
+  assert_eq!(
+    canvas.pixel(5, 5),
+    &world.color_at(&camera.ray_for_pixel(5, 5))
+  );
+  assert!(canvas.pixel(5, 5).g > 0.1);
+}
+
+#[test]
+fn render_does_not_depend_on_threads() {
+  let world = sphere_world();
+  let camera = Camera::new(24, 16, 60., Matrix::identity()).with_samples(4);
+  let single = camera.render(&world);
+  let parallel = camera.render_parallel(&world, 4, |_| {});
+  assert_eq!(single.pixels(), parallel.pixels());
+}
+
+#[test]
+fn render_to_png_and_back() {
+  let canvas = Camera::new(16, 12, 60., Matrix::identity()).render(&sphere_world());
+  let mut data = Vec::new();
+  let linear = DisplayTransform::default().with_transfer(TransferFunction::Linear);
+  canvas
+    .write_png(&mut data, PngBitDepth::Sixteen, &linear)
+    .unwrap();
+
+  let decoded = read_image(&data).unwrap();
+  assert_eq!((decoded.width(), decoded.height()), (16, 12));
+  for (decoded, original) in decoded.pixels().iter().zip(canvas.pixels()) {
+    assert!((decoded.r - original.r.min(1.)).abs() < 1e-4);
+    assert!((decoded.g - original.g.min(1.)).abs() < 1e-4);
+    assert!((decoded.b - original.b.min(1.)).abs() < 1e-4);
+  }
+}
@@ -0,0 +1,44 @@
+#![allow(clippy::needless_return)]
+
+use sorb_rust::prelude::*;
+use std::path::Path;
+
+#[test]
+fn render_scene_description() {
+  let scene = Scene::parse(
+    "camera { size 20 10; fov 60; from 0 0 -5; to 0 0 0; up 0 1 0 }
+     light { position -10 10 -10; color 1 1 1 }
+     sphere { material { color 1 0 0; specular 0 } }",
+  )
+  .unwrap();
+  let (camera, world) = scene.into_parts();
+  let canvas = camera.render(&world);
+  assert_eq!((canvas.width(), canvas.height()), (20, 10));
+
+  let center = canvas.pixel(10, 5);
+  assert!(center.r > 0.5);
+  assert_eq!((center.g, center.b), (0., 0.));
+  assert_eq!(canvas.pixel(0, 0), &Color::black());
+}
+
+#[test]
+fn render_example_scene() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/corner.scene");
+  let (camera, world) = Scene::open(path).unwrap().into_parts();
+  let camera = Camera::new(32, 24, camera.fov(), *camera.camera_to_world());
+  let canvas = camera.render_parallel(&world, 2, |_| {});
+  let lit = canvas
+    .pixels()
+    .iter()
+    .filter(|color| **color != Color::black())
+    .count();
+  assert_eq!(lit, 32 * 24);
+}
+
+#[test]
+fn invalid_scene_reports_position() {
+  let error = Scene::parse("camera { size 20 10 }\ncube { }")
+    .err()
+    .unwrap();
+  assert!(matches!(error, SceneError::Invalid { line: 2, .. }));
+}